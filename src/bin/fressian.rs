@@ -0,0 +1,156 @@
+//! Look at fressian from the command line. Built with the `cli` feature:
+//!
+//!     cargo run --features cli --bin fressian -- dump data.fress
+
+extern crate serde_fressian;
+
+use std::env;
+use std::fs;
+use std::io::{self, Read, Write};
+use std::process;
+
+use serde_fressian::de;
+use serde_fressian::error::Error;
+use serde_fressian::inspect;
+use serde_fressian::lazy;
+use serde_fressian::ser;
+use serde_fressian::value::{self, PathSegment, Value};
+
+const USAGE: &str = "usage: fressian <command> <file> [path..]
+
+commands:
+    dump <file>        print every value as EDN
+    disasm <file>      print every byte in hex, with what it encodes
+    from-edn <file>    encode an EDN form, the bytes are written to stdout
+    validate <file>    check that every value and footer reads cleanly
+    stats <file>       count values and bytes by type, and cache references
+    get <file> path..  print what a path of keys and indexes leads to in the
+                       first value, reading nothing else. :users 0 name
+
+<file> can be - to read stdin";
+
+fn main() {
+    let args: Vec<String> = env::args().skip(1).collect();
+    let command = args.get(0).map(String::as_str);
+    let path = args.get(1).map(String::as_str);
+    let res = match (command, path) {
+        (Some("dump"), Some(path)) => dump(path),
+        (Some("disasm"), Some(path)) => disasm(path),
+        (Some("from-edn"), Some(path)) => from_edn(path),
+        (Some("validate"), Some(path)) => validate(path),
+        (Some("stats"), Some(path)) => stats(path),
+        (Some("get"), Some(path)) => get(path, &args[2..]),
+        _ => {
+            eprintln!("{}", USAGE);
+            process::exit(2)
+        }
+    };
+    if let Err(msg) = res {
+        eprintln!("fressian: {}", msg);
+        process::exit(1)
+    }
+}
+
+fn read_input(path: &str) -> Result<Vec<u8>, String> {
+    let mut bytes = Vec::new();
+    let res = if path == "-" {
+        io::stdin().read_to_end(&mut bytes).map(|_| ())
+    } else {
+        fs::File::open(path).and_then(|mut file| file.read_to_end(&mut bytes).map(|_| ()))
+    };
+    res.map(|_| bytes).map_err(|err| format!("{}: {}", path, err))
+}
+
+fn describe(err: &Error, offset: usize) -> String {
+    format!("{} at byte {}", err.err.code, offset + err.err.position)
+}
+
+/// every top level value. A footer ends the values that share a cache, so
+/// reading starts over after each one
+fn read_values(bytes: &[u8]) -> Result<Vec<Value>, String> {
+    let mut values = Vec::new();
+    let mut offset = 0;
+    while offset < bytes.len() {
+        let mut stream = de::Deserializer::from_bytes(&bytes[offset..]).into_iter::<Value>();
+        for value in &mut stream {
+            values.push(value.map_err(|err| describe(&err, offset))?);
+        }
+        match stream.byte_offset() {
+            0 => return Err(format!("no value at byte {}", offset)),
+            read => offset += read
+        }
+    }
+    Ok(values)
+}
+
+fn dump(path: &str) -> Result<(), String> {
+    let bytes = read_input(path)?;
+    let stdout = io::stdout();
+    let mut out = stdout.lock();
+    for value in read_values(&bytes)? {
+        writeln!(out, "{:#}", value).map_err(|err| err.to_string())?;
+    }
+    Ok(())
+}
+
+fn disasm(path: &str) -> Result<(), String> {
+    let bytes = read_input(path)?;
+    let annotated = inspect::disassemble(&bytes);
+    io::stdout().write_all(inspect::render(&annotated).as_bytes()).map_err(|err| err.to_string())
+}
+
+fn from_edn(path: &str) -> Result<(), String> {
+    let bytes = read_input(path)?;
+    let text = String::from_utf8(bytes).map_err(|err| format!("{}: {}", path, err))?;
+    let value = value::from_edn(&text).map_err(|err| describe(&err, 0))?;
+    let bytes = ser::to_vec(&value).map_err(|err| describe(&err, 0))?;
+    io::stdout().write_all(&bytes).map_err(|err| err.to_string())
+}
+
+fn validate(path: &str) -> Result<(), String> {
+    let bytes = read_input(path)?;
+    let summary = inspect::validate(&bytes).map_err(|err| describe(&err, 0))?;
+    read_values(&bytes)?;
+    println!("ok: {} values, {} objects, {} footers, {} bytes",
+             summary.values, summary.objects, summary.footers, summary.bytes);
+    Ok(())
+}
+
+fn stats(path: &str) -> Result<(), String> {
+    let bytes = read_input(path)?;
+    let stats = inspect::stats(&bytes).map_err(|err| describe(&err, 0))?;
+    println!("{} values, {} footers, {} bytes", stats.values, stats.footers, stats.bytes);
+    println!();
+    println!("{:<20}{:>10}{:>12}{:>9}", "type", "count", "bytes", "share");
+    let mut types: Vec<_> = stats.types.iter().collect();
+    types.sort_by(|a, b| b.1.bytes.cmp(&a.1.bytes).then(a.0.cmp(b.0)));
+    for (name, t) in types {
+        let share = 100.0 * t.bytes as f64 / stats.bytes.max(1) as f64;
+        println!("{:<20}{:>10}{:>12}{:>8.1}%", name, t.count, t.bytes, share);
+    }
+    if 0 < stats.cache_puts {
+        println!();
+        println!("{} cache puts, {} references", stats.cache_puts, stats.cache_hits.values().sum::<usize>());
+        if !stats.cache_hits.is_empty() {
+            println!("{:<20}{:>10}", "cache index", "refs");
+            for (index, hits) in &stats.cache_hits {
+                println!("{:<20}{:>10}", index, hits);
+            }
+        }
+    }
+    Ok(())
+}
+
+fn get(path: &str, keys: &[String]) -> Result<(), String> {
+    let bytes = read_input(path)?;
+    let segments: Vec<PathSegment> = keys.iter()
+        .map(|k| k.parse::<usize>().map(PathSegment::Index).unwrap_or_else(|_| PathSegment::Key(k)))
+        .collect();
+    match lazy::get_in(&bytes, &segments).map_err(|err| describe(&err, 0))? {
+        Some(value) => {
+            println!("{:#}", value);
+            Ok(())
+        }
+        None => Err(format!("nothing at {}", keys.join(" ")))
+    }
+}
@@ -4,35 +4,77 @@ use serde::de::{
 };
 
 use crate::error::{Error, ErrorCode, Result};
-use crate::imp::io::{ByteReader};
+use crate::imp::io::{ByteReader, IReadBytes, Reference};
+#[cfg(feature = "std")]
+use crate::imp::io::IoReader;
 use crate::imp::rawinput::{RawInput};
 use crate::imp::codes;
+use crate::imp::walk::MAX_DEPTH;
 use crate::value::{Value};
 
-pub struct Deserializer<'de>{
-    rdr: ByteReader<'de>,
+use core::marker::PhantomData;
+use core::str;
+#[cfg(feature = "std")]
+use std::io;
+
+use crate::lib::*;
+
+pub struct Deserializer<'de, R = ByteReader<'de>>{
+    rdr: R,
+    marker: PhantomData<&'de ()>,
     cache_next: bool,
-    priority_cache: Vec<Value>
+    priority_cache: Vec<Value>,
+    /// how deeply skip_value has nested
+    skip_depth: usize
 }
 
-fn error<T>(de: &Deserializer, reason: ErrorCode) -> Result<T>
+fn error<'de, R: IReadBytes<'de>, T>(de: &Deserializer<'de, R>, reason: ErrorCode) -> Result<T>
 {
     let position: usize = de.rdr.get_bytes_read();
     Err(Error::syntax(reason, position))
 }
 
-impl<'de> Deserializer<'de>
+impl<'de> Deserializer<'de, ByteReader<'de>>
 {
     pub fn from_bytes(bytes: &'de [u8]) -> Self {
+        Deserializer::with_cache(bytes, Vec::<Value>::new())
+    }
+
+    pub fn from_vec(v: &'de Vec<u8>) -> Self {
+        Deserializer::from_bytes(v.as_slice())
+    }
+
+    /// create a deserializer whose priority cache is seeded with the given values,
+    /// such as a cache carried over from previous messages on the same stream
+    pub fn with_cache(bytes: &'de [u8], cache: Vec<Value>) -> Self {
+        Deserializer::new(ByteReader::new(bytes), cache)
+    }
+}
+
+#[cfg(feature = "std")]
+impl<'de, R: io::Read> Deserializer<'de, IoReader<R>>
+{
+    /// create a deserializer that reads incrementally from an io::Read
+    pub fn from_reader(rdr: R) -> Self {
+        Deserializer::new(IoReader::new(rdr), Vec::<Value>::new())
+    }
+}
+
+impl<'de, R: IReadBytes<'de>> Deserializer<'de, R>
+{
+    fn new(rdr: R, cache: Vec<Value>) -> Self {
         Deserializer {
-            rdr: ByteReader::new(bytes),
+            rdr,
+            marker: PhantomData,
             cache_next: false,
-            priority_cache: Vec::<Value>::new()
+            priority_cache: cache,
+            skip_depth: 0
         }
     }
 
-    pub fn from_vec(v: &'de Vec<u8>) -> Self {
-        Deserializer::from_bytes(v.as_slice())
+    /// take ownership of the deserializer's priority cache
+    pub fn into_cache(self) -> Vec<Value> {
+        self.priority_cache
     }
 
     /// abstract out as fressian reader
@@ -40,10 +82,71 @@ impl<'de> Deserializer<'de>
         RawInput.read_next_code(&mut self.rdr)
     }
 
-    fn peek_next_code(&mut self) -> Result<i8> {
+    pub(crate) fn peek_next_code(&mut self) -> Result<i8> {
         RawInput.peek_next_code(&mut self.rdr)
     }
 
+    /// read the code of the next value, going through the cache codes that
+    /// can come before it. Only strings are expected to be cached here
+    pub(crate) fn read_value_code(&mut self) -> Result<u8> {
+        match self.read_cache_codes()? {
+            Some(_) => error(self, ErrorCode::UnsupportedCacheType),
+            None => Ok(self.read_next_code()? as u8)
+        }
+    }
+
+    /// Read the cache codes in front of the next value. The value is returned
+    /// when it is a cache reference, or was put in the cache and so has
+    /// already been read. None leaves the reader at the value's own code
+    pub(crate) fn read_cache_codes(&mut self) -> Result<Option<Value>> {
+        loop {
+            match self.peek_next_code()? as u8 {
+                codes::PUT_PRIORITY_CACHE => {
+                    self.read_next_code()?;
+                    if let Some(value) = self.put_value()? {
+                        return Ok(Some(value))
+                    }
+                }
+                codes::RESET_CACHES => {
+                    self.read_next_code()?;
+                    self.priority_cache.clear();
+                }
+                code @ codes::PRIORITY_CACHE_PACKED_START..=159 => {
+                    self.read_next_code()?;
+                    let index = code - codes::PRIORITY_CACHE_PACKED_START;
+                    return cached_value(self, index as usize).map(Some)
+                }
+                codes::GET_PRIORITY_CACHE => {
+                    self.read_next_code()?;
+                    let index = RawInput.read_int(&mut self.rdr)?;
+                    return cached_value(self, index as usize).map(Some)
+                }
+                _ => return Ok(None)
+            }
+        }
+    }
+
+    /// PUT_PRIORITY_CACHE has been read. A string is cached as it is read, so
+    /// this only flags it. Anything else is read whole as a Value and
+    /// returned. Its slot is taken before reading it, so that puts nested
+    /// inside get the later indexes, as writers number them
+    fn put_value(&mut self) -> Result<Option<Value>> {
+        if is_string_code(self.peek_next_code()? as u8) {
+            self.cache_next = true;
+            return Ok(None)
+        }
+        let index = self.priority_cache.len();
+        self.add_priority_cache(Value::NULL);
+        let value = Value::deserialize(&mut *self)?;
+        self.priority_cache[index] = value.clone();
+        Ok(Some(value))
+    }
+
+    /// a length or count, negative ones are an error
+    pub(crate) fn read_count(&mut self) -> Result<usize> {
+        Ok(RawInput.read_count(&mut self.rdr)? as usize)
+    }
+
     fn add_priority_cache(&mut self, value: Value) {
         self.priority_cache.push(value)
     }
@@ -56,38 +159,329 @@ impl<'de> Deserializer<'de>
         self.rdr.reset();
         self.priority_cache.clear();
     }
+
+    /// Check that all input has been consumed, apart from an optional footer.
+    /// `from_bytes` calls this after reading its value
+    pub fn end(&mut self) -> Result<()> {
+        match self.rdr.peek_u8() {
+            Err(ref err) if err.is_eof() => Ok(()),
+            Err(err) => Err(err),
+            Ok(codes::FOOTER) => {
+                self.read_footer()?;
+                match self.rdr.peek_u8() {
+                    Err(ref err) if err.is_eof() => Ok(()),
+                    Err(err) => Err(err),
+                    Ok(_) => error(self, ErrorCode::TrailingBytes)
+                }
+            }
+            Ok(_) => error(self, ErrorCode::TrailingBytes)
+        }
+    }
+
+    /// Move past the next value without building it. Lengths are read and
+    /// skipped, except for strings put in the priority cache which are read
+    /// and cached like `deserialize_any` would, so that later references to
+    /// them still resolve. Nesting stops at the Walker's MAX_DEPTH, so that
+    /// input cannot exhaust the stack
+    pub(crate) fn skip_value(&mut self) -> Result<()> {
+        if MAX_DEPTH <= self.skip_depth {
+            return error(self, ErrorCode::DepthLimitExceeded)
+        }
+        self.skip_depth += 1;
+        let skipped = self.skip_next();
+        self.skip_depth -= 1;
+        skipped
+    }
+
+    fn skip_next(&mut self) -> Result<()> {
+        let code = self.read_next_code()? as u8;
+        match code {
+            codes::NULL | codes::TRUE | codes::FALSE | codes::DOUBLE_0 | codes::DOUBLE_1 => Ok(()),
+
+            codes::INT | 0x00..=0x7f | 0xFF => {
+                RawInput.read_int_code(&mut self.rdr, code as i8).map(|_| ())
+            }
+
+            codes::FLOAT => self.rdr.skip_bytes(4),
+
+            codes::DOUBLE => self.rdr.skip_bytes(8),
+
+            codes::BYTES_PACKED_LENGTH_START..=215 => {
+                self.rdr.skip_bytes((code - codes::BYTES_PACKED_LENGTH_START) as usize)
+            }
+
+            codes::BYTES => {
+                let length = self.read_count()?;
+                self.rdr.skip_bytes(length)
+            }
+
+            codes::STRING_PACKED_LENGTH_START..=225 => {
+                self.skip_str_payload((code - codes::STRING_PACKED_LENGTH_START) as usize, false)
+            }
+
+            codes::STRING => {
+                let length = self.read_count()?;
+                self.skip_str_payload(length, false)
+            }
+
+            codes::UTF8 => {
+                let length = self.read_count()?;
+                self.skip_str_payload(length, true)
+            }
+
+            codes::LIST_PACKED_LENGTH_START..=235 => {
+                self.skip_values((code - codes::LIST_PACKED_LENGTH_START) as usize)
+            }
+
+            codes::LIST
+            | codes::INT_ARRAY
+            | codes::LONG_ARRAY
+            | codes::FLOAT_ARRAY
+            | codes::DOUBLE_ARRAY
+            | codes::BOOLEAN_ARRAY => {
+                let length = self.read_count()?;
+                self.skip_values(length)
+            }
+
+            codes::BEGIN_CLOSED_LIST => {
+                while self.peek_next_code()? as u8 != codes::END_COLLECTION {
+                    self.skip_value()?;
+                }
+                self.read_next_code().map(|_| ())
+            }
+
+            codes::BEGIN_OPEN_LIST => {
+                loop {
+                    match self.peek_next_code() {
+                        Err(ref err) if err.is_eof() => return Ok(()),
+                        Err(err) => return Err(err),
+                        Ok(code) if code as u8 == codes::END_COLLECTION => {
+                            return self.read_next_code().map(|_| ())
+                        }
+                        Ok(_) => self.skip_value()?
+                    }
+                }
+            }
+
+            codes::MAP | codes::SET | codes::INST | codes::UUID | codes::URI | codes::REGEX => {
+                self.skip_value()
+            }
+
+            codes::KEY | codes::SYM => self.skip_values(2),
+
+            codes::PUT_PRIORITY_CACHE => match self.put_value()? {
+                Some(_) => Ok(()),
+                None => self.skip_value()
+            },
+
+            codes::PRIORITY_CACHE_PACKED_START..=159 => {
+                self.skip_cached((code - codes::PRIORITY_CACHE_PACKED_START) as usize)
+            }
+
+            codes::GET_PRIORITY_CACHE => {
+                let index = RawInput.read_int(&mut self.rdr)?;
+                self.skip_cached(index as usize)
+            }
+
+            codes::RESET_CACHES => {
+                self.priority_cache.clear();
+                self.skip_value()
+            }
+
+            // chunks are not read yet, but they can be skipped unless a
+            // string is to be cached
+            codes::BYTES_CHUNK => self.skip_chunks(codes::BYTES_CHUNK, codes::BYTES),
+
+            codes::STRING_CHUNK if !self.cache_next => self.skip_chunks(codes::STRING_CHUNK, codes::STRING),
+
+            codes::STRING_CHUNK | codes::OBJECT_ARRAY => {
+                error(self, ErrorCode::UnsupportedType)
+            }
+
+            _ => error(self, ErrorCode::UnmatchedCode(code)),
+        }
+    }
+
+    fn skip_values(&mut self, count: usize) -> Result<()> {
+        for _ in 0..count {
+            self.skip_value()?;
+        }
+        Ok(())
+    }
+
+    fn skip_chunks(&mut self, chunk: u8, last: u8) -> Result<()> {
+        let mut code = chunk;
+        while code == chunk {
+            let length = self.read_count()?;
+            self.rdr.skip_bytes(length)?;
+            code = self.read_next_code()? as u8;
+        }
+        if code == last {
+            let length = self.read_count()?;
+            self.rdr.skip_bytes(length)
+        } else {
+            error(self, ErrorCode::ExpectedChunkBytesConclusion)
+        }
+    }
+
+    fn skip_str_payload(&mut self, length: usize, utf8: bool) -> Result<()> {
+        if self.cache_next {
+            visit_str_payload(self, length, utf8, de::IgnoredAny).map(|_| ())
+        } else {
+            self.rdr.skip_bytes(length)
+        }
+    }
+
+    fn skip_cached(&mut self, index: usize) -> Result<()> {
+        if self.priority_cache.len() <= index {
+            let position = self.rdr.get_bytes_read();
+            Err(Error::syntax(ErrorCode::UnknownCacheRef(index), position))
+        } else {
+            Ok(())
+        }
+    }
+
+    /// the number of bytes read so far
+    pub fn byte_offset(&self) -> usize {
+        self.rdr.get_bytes_read()
+    }
+
+    /// Iterate over the top level values of a stream. Iteration stops at the
+    /// end of input or at a footer, and the priority cache is shared by all values.
+    #[allow(clippy::should_implement_trait)]
+    pub fn into_iter<T>(self) -> StreamDeserializer<'de, R, T>
+        where T: Deserialize<'de>,
+    {
+        StreamDeserializer {
+            de: self,
+            offset: 0,
+            finished: false,
+            output: PhantomData
+        }
+    }
+
+    /// read and check a footer. The length must match the bytes read before it
+    fn read_footer(&mut self) -> Result<()> {
+        let position = self.rdr.get_bytes_read();
+        let (length, _checksum) = RawInput.read_footer(&mut self.rdr)?;
+        if length as usize != position {
+            Err(Error::syntax(ErrorCode::InvalidFooter, position))
+        } else {
+            Ok(())
+        }
+    }
+}
+
+/// Iterator over the top level values in a stream, see `Deserializer::into_iter`
+pub struct StreamDeserializer<'de, R, T> {
+    de: Deserializer<'de, R>,
+    offset: usize,
+    finished: bool,
+    output: PhantomData<T>
 }
 
+impl<'de, R, T> StreamDeserializer<'de, R, T>
+    where R: IReadBytes<'de>,
+          T: Deserialize<'de>,
+{
+    /// the number of bytes consumed by the values read so far. After an error
+    /// this is where the value that failed began
+    pub fn byte_offset(&self) -> usize {
+        self.offset
+    }
+
+    pub fn into_inner(self) -> Deserializer<'de, R> {
+        self.de
+    }
+}
+
+impl<'de, R, T> Iterator for StreamDeserializer<'de, R, T>
+    where R: IReadBytes<'de>,
+          T: Deserialize<'de>,
+{
+    type Item = Result<T>;
+
+    fn next(&mut self) -> Option<Result<T>> {
+        if self.finished {
+            return None
+        }
+        let res = match self.de.rdr.peek_u8() {
+            Err(ref err) if err.is_eof() => {
+                self.finished = true;
+                return None
+            }
+            Err(err) => Err(err),
+            Ok(codes::FOOTER) => {
+                self.finished = true;
+                return match self.de.read_footer() {
+                    Ok(()) => {
+                        self.offset = self.de.rdr.get_bytes_read();
+                        None
+                    }
+                    Err(err) => Some(Err(err))
+                }
+            }
+            Ok(_) => T::deserialize(&mut self.de)
+        };
+        match res {
+            Ok(value) => {
+                self.offset = self.de.rdr.get_bytes_read();
+                Some(Ok(value))
+            }
+            Err(err) => {
+                self.finished = true;
+                Some(Err(err))
+            }
+        }
+    }
+}
+
+/// deserialize a single value. Anything but a footer after the value is
+/// an `ErrorCode::TrailingBytes` error
 pub fn from_bytes<'a, T>(s: &'a [u8]) -> Result<T>
 where
     T: de::Deserialize<'a>,
 {
     let mut deserializer = Deserializer::from_bytes(s);
-    T::deserialize(&mut deserializer)
+    let value = T::deserialize(&mut deserializer)?;
+    deserializer.end()?;
+    Ok(value)
+}
+
+/// deserialize the first value and return it along with the number of bytes
+/// it took up. Whatever follows is left alone
+pub fn from_bytes_partial<'a, T>(s: &'a [u8]) -> Result<(T, usize)>
+where
+    T: de::Deserialize<'a>,
+{
+    let mut deserializer = Deserializer::from_bytes(s);
+    let value = T::deserialize(&mut deserializer)?;
+    Ok((value, deserializer.byte_offset()))
 }
 
 pub fn from_vec<'a, T>(v: &'a Vec<u8>) -> Result<T>
     where T: Deserialize<'a>,
 {
-    let mut deserializer = Deserializer::from_bytes(v.as_slice());
-    T::deserialize(&mut deserializer)
+    from_bytes(v.as_slice())
 }
 
-use std::io;
-
-pub fn from_reader<R, T>(mut rdr: R) -> Result<T>
+/// deserialize from an io::Read. Bytes are read as they are needed rather
+/// than all up front. As with `from_bytes` the input has to end after the
+/// value, apart from a footer; `Deserializer::from_reader` reads values off
+/// a stream that goes on.
+#[cfg(feature = "std")]
+pub fn from_reader<R, T>(rdr: R) -> Result<T>
 where
     R: io::Read,
     T: de::DeserializeOwned,
 {
-    let mut bytes = Vec::new();
-    match rdr.read_to_end(&mut bytes){
-        Ok(_) => from_bytes(&bytes),
-        Err(e) => Err(Error::io(e))
-    }
+    let mut deserializer = Deserializer::from_reader(rdr);
+    let value = T::deserialize(&mut deserializer)?;
+    deserializer.end()?;
+    Ok(value)
 }
 
-impl<'de, 'a> de::Deserializer<'de> for &'a mut Deserializer<'de> {
+impl<'de, 'a, R: IReadBytes<'de>> de::Deserializer<'de> for &'a mut Deserializer<'de, R> {
     type Error = Error;
 
     fn deserialize_any<V>(self, visitor: V) -> Result<V::Value>
@@ -117,7 +511,7 @@ impl<'de, 'a> de::Deserializer<'de> for &'a mut Deserializer<'de> {
             }
 
             codes::BYTES | codes::BYTES_PACKED_LENGTH_START..=215 => {
-                visitor.visit_bytes(RawInput.read_bytes_code(&mut self.rdr, code)?)
+                visit_bytes_ref(RawInput.read_bytes_code(&mut self.rdr, code)?, visitor)
             }
 
             codes::BYTES_CHUNK => {
@@ -126,26 +520,12 @@ impl<'de, 'a> de::Deserializer<'de> for &'a mut Deserializer<'de> {
 
             codes::STRING_PACKED_LENGTH_START..=225 => {
                 let length = code as u8 - codes::STRING_PACKED_LENGTH_START;
-                let string: String = RawInput.read_fressian_string(&mut self.rdr, length as usize)?;
-
-                if self.cache_next {
-                    self.cache_next = false;
-                    self.add_priority_cache(Value::STRING(string.clone()))
-                }
-
-                visitor.visit_string(string)
+                visit_str_payload(self, length as usize, false, visitor)
             }
 
             codes::STRING => {
                 let length = RawInput.read_count(&mut self.rdr)?;
-                let string: String = RawInput.read_fressian_string(&mut self.rdr, length as usize)?;
-
-                if self.cache_next {
-                    self.cache_next = false;
-                    self.add_priority_cache(Value::STRING(string.clone()))
-                }
-
-                visitor.visit_string(string)
+                visit_str_payload(self, length as usize, false, visitor)
             }
 
             codes::STRING_CHUNK => {
@@ -154,14 +534,7 @@ impl<'de, 'a> de::Deserializer<'de> for &'a mut Deserializer<'de> {
 
             codes::UTF8 => {
                 let length = RawInput.read_count(&mut self.rdr)?;
-                let string = RawInput.read_raw_utf8(&mut self.rdr, length as usize)?;
-
-                if self.cache_next {
-                    self.cache_next = false;
-                    self.add_priority_cache(Value::STRING(string.clone()))
-                }
-
-                visitor.visit_string(string)
+                visit_str_payload(self, length as usize, true, visitor)
             }
 
             codes::LIST_PACKED_LENGTH_START..=235 => {
@@ -213,7 +586,7 @@ impl<'de, 'a> de::Deserializer<'de> for &'a mut Deserializer<'de> {
             }
 
             codes::UUID => {
-                visitor.visit_bytes(RawInput.read_bytes(&mut self.rdr)?)
+                visit_bytes_ref(RawInput.read_bytes(&mut self.rdr)?, visitor)
             }
 
             codes::URI => {
@@ -249,25 +622,25 @@ impl<'de, 'a> de::Deserializer<'de> for &'a mut Deserializer<'de> {
                 visitor.visit_seq(FixedListReader::new(self, length as usize))
             }
 
-            codes::PUT_PRIORITY_CACHE => {
-                self.cache_next = true;
-                self.deserialize_any(visitor)
-            }
+            codes::PUT_PRIORITY_CACHE => match self.put_value()? {
+                Some(value) => serde::Deserializer::deserialize_any(value, visitor),
+                None => self.deserialize_any(visitor)
+            },
 
             codes::PRIORITY_CACHE_PACKED_START..=159 => {
                 let index = code as u8 - codes::PRIORITY_CACHE_PACKED_START;
-                if let Some(ref val) = self.get_priority_cache(index as usize) {
-                    match val {
-                        Value::STRING(s) => {
-                            visitor.visit_string(s.clone())
-                        }
-                        _ => Err(Error::msg("unsupported cached Value type".to_string())) //need value formatting
-                    }
-                } else {
-                    Err(Error::msg("missing cached object".to_string()))
-                }
+                visit_cached(self, index as usize, visitor)
             }
 
+            codes::GET_PRIORITY_CACHE => {
+                let index = RawInput.read_int(&mut self.rdr)?;
+                visit_cached(self, index as usize, visitor)
+            }
+
+            codes::RESET_CACHES => {
+                self.priority_cache.clear();
+                self.deserialize_any(visitor)
+            }
 
             _ => error(self, ErrorCode::UnmatchedCode(code as u8)),
         }
@@ -275,11 +648,20 @@ impl<'de, 'a> de::Deserializer<'de> for &'a mut Deserializer<'de> {
 
     serde::forward_to_deserialize_any! {
         bool i8 i16 i32 i64 i128 u8 u16 u32 u64 u128 f32 f64 char str string
-        bytes byte_buf unit unit_struct seq map struct identifier ignored_any
+        bytes byte_buf unit unit_struct seq map struct identifier
         enum
         // option tuple tuple_struct newtype_struct
     }
 
+    /// ignored values are skipped over by their lengths rather than read
+    fn deserialize_ignored_any<V>(self, visitor: V) -> Result<V::Value>
+        where
+            V: Visitor<'de>,
+    {
+        self.skip_value()?;
+        visitor.visit_unit()
+    }
+
     fn deserialize_option<V>(self, visitor: V) -> Result<V::Value>
         where
             V: Visitor<'de>,
@@ -301,11 +683,19 @@ impl<'de, 'a> de::Deserializer<'de> for &'a mut Deserializer<'de> {
     {
         match name {
             "CODE" => {
-                // this exists for deserialize Value lookahead
-                //this will choke on cache codes, need to peek until next value code///////////////////////////////
+                // this exists for deserialize Value lookahead, VALUE has
+                // already read the cache codes before the value code
                 // should this be read?
                 visitor.visit_i8(self.peek_next_code()?)
             }
+            "VALUE" => {
+                // Value and ValueRef read the CODE first, then the value itself,
+                // so the cache codes before it are read here
+                match self.read_cache_codes()? {
+                    Some(value) => value.deserialize_tuple_struct(name, _len, visitor),
+                    None => visitor.visit_seq(FixedListReader::new(self, 2))
+                }
+            }
             "KEY" => {
                 // strongly typed deserializing assumes we are already
                 // at component data where as weakly typed will have
@@ -352,8 +742,86 @@ impl<'de, 'a> de::Deserializer<'de> for &'a mut Deserializer<'de> {
 
 ////////////////////////////////////////////////////////////////////
 
-fn visit_list<'a, 'de, V>(de: &'a mut Deserializer<'de>, visitor: V) -> Result<V::Value>
+/// Visit the payload of a STRING or UTF8 value. Bytes borrowed from the input
+/// are handed to the visitor as `&'de str` when they are valid utf8 as is, which
+/// is always true for UTF8 and for STRINGs that are all ascii. Other STRINGs are
+/// modified utf8 and have to be decoded into a String.
+fn visit_str_payload<'a, 'de, R, V>(de: &'a mut Deserializer<'de, R>, length: usize, utf8: bool, visitor: V) -> Result<V::Value>
+    where
+        R: IReadBytes<'de>,
+        V: Visitor<'de>,
+{
+    if length == 0 {
+        cache_string(&mut de.cache_next, &mut de.priority_cache, "");
+        return visitor.visit_borrowed_str("")
+    }
+    let position = de.rdr.get_bytes_read();
+    let bytes = de.rdr.read_bytes(length)?;
+    if !utf8 && !bytes.is_ascii() {
+        let string = RawInput.decode_fressian_string(&bytes, position)?;
+        cache_string(&mut de.cache_next, &mut de.priority_cache, &string);
+        return visitor.visit_string(string)
+    }
+    let invalid = |_| Error::syntax(ErrorCode::InvalidUTF8, position);
+    match bytes {
+        Reference::Borrowed(bytes) => {
+            let s = str::from_utf8(bytes).map_err(invalid)?;
+            cache_string(&mut de.cache_next, &mut de.priority_cache, s);
+            visitor.visit_borrowed_str(s)
+        }
+        Reference::Copied(bytes) => {
+            let s = str::from_utf8(bytes).map_err(invalid)?;
+            cache_string(&mut de.cache_next, &mut de.priority_cache, s);
+            visitor.visit_str(s)
+        }
+    }
+}
+
+/// the codes a string can start with, the ones whose puts are taken as they are read
+fn is_string_code(code: u8) -> bool {
+    // the packed range ends at STRING_CHUNK
+    matches!(code, codes::STRING_PACKED_LENGTH_START..=codes::STRING_PACKED_LENGTH_END
+        | codes::STRING | codes::UTF8)
+}
+
+/// cache a string read after PUT_PRIORITY_CACHE. This takes the fields rather
+/// than the Deserializer so it can be called while a copied string still
+/// borrows the reader
+fn cache_string(cache_next: &mut bool, priority_cache: &mut Vec<Value>, s: &str) {
+    if *cache_next {
+        *cache_next = false;
+        priority_cache.push(Value::STRING(s.to_string()))
+    }
+}
+
+fn visit_bytes_ref<'de, V>(bytes: Reference<'de, '_, [u8]>, visitor: V) -> Result<V::Value>
+    where
+        V: Visitor<'de>,
+{
+    match bytes {
+        Reference::Borrowed(bytes) => visitor.visit_borrowed_bytes(bytes),
+        Reference::Copied(bytes) => visitor.visit_bytes(bytes)
+    }
+}
+
+fn cached_value<'de, R: IReadBytes<'de>>(de: &Deserializer<'de, R>, index: usize) -> Result<Value> {
+    match de.get_priority_cache(index) {
+        Some(val) => Ok(val.clone()),
+        None => error(de, ErrorCode::UnknownCacheRef(index))
+    }
+}
+
+fn visit_cached<'a, 'de, R, V>(de: &'a mut Deserializer<'de, R>, index: usize, visitor: V) -> Result<V::Value>
+    where
+        R: IReadBytes<'de>,
+        V: Visitor<'de>,
+{
+    serde::Deserializer::deserialize_any(cached_value(de, index)?, visitor)
+}
+
+fn visit_list<'a, 'de, R, V>(de: &'a mut Deserializer<'de, R>, visitor: V) -> Result<V::Value>
     where
+        R: IReadBytes<'de>,
         V: Visitor<'de>,
 {
     let list_code = de.read_next_code()?;
@@ -381,14 +849,14 @@ fn visit_list<'a, 'de, V>(de: &'a mut Deserializer<'de>, visitor: V) -> Result<V
 }
 
 
-struct FixedListReader<'a, 'de: 'a> {
-    de: &'a mut Deserializer<'de>,
+struct FixedListReader<'a, 'de: 'a, R> {
+    de: &'a mut Deserializer<'de, R>,
     length: usize,
     items_read: usize,
 }
 
-impl<'a, 'de> FixedListReader<'a, 'de> {
-    fn new(de: &'a mut Deserializer<'de>, length: usize) -> Self {
+impl<'a, 'de, R: IReadBytes<'de>> FixedListReader<'a, 'de, R> {
+    fn new(de: &'a mut Deserializer<'de, R>, length: usize) -> Self {
         FixedListReader {
             de,
             length: length,
@@ -402,7 +870,7 @@ impl<'a, 'de> FixedListReader<'a, 'de> {
 }
 
 
-impl<'de, 'a> SeqAccess<'de> for FixedListReader<'a, 'de> {
+impl<'de, 'a, R: IReadBytes<'de>> SeqAccess<'de> for FixedListReader<'a, 'de, R> {
     type Error = Error;
 
     fn next_element_seed<T>(&mut self, seed: T) -> Result<Option<T::Value>>
@@ -421,7 +889,7 @@ impl<'de, 'a> SeqAccess<'de> for FixedListReader<'a, 'de> {
     }
 }
 
-impl<'de, 'a> MapAccess<'de> for FixedListReader<'a, 'de> {
+impl<'de, 'a, R: IReadBytes<'de>> MapAccess<'de> for FixedListReader<'a, 'de, R> {
     type Error = Error;
 
     fn next_key_seed<K>(&mut self, seed: K) -> Result<Option<K::Value>>
@@ -450,18 +918,18 @@ impl<'de, 'a> MapAccess<'de> for FixedListReader<'a, 'de> {
 
 ///////////////////////////////////////////////////////////////////////////////////////////////////
 
-struct ClosedListReader<'a, 'de: 'a> {
-    de: &'a mut Deserializer<'de>,
+struct ClosedListReader<'a, 'de: 'a, R> {
+    de: &'a mut Deserializer<'de, R>,
     finished: bool
 }
 
-impl<'a, 'de> ClosedListReader<'a, 'de> {
-    fn new(de: &'a mut Deserializer<'de>) -> Self {
+impl<'a, 'de, R: IReadBytes<'de>> ClosedListReader<'a, 'de, R> {
+    fn new(de: &'a mut Deserializer<'de, R>) -> Self {
         ClosedListReader { de, finished: false }
     }
 }
 
-impl<'de, 'a> SeqAccess<'de> for ClosedListReader<'a, 'de> {
+impl<'de, 'a, R: IReadBytes<'de>> SeqAccess<'de> for ClosedListReader<'a, 'de, R> {
     type Error = Error;
 
     fn next_element_seed<T>(&mut self, seed: T) -> Result<Option<T::Value>>
@@ -480,7 +948,7 @@ impl<'de, 'a> SeqAccess<'de> for ClosedListReader<'a, 'de> {
     }
 }
 
-impl<'de, 'a> MapAccess<'de> for ClosedListReader<'a, 'de> {
+impl<'de, 'a, R: IReadBytes<'de>> MapAccess<'de> for ClosedListReader<'a, 'de, R> {
     type Error = Error;
 
     fn next_key_seed<K>(&mut self, seed: K) -> Result<Option<K::Value>>
@@ -509,18 +977,18 @@ impl<'de, 'a> MapAccess<'de> for ClosedListReader<'a, 'de> {
 
 ///////////////////////////////////////////////////////////////////////////////////////////////////
 
-struct OpenListReader<'a, 'de: 'a> {
-    de: &'a mut Deserializer<'de>,
+struct OpenListReader<'a, 'de: 'a, R> {
+    de: &'a mut Deserializer<'de, R>,
     finished: bool
 }
 
-impl<'a, 'de> OpenListReader<'a, 'de> {
-    fn new(de: &'a mut Deserializer<'de>) -> Self {
+impl<'a, 'de, R: IReadBytes<'de>> OpenListReader<'a, 'de, R> {
+    fn new(de: &'a mut Deserializer<'de, R>) -> Self {
         OpenListReader { de, finished: false }
     }
 }
 
-impl<'de, 'a> SeqAccess<'de> for OpenListReader<'a, 'de> {
+impl<'de, 'a, R: IReadBytes<'de>> SeqAccess<'de> for OpenListReader<'a, 'de, R> {
     type Error = Error;
 
     fn next_element_seed<T>(&mut self, seed: T) -> Result<Option<T::Value>>
@@ -1,12 +1,14 @@
 extern crate serde;
 
-use std;
-use std::fmt::{self, Debug, Display};
+use core::fmt::{self, Debug, Display};
 use serde::{de};
 use serde::ser::{self,Serialize, Serializer, SerializeMap};
+#[cfg(feature = "std")]
 use std::io;
 
-pub type Result<T> = ::std::result::Result<T, Error>;
+use crate::lib::*;
+
+pub type Result<T> = ::core::result::Result<T, Error>;
 
 pub struct Error {
     pub err: Box<ErrorImpl>,
@@ -14,9 +16,12 @@ pub struct Error {
 
 pub enum ErrorCode {
     Eof,
+    #[cfg(feature = "std")]
     Io(io::Error),
     Message(String),
     UnsupportedType,
+    /// a Value conversion found (expected, found) types
+    UnexpectedValueType(&'static str, &'static str),
     //// serialization errors
     UnsupportedTAType,
     UnsupportedCacheType, //temporary
@@ -32,10 +37,23 @@ pub enum ErrorCode {
     ExpectedBytesCode,
     ExpectedStringCode,
     MapExpectedListCode,
+    /// a map's list holds an odd number of keys and values
+    OddMapLength,
     ExpectedListCode,
     InvalidUTF8,
     UnexpectedEof,
     AttemptToReadPastEnd,
+    UnknownCacheRef(usize),
+    UnknownStructRef(usize),
+    InvalidFooter,
+    /// a footer's adler32 checksum does not match the bytes before it
+    InvalidChecksum,
+    /// values are nested deeper than the walker follows them
+    DepthLimitExceeded,
+    TrailingBytes,
+    BufferFull,
+    /// from_edn could not read its input, says what was wrong
+    InvalidEdn(&'static str),
 }
 
 pub struct ErrorImpl {
@@ -57,14 +75,17 @@ impl ErrorImpl{
         match self.code {
             ErrorCode::Eof => Category::Eof,
 
+            #[cfg(feature = "std")]
             ErrorCode::Io(_) => Category::Io,
 
             ErrorCode::UnsupportedType
+            | ErrorCode::UnexpectedValueType(..)
             | ErrorCode::Message(_) => Category::Misc,
 
             ErrorCode::UnsupportedTAType
             | ErrorCode::UnsupportedCacheType
-            | ErrorCode::IntTooLargeFori64 => Category::Ser,
+            | ErrorCode::IntTooLargeFori64
+            | ErrorCode::BufferFull => Category::Ser,
 
             _ => Category::De
         }
@@ -72,7 +93,7 @@ impl ErrorImpl{
 }
 
 impl Serialize for ErrorImpl {
-    fn serialize<S>(&self, serializer: S) -> std::result::Result<S::Ok, S::Error>
+    fn serialize<S>(&self, serializer: S) -> core::result::Result<S::Ok, S::Error>
     where
         S: Serializer,
     {
@@ -90,6 +111,7 @@ impl Serialize for ErrorImpl {
         map_state.serialize_key("ErrorCode")?;
 
         match &self.code {
+            #[cfg(feature = "std")]
             ErrorCode::Io(_) => {
                 map_state.serialize_value("IO::Error")?;
             }
@@ -103,6 +125,24 @@ impl Serialize for ErrorImpl {
                 map_state.serialize_key("value")?;
                 map_state.serialize_value(&code)?;
             }
+            ErrorCode::InvalidEdn(msg) => {
+                map_state.serialize_value("InvalidEdn")?;
+                map_state.serialize_key("value")?;
+                map_state.serialize_value(msg)?;
+            }
+            ErrorCode::UnexpectedValueType(expected, found) => {
+                map_state.serialize_value("UnexpectedValueType")?;
+                map_state.serialize_key("expected")?;
+                map_state.serialize_value(expected)?;
+                map_state.serialize_key("found")?;
+                map_state.serialize_value(found)?;
+            }
+            ErrorCode::UnknownCacheRef(index)
+            | ErrorCode::UnknownStructRef(index) => {
+                map_state.serialize_value(&self.code.to_string())?;
+                map_state.serialize_key("value")?;
+                map_state.serialize_value(&index)?;
+            }
             _ => {
                 map_state.serialize_value(&self.code.to_string())?;
             }
@@ -113,7 +153,7 @@ impl Serialize for ErrorImpl {
 }
 
 impl Serialize for Error {
-    fn serialize<S>(&self, serializer: S) -> std::result::Result<S::Ok, S::Error>
+    fn serialize<S>(&self, serializer: S) -> core::result::Result<S::Ok, S::Error>
     where
         S: Serializer,
     {
@@ -154,6 +194,15 @@ impl Error {
         }
     }
 
+    pub fn unexpected_value_type(expected: &'static str, found: &'static str) -> Self {
+        Error {
+            err: Box::new(ErrorImpl {
+                code: ErrorCode::UnexpectedValueType(expected, found),
+                position: 0,
+            }),
+        }
+    }
+
     pub fn eof(position: usize) -> Self {
         Error {
             err: Box::new(ErrorImpl {
@@ -162,6 +211,7 @@ impl Error {
             }),
         }
     }
+    #[cfg(feature = "std")]
     pub fn io(error: io::Error) -> Self {
         Error {
             err: Box::new(ErrorImpl {
@@ -188,15 +238,20 @@ impl Display for ErrorCode {
         match *self {
             ErrorCode::Eof => f.write_str("Eof"),
             ErrorCode::Message(ref msg) => f.write_str(msg),
+            #[cfg(feature = "std")]
             ErrorCode::Io(ref err) => Display::fmt(err, f),
             ErrorCode::UnmatchedCode(_code) => f.write_str("UnmatchedCode"),
             ErrorCode::UnsupportedType => f.write_str("UnsupportedType"),
+            ErrorCode::UnexpectedValueType(expected, found) => {
+                write!(f, "expected {}, found {}", expected, found)
+            }
             ErrorCode::UnsupportedTAType => f.write_str("UnsupportedTAType"),
             ErrorCode::UnsupportedCacheType => f.write_str("UnsupportedCacheType"),
             ErrorCode::AttemptToReadPastEnd => f.write_str("AttemptToReadPastEnd"),
             ErrorCode::UnexpectedEof => f.write_str("UnexpectedEof"),
             ErrorCode::ExpectedListCode => f.write_str("ExpectedListCode"),
             ErrorCode::MapExpectedListCode => f.write_str("MapExpectedListCode"),
+            ErrorCode::OddMapLength => f.write_str("OddMapLength"),
             ErrorCode::IntTooLargeFori64 => f.write_str("IntTooLargeFori64"),
             ErrorCode::Expectedi64 => f.write_str("Expectedi64"),
             ErrorCode::ExpectedDoubleCode => f.write_str("ExpectedDoubleCode"),
@@ -206,7 +261,15 @@ impl Display for ErrorCode {
             ErrorCode::ExpectedBytesCode => f.write_str("ExpectedBytesCode"),
             ErrorCode::InvalidUTF8 => f.write_str("InvalidUTF8"),
             ErrorCode::ExpectedStringCode => f.write_str("ExpectedStringCode"),
-            ErrorCode::ExpectedNonZeroReadLength => f.write_str("ExpectedNonZeroReadLength")
+            ErrorCode::ExpectedNonZeroReadLength => f.write_str("ExpectedNonZeroReadLength"),
+            ErrorCode::UnknownCacheRef(_index) => f.write_str("UnknownCacheRef"),
+            ErrorCode::UnknownStructRef(_index) => f.write_str("UnknownStructRef"),
+            ErrorCode::InvalidFooter => f.write_str("InvalidFooter"),
+            ErrorCode::InvalidChecksum => f.write_str("InvalidChecksum"),
+            ErrorCode::DepthLimitExceeded => f.write_str("DepthLimitExceeded"),
+            ErrorCode::TrailingBytes => f.write_str("TrailingBytes"),
+            ErrorCode::BufferFull => f.write_str("BufferFull"),
+            ErrorCode::InvalidEdn(msg) => write!(f, "InvalidEdn: {}", msg)
         }
     }
 }
@@ -214,7 +277,7 @@ impl Display for ErrorCode {
 
 impl Display for Error {
     fn fmt(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
-        formatter.write_str(std::error::Error::description(self))
+        formatter.write_str(self.description())
     }
 }
 
@@ -258,9 +321,11 @@ impl de::Error for Error {
 
 
 
-impl std::error::Error for Error {
+impl Error {
     fn description(&self) -> &str {
         match self.err.code {
+            #[cfg(feature = "std")]
+            #[allow(deprecated)]
             ErrorCode::Io(ref err) => std::error::Error::description(err),
             // should use Display::fmt or to_string().
             _ => "fressian error!",
@@ -268,3 +333,11 @@ impl std::error::Error for Error {
     }
 }
 
+#[cfg(feature = "std")]
+impl std::error::Error for Error {
+    #[allow(deprecated)]
+    fn description(&self) -> &str {
+        Error::description(self)
+    }
+}
+
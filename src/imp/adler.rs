@@ -3,8 +3,7 @@ Adler-32 checksum
 This implementation is based off the example found at
 http://en.wikipedia.org/wiki/Adler-32.
 # Example
-```rust
-use compress::checksum::adler;
+```ignore
 let mut state = adler::State32::new();
 state.feed(b"abracadabra");
 let checksum = state.result();
@@ -19,6 +18,12 @@ pub struct State32 {
     b: u32,
 }
 
+impl Default for State32 {
+    fn default() -> Self {
+        State32::new()
+    }
+}
+
 impl State32 {
     /// Create a new state
     pub fn new() -> State32 {
@@ -39,6 +44,7 @@ impl State32 {
     }
 
     /// Reset the state
+    #[allow(dead_code)]
     pub fn reset(&mut self) {
         self.a = 1;
         self.b = 0;
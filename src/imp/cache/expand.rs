@@ -0,0 +1,128 @@
+use byteorder::{BigEndian, ByteOrder};
+
+use crate::error::{Error, ErrorCode, Result};
+use crate::imp::codes;
+use crate::imp::walk::{Event, Visit, Walker};
+use crate::lib::*;
+
+/// Rewrite fressian bytes into an equivalent cache free form. Priority cache
+/// puts are dropped, every cache reference is replaced by the bytes of the value
+/// it refers to, struct references are written out as full STRUCTTYPE headers and
+/// RESET_CACHES codes are removed. Footers are rewritten with the new length.
+///
+/// Values are copied byte for byte, they are never decoded.
+pub fn expand(bytes: &[u8]) -> Result<Vec<u8>> {
+    let mut expander = Expander::new();
+    Walker::new(bytes).walk_all(&mut expander)?;
+    Ok(expander.into_inner())
+}
+
+struct Expander {
+    // the bottom buffer is the output, a buffer is pushed for each value being cached
+    out: Vec<Vec<u8>>,
+    pending: Vec<bool>,
+    priority_cache: Vec<Option<Vec<u8>>>,
+    struct_cache: Vec<Vec<u8>>,
+    struct_start: Vec<usize>,
+    footer_start: usize
+}
+
+impl Expander {
+    fn new() -> Self {
+        Expander {
+            out: vec![Vec::new()],
+            pending: Vec::new(),
+            priority_cache: Vec::new(),
+            struct_cache: Vec::new(),
+            struct_start: Vec::new(),
+            footer_start: 0
+        }
+    }
+
+    fn top(&mut self) -> &mut Vec<u8> {
+        self.out.last_mut().expect("expander output")
+    }
+
+    fn begin_cached(&mut self, keep: bool) {
+        self.priority_cache.push(None);
+        self.pending.push(keep);
+        self.out.push(Vec::new());
+    }
+
+    fn into_inner(mut self) -> Vec<u8> {
+        self.out.swap_remove(0)
+    }
+}
+
+impl<'a> Visit<'a> for Expander {
+    fn visit(&mut self, event: Event, position: usize, bytes: &'a [u8]) -> Result<()> {
+        match event {
+            Event::Scalar(_)
+            | Event::Begin{..}
+            | Event::End(_) => {
+                self.top().extend_from_slice(bytes);
+            }
+
+            Event::PutCache(_) => self.begin_cached(true),
+
+            // precached values are only written where they are referenced
+            Event::Precache(_) => self.begin_cached(false),
+
+            Event::Cached(index) => {
+                let value = self.out.pop().expect("cached value");
+                if self.pending.pop() == Some(true) {
+                    self.top().extend_from_slice(&value);
+                }
+                self.priority_cache[index] = Some(value);
+            }
+
+            Event::CacheRef(index) => {
+                match self.priority_cache.get(index) {
+                    Some(Some(value)) => {
+                        let value = value.clone();
+                        self.top().extend_from_slice(&value);
+                    }
+                    // a reference to a value that is still being read
+                    _ => return Err(Error::syntax(ErrorCode::UnknownCacheRef(index), position))
+                }
+            }
+
+            Event::StructType => {
+                let start = self.top().len();
+                self.struct_start.push(start);
+                self.top().extend_from_slice(bytes);
+            }
+
+            Event::StructFields{..} => {
+                self.top().extend_from_slice(bytes);
+                let start = self.struct_start.pop().expect("struct start");
+                let header = self.top()[start..].to_vec();
+                self.struct_cache.push(header);
+            }
+
+            Event::StructRef{index, ..} => {
+                let header = self.struct_cache[index].clone();
+                self.top().extend_from_slice(&header);
+            }
+
+            Event::ResetCaches => {
+                self.priority_cache.clear();
+                self.struct_cache.clear();
+            }
+
+            Event::Footer{..} => {
+                self.priority_cache.clear();
+                self.struct_cache.clear();
+                let length = self.top().len() - self.footer_start;
+                let mut footer = [0u8; 12];
+                BigEndian::write_u32(&mut footer[0..4], codes::FOOTER_MAGIC as u32);
+                BigEndian::write_u32(&mut footer[4..8], length as u32);
+                // checksums are not written yet, see Serializer::write_footer
+                BigEndian::write_u32(&mut footer[8..12], 0);
+                self.top().extend_from_slice(&footer);
+                self.footer_start = self.top().len();
+            }
+        }
+        Ok(())
+    }
+}
@@ -1,13 +1,49 @@
+#[cfg(feature = "std")]
 use std::collections::hash_map::DefaultHasher;
-use std::hash::{Hash, Hasher};
+use core::hash::{Hash, Hasher};
 use serde::ser::{Serialize};
 
+use crate::lib::*;
+
+mod expand;
+mod optimize;
+mod train;
+pub use self::expand::expand;
+pub use self::optimize::{optimize, optimize_with, CachePolicy, Optimized};
+pub use self::train::{train, CacheTable};
+
 pub trait ICache {
     fn get<T: Serialize + Hash + PartialEq>(&self, object: &T) -> Option<usize>;
     fn intern<T: Serialize + Hash + PartialEq>(&mut self, object: &T) -> Option<usize>; //u32?
     fn reset(&mut self) -> ();
 }
 
+/// without std there is no DefaultHasher, 64 bit FNV-1a is small and good
+/// enough for telling cached values apart
+#[cfg(not(feature = "std"))]
+struct DefaultHasher(u64);
+
+#[cfg(not(feature = "std"))]
+impl DefaultHasher {
+    fn new() -> Self {
+        DefaultHasher(0xcbf2_9ce4_8422_2325)
+    }
+}
+
+#[cfg(not(feature = "std"))]
+impl Hasher for DefaultHasher {
+    fn write(&mut self, bytes: &[u8]) {
+        for byte in bytes {
+            self.0 ^= u64::from(*byte);
+            self.0 = self.0.wrapping_mul(0x0100_0000_01b3);
+        }
+    }
+
+    fn finish(&self) -> u64 {
+        self.0
+    }
+}
+
 fn default_hash<T: Hash>(t: &T) -> u64 {
     let mut s = DefaultHasher::new(); //this should be parameterized
     t.hash(&mut s);
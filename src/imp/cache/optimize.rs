@@ -0,0 +1,365 @@
+use serde::de::Deserialize;
+use serde::ser::Serialize;
+
+use crate::de;
+use crate::error::Result;
+use crate::imp::cache::{expand, Cache};
+use crate::imp::codes;
+use crate::imp::io::ByteWriter;
+use crate::imp::walk::{Event, Visit, Walker};
+use crate::ser::{self, Serializer};
+use crate::value::Value;
+use crate::lib::*;
+
+/// What the optimizer is allowed to cache
+#[derive(Clone, Debug, PartialEq)]
+pub struct CachePolicy {
+    /// cache the namespace and name strings of keywords and symbols that repeat
+    pub keywords: bool,
+    /// cache string map keys that repeat
+    pub map_keys: bool,
+    /// cache any other repeated value that encodes to at least this many bytes.
+    /// Off by default
+    pub min_value_size: Option<usize>,
+}
+
+impl Default for CachePolicy {
+    fn default() -> Self {
+        CachePolicy {
+            keywords: true,
+            map_keys: true,
+            min_value_size: None
+        }
+    }
+}
+
+/// The result of `cache::optimize`
+#[derive(Clone, Debug, PartialEq)]
+pub struct Optimized {
+    pub bytes: Vec<u8>,
+    pub original_size: usize,
+}
+
+impl Optimized {
+    pub fn optimized_size(&self) -> usize {
+        self.bytes.len()
+    }
+
+    /// bytes saved, negative if re-encoding made things worse
+    pub fn saved(&self) -> isize {
+        self.original_size as isize - self.optimized_size() as isize
+    }
+}
+
+/// Re-encode fressian bytes using the default CachePolicy
+pub fn optimize(bytes: &[u8]) -> Result<Optimized> {
+    optimize_with(bytes, &CachePolicy::default())
+}
+
+/// Re-encode fressian bytes, caching whatever `policy` allows. Existing cache
+/// references are expanded first, so any fressian bytes are accepted. Top level
+/// values share one cache, and footers are kept.
+pub fn optimize_with(bytes: &[u8], policy: &CachePolicy) -> Result<Optimized> {
+    let expanded = expand(bytes)?;
+
+    let mut segments: Vec<(Vec<Value>, bool)> = vec![(Vec::new(), false)];
+    let mut walker = Walker::new(&expanded);
+    while !walker.is_done() {
+        let start = walker.position();
+        let mut top = TopLevel { footer: false };
+        walker.walk_next(&mut top)?;
+        if top.footer {
+            segments.last_mut().expect("segment").1 = true;
+            segments.push((Vec::new(), false));
+        } else {
+            let value = Value::deserialize(&mut de::Deserializer::from_bytes(&expanded[start..walker.position()]))?;
+            segments.last_mut().expect("segment").0.push(value);
+        }
+    }
+
+    let mut out: Vec<u8> = Vec::with_capacity(bytes.len());
+    for (values, footer) in segments.iter() {
+        let counts = Counts::new(values, policy);
+        let mut ser = Serializer::new();
+        for value in values {
+            write_value(&mut ser, value, &counts)?;
+        }
+        if *footer {
+            ser.write_footer()?;
+        }
+        out.extend_from_slice(ser.get_ref());
+    }
+
+    Ok(Optimized {
+        bytes: out,
+        original_size: bytes.len()
+    })
+}
+
+struct TopLevel {
+    footer: bool
+}
+
+impl<'a> Visit<'a> for TopLevel {
+    fn visit(&mut self, event: Event, _position: usize, _bytes: &'a [u8]) -> Result<()> {
+        if let Event::Footer{..} = event {
+            self.footer = true;
+        }
+        Ok(())
+    }
+}
+
+/// the strings and values worth caching in one segment
+struct Counts<'v> {
+    strings: BTreeMap<&'v str, usize>,
+    values: BTreeMap<&'v Value, usize>,
+}
+
+impl<'v> Counts<'v> {
+    fn new(values: &'v [Value], policy: &CachePolicy) -> Self {
+        let mut counts = Counts {
+            strings: BTreeMap::new(),
+            values: BTreeMap::new(),
+        };
+        for value in values {
+            counts.count(value, policy);
+        }
+        counts.strings.retain(|s, count| 1 < *count && !s.is_empty());
+        let min_size = policy.min_value_size;
+        counts.values.retain(|value, count| {
+            1 < *count && match min_size {
+                Some(min_size) => min_size <= encoded_size(value),
+                None => false
+            }
+        });
+        counts.prune(values);
+        counts
+    }
+
+    /// Drop the entries that would be put without being referenced later,
+    /// as when all but one of a string's uses are inside a cached value that
+    /// is written once and then referenced. Dropping a value has its
+    /// contents written again, so this goes until nothing changes
+    fn prune(&mut self, values: &'v [Value]) {
+        loop {
+            let mut written = Counts {
+                strings: BTreeMap::new(),
+                values: BTreeMap::new(),
+            };
+            for value in values {
+                self.count_written(value, &mut written);
+            }
+            let before = (self.strings.len(), self.values.len());
+            self.strings.retain(|s, _| written.strings.get(s).is_some_and(|n| 1 < *n));
+            self.values.retain(|v, _| written.values.get(v).is_some_and(|n| 1 < *n));
+            if before == (self.strings.len(), self.values.len()) {
+                return
+            }
+        }
+    }
+
+    /// count what `write_value` puts in the cache or references
+    fn count_written(&self, value: &'v Value, written: &mut Counts<'v>) {
+        match value {
+            Value::STRING(s) if self.cache_str(s) => written.count_str(Some(s)),
+            // written whole the first time, then referenced
+            _ if self.cache_value(value) => {
+                let count = written.values.entry(value).or_insert(0);
+                *count += 1;
+                if *count == 1 {
+                    self.count_written_keywords(value, written);
+                }
+            }
+            Value::KEY(k) => {
+                self.count_written_str(k.namespace(), written);
+                self.count_written_str(Some(k.name()), written);
+            }
+            Value::SYM(s) => {
+                self.count_written_str(s.namespace(), written);
+                self.count_written_str(Some(s.name()), written);
+            }
+            Value::LIST(items) => {
+                for item in items {
+                    self.count_written(item, written);
+                }
+            }
+            Value::SET(set) => {
+                for item in set.iter() {
+                    self.count_written(item, written);
+                }
+            }
+            Value::MAP(m) => {
+                for (k, v) in m.iter() {
+                    self.count_written(k, written);
+                    self.count_written(v, written);
+                }
+            }
+            _ => {}
+        }
+    }
+
+    /// a value written whole still caches the strings of its keywords and
+    /// symbols, the Serializer does that for every one it writes
+    fn count_written_keywords(&self, value: &'v Value, written: &mut Counts<'v>) {
+        match value {
+            Value::KEY(k) => {
+                self.count_written_str(k.namespace(), written);
+                self.count_written_str(Some(k.name()), written);
+            }
+            Value::SYM(s) => {
+                self.count_written_str(s.namespace(), written);
+                self.count_written_str(Some(s.name()), written);
+            }
+            Value::LIST(items) => {
+                for item in items {
+                    self.count_written_keywords(item, written);
+                }
+            }
+            Value::SET(set) => {
+                for item in set.iter() {
+                    self.count_written_keywords(item, written);
+                }
+            }
+            Value::MAP(m) => {
+                for (k, v) in m.iter() {
+                    self.count_written_keywords(k, written);
+                    self.count_written_keywords(v, written);
+                }
+            }
+            _ => {}
+        }
+    }
+
+    fn count_written_str(&self, s: Option<&'v str>, written: &mut Counts<'v>) {
+        if let Some(s) = s.filter(|s| self.cache_str(s)) {
+            written.count_str(Some(s));
+        }
+    }
+
+    fn count(&mut self, value: &'v Value, policy: &CachePolicy) {
+        match value {
+            Value::KEY(k) if policy.keywords => {
+                self.count_str(k.namespace());
+                self.count_str(Some(k.name()));
+            }
+            Value::SYM(s) if policy.keywords => {
+                self.count_str(s.namespace());
+                self.count_str(Some(s.name()));
+            }
+            Value::LIST(items) => {
+                self.count_value(value, policy);
+                for item in items {
+                    self.count(item, policy);
+                }
+            }
+            Value::SET(set) => {
+                self.count_value(value, policy);
+                for item in set.iter() {
+                    self.count(item, policy);
+                }
+            }
+            Value::MAP(m) => {
+                self.count_value(value, policy);
+                for (k, v) in m.iter() {
+                    match k {
+                        Value::STRING(s) if policy.map_keys => self.count_str(Some(s)),
+                        _ => self.count(k, policy)
+                    }
+                    self.count(v, policy);
+                }
+            }
+            _ => self.count_value(value, policy)
+        }
+    }
+
+    fn count_str(&mut self, s: Option<&'v str>) {
+        if let Some(s) = s {
+            *self.strings.entry(s).or_insert(0) += 1;
+        }
+    }
+
+    fn count_value(&mut self, value: &'v Value, policy: &CachePolicy) {
+        if policy.min_value_size.is_some() {
+            *self.values.entry(value).or_insert(0) += 1;
+        }
+    }
+
+    fn cache_str(&self, s: &str) -> bool {
+        self.strings.contains_key(s)
+    }
+
+    fn cache_value(&self, value: &Value) -> bool {
+        self.values.contains_key(value)
+    }
+}
+
+fn encoded_size(value: &Value) -> usize {
+    ser::to_vec(value).map(|bytes| bytes.len()).unwrap_or(0)
+}
+
+type ValueSerializer = Serializer<ByteWriter<Vec<u8>>, Cache>;
+
+fn write_str(ser: &mut ValueSerializer, s: &str, counts: &Counts) -> Result<()> {
+    if counts.cache_str(s) {
+        ser.caching_serialize(s)
+    } else {
+        s.serialize(&mut *ser)
+    }
+}
+
+fn write_value(ser: &mut ValueSerializer, value: &Value, counts: &Counts) -> Result<()> {
+    match value {
+        Value::STRING(s) if counts.cache_str(s) => ser.caching_serialize(s.as_str()),
+
+        // the cache entry is reserved before the value is written, so the
+        // serializer's own caching of nested keywords lines up with readers
+        _ if counts.cache_value(value) => ser.caching_serialize(value),
+
+        Value::KEY(k) => {
+            ser.write_code(codes::KEY)?;
+            match k.namespace() {
+                Some(ns) => write_str(ser, ns, counts)?,
+                None => ser.write_null()?
+            }
+            write_str(ser, k.name(), counts)
+        }
+
+        Value::SYM(s) => {
+            ser.write_code(codes::SYM)?;
+            match s.namespace() {
+                Some(ns) => write_str(ser, ns, counts)?,
+                None => ser.write_null()?
+            }
+            write_str(ser, s.name(), counts)
+        }
+
+        Value::LIST(items) => {
+            ser.write_list_header(items.len())?;
+            for item in items {
+                write_value(ser, item, counts)?;
+            }
+            Ok(())
+        }
+
+        Value::SET(set) => {
+            ser.write_code(codes::SET)?;
+            ser.write_list_header(set.len())?;
+            for item in set.iter() {
+                write_value(ser, item, counts)?;
+            }
+            Ok(())
+        }
+
+        Value::MAP(m) => {
+            ser.write_code(codes::MAP)?;
+            ser.write_list_header(m.len() * 2)?;
+            for (k, v) in m.iter() {
+                write_value(ser, k, counts)?;
+                write_value(ser, v, counts)?;
+            }
+            Ok(())
+        }
+
+        _ => value.serialize(&mut *ser)
+    }
+}
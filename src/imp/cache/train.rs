@@ -0,0 +1,130 @@
+use crate::imp::cache::{Cache, ICache};
+use crate::ser;
+use crate::value::Value;
+use crate::lib::*;
+
+/// A ranked table of values that a writer and reader agree on ahead of time.
+/// Entry `i` is priority cache slot `i`, so the first 32 entries are referenced
+/// with a single byte. The table is itself serializable, so it can be written
+/// with `ser::to_vec` and shipped alongside a wasm bundle.
+#[derive(Clone, PartialEq, Debug, Serialize, Deserialize)]
+pub struct CacheTable(Vec<Value>);
+
+impl CacheTable {
+    pub fn from_vec(entries: Vec<Value>) -> Self {
+        CacheTable(entries)
+    }
+
+    pub fn entries(&self) -> &[Value] {
+        &self.0
+    }
+
+    pub fn len(&self) -> usize {
+        self.0.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+
+    /// cache for a Serializer, ie `Serializer::with_cache(table.to_cache())`.
+    /// The serializer caches the strings inside keywords and symbols, so string
+    /// entries are interned as strings and everything else keeps its slot.
+    pub fn to_cache(&self) -> Cache {
+        let mut cache = Cache::new();
+        for entry in self.0.iter() {
+            match entry {
+                Value::STRING(s) => cache.intern(s),
+                _ => cache.intern(entry)
+            };
+        }
+        cache
+    }
+
+    /// cache for a Deserializer, ie `Deserializer::with_cache(bytes, table.to_values())`
+    pub fn to_values(&self) -> Vec<Value> {
+        self.0.clone()
+    }
+
+    pub fn into_inner(self) -> Vec<Value> {
+        self.0
+    }
+}
+
+/// Find the values worth caching in a corpus of samples.
+///
+/// Candidates are the namespace and name strings of keywords and symbols, which
+/// are the only values the Serializer caches, wherever they are nested. Each
+/// candidate that occurs more than once is scored by the bytes it would save if
+/// every occurrence were a cache reference, and the best `budget` candidates are
+/// returned, most valuable first.
+///
+/// Plain strings and whole sub-values are not candidates. The Serializer writes
+/// them without looking in its cache, so table entries for them would only take
+/// slots away from keywords. `cache::optimize` can still cache them when
+/// re-encoding bytes.
+pub fn train(samples: &[Value], budget: usize) -> CacheTable {
+    let mut counts: BTreeMap<Value, usize> = BTreeMap::new();
+    for sample in samples {
+        count_candidates(sample, &mut counts);
+    }
+
+    let mut scored: Vec<(usize, Value)> = counts.into_iter()
+        .filter(|(_, count)| 1 < *count)
+        .filter_map(|(value, count)| {
+            let size = ser::to_vec(&value).map(|bytes| bytes.len()).unwrap_or(0);
+            if 1 < size {
+                Some(((size - 1) * count, value))
+            } else {
+                None
+            }
+        })
+        .collect();
+
+    // best savings first so the most valuable entries get the packed references.
+    // ties are broken by value so training is deterministic
+    scored.sort_by(|a, b| b.0.cmp(&a.0).then_with(|| a.1.cmp(&b.1)));
+    scored.truncate(budget);
+
+    CacheTable(scored.into_iter().map(|(_, value)| value).collect())
+}
+
+fn count_string(s: &str, counts: &mut BTreeMap<Value, usize>) {
+    if !s.is_empty() {
+        *counts.entry(Value::STRING(s.to_string())).or_insert(0) += 1;
+    }
+}
+
+fn count_candidates(value: &Value, counts: &mut BTreeMap<Value, usize>) {
+    match value {
+        Value::KEY(k) => {
+            if let Some(ns) = k.namespace() {
+                count_string(ns, counts);
+            }
+            count_string(k.name(), counts);
+        }
+        Value::SYM(s) => {
+            if let Some(ns) = s.namespace() {
+                count_string(ns, counts);
+            }
+            count_string(s.name(), counts);
+        }
+        Value::LIST(items) => {
+            for item in items {
+                count_candidates(item, counts);
+            }
+        }
+        Value::SET(set) => {
+            for item in set.iter() {
+                count_candidates(item, counts);
+            }
+        }
+        Value::MAP(m) => {
+            for (k, v) in m.iter() {
+                count_candidates(k, counts);
+                count_candidates(v, counts);
+            }
+        }
+        _ => {}
+    }
+}
@@ -0,0 +1,265 @@
+//! serde_bytes and ordered-float both need std. With the `std` feature their
+//! types are used as is, without it these stand-ins provide the parts this
+//! crate relies on so that `Value` looks the same either way.
+
+#[cfg(feature = "std")]
+pub use serde_bytes::ByteBuf;
+#[cfg(feature = "std")]
+pub use ordered_float::OrderedFloat;
+
+#[cfg(not(feature = "std"))]
+pub use self::bytes::ByteBuf;
+#[cfg(not(feature = "std"))]
+pub use self::float::OrderedFloat;
+
+#[cfg(not(feature = "std"))]
+mod bytes {
+    use core::fmt;
+    use core::ops::{Deref, DerefMut};
+
+    use serde::de::{self, Deserialize, Deserializer, SeqAccess, Visitor};
+    use serde::ser::{Serialize, Serializer};
+
+    use crate::lib::*;
+
+    /// a Vec<u8> that serializes as bytes
+    #[derive(Clone, Default, PartialEq, Eq, PartialOrd, Ord, Hash, Debug)]
+    pub struct ByteBuf(Vec<u8>);
+
+    impl ByteBuf {
+        pub fn new() -> Self {
+            ByteBuf(Vec::new())
+        }
+
+        pub fn into_vec(self) -> Vec<u8> {
+            self.0
+        }
+    }
+
+    impl From<Vec<u8>> for ByteBuf {
+        fn from(bytes: Vec<u8>) -> Self {
+            ByteBuf(bytes)
+        }
+    }
+
+    impl<'a> From<&'a [u8]> for ByteBuf {
+        fn from(bytes: &'a [u8]) -> Self {
+            ByteBuf(bytes.to_vec())
+        }
+    }
+
+    impl From<ByteBuf> for Vec<u8> {
+        fn from(bytes: ByteBuf) -> Self {
+            bytes.0
+        }
+    }
+
+    impl Deref for ByteBuf {
+        type Target = Vec<u8>;
+
+        fn deref(&self) -> &Vec<u8> { &self.0 }
+    }
+
+    impl DerefMut for ByteBuf {
+        fn deref_mut(&mut self) -> &mut Vec<u8> { &mut self.0 }
+    }
+
+    impl Serialize for ByteBuf {
+        fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+        where
+            S: Serializer,
+        {
+            serializer.serialize_bytes(&self.0)
+        }
+    }
+
+    struct ByteBufVisitor;
+
+    impl<'de> Visitor<'de> for ByteBufVisitor {
+        type Value = ByteBuf;
+
+        fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+            formatter.write_str("byte array")
+        }
+
+        fn visit_seq<V>(self, mut visitor: V) -> Result<ByteBuf, V::Error>
+        where
+            V: SeqAccess<'de>,
+        {
+            let mut bytes = Vec::with_capacity(visitor.size_hint().unwrap_or(0));
+            while let Some(b) = visitor.next_element()? {
+                bytes.push(b);
+            }
+            Ok(ByteBuf(bytes))
+        }
+
+        fn visit_bytes<E: de::Error>(self, v: &[u8]) -> Result<ByteBuf, E> {
+            Ok(ByteBuf(v.to_vec()))
+        }
+
+        fn visit_byte_buf<E: de::Error>(self, v: Vec<u8>) -> Result<ByteBuf, E> {
+            Ok(ByteBuf(v))
+        }
+
+        fn visit_str<E: de::Error>(self, v: &str) -> Result<ByteBuf, E> {
+            Ok(ByteBuf(v.as_bytes().to_vec()))
+        }
+    }
+
+    impl<'de> Deserialize<'de> for ByteBuf {
+        fn deserialize<D>(deserializer: D) -> Result<ByteBuf, D::Error>
+        where
+            D: Deserializer<'de>,
+        {
+            deserializer.deserialize_byte_buf(ByteBufVisitor)
+        }
+    }
+}
+
+#[cfg(not(feature = "std"))]
+mod float {
+    use core::cmp::Ordering;
+    use core::hash::{Hash, Hasher};
+    use core::ops::Deref;
+
+    use serde::de::{Deserialize, Deserializer};
+    use serde::ser::{Serialize, Serializer};
+
+    pub trait Float: Copy + PartialEq + PartialOrd {
+        fn is_nan(self) -> bool;
+        /// bits with every NaN and both zeros made equal, for hashing
+        fn canonical_bits(self) -> u64;
+    }
+
+    impl Float for f32 {
+        fn is_nan(self) -> bool { f32::is_nan(self) }
+
+        fn canonical_bits(self) -> u64 {
+            if self.is_nan() {
+                f32::NAN.to_bits() as u64
+            } else if self == 0.0 {
+                0
+            } else {
+                self.to_bits() as u64
+            }
+        }
+    }
+
+    impl Float for f64 {
+        fn is_nan(self) -> bool { f64::is_nan(self) }
+
+        fn canonical_bits(self) -> u64 {
+            if self.is_nan() {
+                f64::NAN.to_bits()
+            } else if self == 0.0 {
+                0
+            } else {
+                self.to_bits()
+            }
+        }
+    }
+
+    /// A float with a total order, NaN equals itself and is greater than
+    /// everything else. Same as ordered_float::OrderedFloat
+    #[derive(Clone, Copy, Default, Debug)]
+    pub struct OrderedFloat<T: Float>(pub T);
+
+    impl<T: Float> OrderedFloat<T> {
+        pub fn into_inner(self) -> T {
+            self.0
+        }
+    }
+
+    impl<T: Float> From<T> for OrderedFloat<T> {
+        fn from(val: T) -> Self {
+            OrderedFloat(val)
+        }
+    }
+
+    impl<T: Float> Deref for OrderedFloat<T> {
+        type Target = T;
+
+        fn deref(&self) -> &T { &self.0 }
+    }
+
+    impl<T: Float> PartialEq for OrderedFloat<T> {
+        fn eq(&self, other: &Self) -> bool {
+            if self.0.is_nan() {
+                other.0.is_nan()
+            } else {
+                self.0 == other.0
+            }
+        }
+    }
+
+    impl<T: Float> Eq for OrderedFloat<T> {}
+
+    impl<T: Float> PartialOrd for OrderedFloat<T> {
+        fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+            Some(self.cmp(other))
+        }
+    }
+
+    impl<T: Float> Ord for OrderedFloat<T> {
+        fn cmp(&self, other: &Self) -> Ordering {
+            match self.0.partial_cmp(&other.0) {
+                Some(ordering) => ordering,
+                None => match (self.0.is_nan(), other.0.is_nan()) {
+                    (true, true) => Ordering::Equal,
+                    (true, false) => Ordering::Greater,
+                    _ => Ordering::Less
+                }
+            }
+        }
+    }
+
+    impl<T: Float> Hash for OrderedFloat<T> {
+        fn hash<H: Hasher>(&self, state: &mut H) {
+            self.0.canonical_bits().hash(state)
+        }
+    }
+
+    impl<T: Float + Serialize> Serialize for OrderedFloat<T> {
+        fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+        where
+            S: Serializer,
+        {
+            self.0.serialize(serializer)
+        }
+    }
+
+    impl<'de, T: Float + Deserialize<'de>> Deserialize<'de> for OrderedFloat<T> {
+        fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+        where
+            D: Deserializer<'de>,
+        {
+            T::deserialize(deserializer).map(OrderedFloat)
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::de;
+    use crate::ser;
+    use crate::value::Value;
+
+    #[test]
+    fn compat_rt_test(){
+        let values = vec![
+            Value::BYTES(ByteBuf::from(vec![1u8, 2, 3])),
+            Value::FLOAT(OrderedFloat::from(1.5f32)),
+            Value::DOUBLE(OrderedFloat::from(-2.25f64)),
+        ];
+        let value = Value::LIST(values);
+        let bytes = ser::to_vec(&value).unwrap();
+        let out: Value = de::from_bytes(&bytes).unwrap();
+        assert_eq!(out, value);
+
+        let nan = OrderedFloat::from(f64::NAN);
+        assert_eq!(nan, OrderedFloat::from(f64::NAN));
+        assert!(OrderedFloat::from(f64::INFINITY) < nan);
+        assert_eq!(OrderedFloat::from(0.0f64), OrderedFloat::from(-0.0f64));
+    }
+}
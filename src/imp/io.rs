@@ -1,12 +1,17 @@
 use crate::error::{Error, ErrorCode, Result};
-use std::cmp;
+use core::cmp;
+use core::ops::Deref;
+#[cfg(feature = "std")]
+use std::io;
+
+use crate::lib::*;
 
 pub struct ByteReader<'a> {
     input: &'a [u8],
     bytes_read: usize
 }
 
-fn rdr_error<'a, T>(rdr: &'a mut ByteReader, reason: ErrorCode) -> Result<T> {
+fn rdr_error<T>(rdr: &ByteReader, reason: ErrorCode) -> Result<T> {
     let position: usize = rdr.get_bytes_read();
     Err(Error::syntax(reason, position))
 }
@@ -28,52 +33,220 @@ impl<'a> ByteReader<'a> {
         self.bytes_read += count;
     }
 
-    pub fn get_bytes_read(&self) -> usize {
-        self.bytes_read
-    }
-
-    pub fn reset(&mut self) {
-        self.bytes_read = 0
-    }
-
     // pub fn validateChecksum(&mut self) -> Result<()> {}
+}
 
-    pub fn read_u8(&mut self) -> Result<&u8> {
+impl<'a> IReadBytes<'a> for ByteReader<'a> {
+    fn read_u8(&mut self) -> Result<u8> {
         match self.input.get(self.bytes_read) {
             Some(byte) => {
                 self.notify_bytes_read(1);
-                Ok(byte)
+                Ok(*byte)
             }
             None => rdr_error(self, ErrorCode::Eof)
         }
     }
-    pub fn read_i8(&mut self) -> Result<i8> {
-        Ok(*self.read_u8()? as i8)
-    }
 
-    pub fn peek_u8(&mut self) -> Result<&u8> {
+    fn peek_u8(&mut self) -> Result<u8> {
         match self.input.get(self.bytes_read) {
             Some(byte) => {
-                Ok(byte)
+                Ok(*byte)
             }
             None => rdr_error(self, ErrorCode::Eof)
         }
     }
 
-    pub fn read_bytes(&mut self, length: usize) -> Result<&[u8]>{
+    fn read_bytes<'s>(&'s mut self, length: usize) -> Result<Reference<'a, 's, [u8]>>{
         if length == 0 {
             rdr_error(self, ErrorCode::ExpectedNonZeroReadLength) // hmm
         } else {
-            let end = self.bytes_read + length;
+            let end = self.bytes_read.saturating_add(length);
             if self.input.len() < end {
                 rdr_error(self, ErrorCode::Eof)
             } else {
                 let start = self.bytes_read;
                 self.notify_bytes_read(length);
-                let bytes: &[u8] = &self.input[start..end];
-                Ok(bytes)
+                let bytes: &'a [u8] = &self.input[start..end];
+                Ok(Reference::Borrowed(bytes))
+            }
+        }
+    }
+
+    fn get_bytes_read(&self) -> usize {
+        self.bytes_read
+    }
+
+    fn reset(&mut self) {
+        self.bytes_read = 0
+    }
+}
+
+/// Bytes read from an IReadBytes. Slices hand out bytes borrowed for the
+/// whole input lifetime, buffered readers can only lend out their buffer.
+pub enum Reference<'b, 'c, T: ?Sized + 'static> {
+    Borrowed(&'b T),
+    Copied(&'c T),
+}
+
+impl<'b, 'c, T: ?Sized + 'static> Deref for Reference<'b, 'c, T> {
+    type Target = T;
+
+    fn deref(&self) -> &Self::Target {
+        match *self {
+            Reference::Borrowed(b) => b,
+            Reference::Copied(c) => c,
+        }
+    }
+}
+
+pub trait IReadBytes<'de> {
+    fn read_u8(&mut self) -> Result<u8>;
+
+    fn read_i8(&mut self) -> Result<i8> {
+        Ok(self.read_u8()? as i8)
+    }
+
+    fn peek_u8(&mut self) -> Result<u8>;
+
+    fn read_bytes<'s>(&'s mut self, length: usize) -> Result<Reference<'de, 's, [u8]>>;
+
+    /// move past `length` bytes without looking at them
+    fn skip_bytes(&mut self, length: usize) -> Result<()> {
+        if 0 < length {
+            self.read_bytes(length)?;
+        }
+        Ok(())
+    }
+
+    fn get_bytes_read(&self) -> usize;
+
+    fn reset(&mut self);
+}
+
+const IO_BUFFER_SIZE: usize = 8 * 1024;
+
+/// IReadBytes over any io::Read. Bytes are pulled into a fixed size buffer as
+/// they are needed, so memory stays bounded by the buffer and the largest single
+/// string or byte array in the input.
+#[cfg(feature = "std")]
+pub struct IoReader<R> {
+    rdr: R,
+    buf: Vec<u8>,
+    pos: usize,
+    len: usize,
+    scratch: Vec<u8>,
+    bytes_read: usize
+}
+
+#[cfg(feature = "std")]
+impl<R: io::Read> IoReader<R> {
+    pub fn new(rdr: R) -> Self {
+        IoReader {
+            rdr,
+            buf: vec![0; IO_BUFFER_SIZE],
+            pos: 0,
+            len: 0,
+            scratch: Vec::new(),
+            bytes_read: 0
+        }
+    }
+
+    pub fn into_inner(self) -> R {
+        self.rdr
+    }
+
+    fn io_error<T>(&self, err: io::Error) -> Result<T> {
+        if err.kind() == io::ErrorKind::UnexpectedEof {
+            Err(Error::syntax(ErrorCode::Eof, self.bytes_read))
+        } else {
+            Err(Error::syntax(ErrorCode::Io(err), self.bytes_read))
+        }
+    }
+
+    /// make sure at least one byte is buffered, false at eof
+    fn fill(&mut self) -> Result<bool> {
+        while self.pos == self.len {
+            match self.rdr.read(&mut self.buf) {
+                Ok(0) => return Ok(false),
+                Ok(n) => {
+                    self.pos = 0;
+                    self.len = n;
+                }
+                Err(ref err) if err.kind() == io::ErrorKind::Interrupted => {}
+                Err(err) => return self.io_error(err)
             }
         }
+        Ok(true)
+    }
+}
+
+#[cfg(feature = "std")]
+impl<'de, R: io::Read> IReadBytes<'de> for IoReader<R> {
+    fn read_u8(&mut self) -> Result<u8> {
+        let byte = self.peek_u8()?;
+        self.pos += 1;
+        self.bytes_read += 1;
+        Ok(byte)
+    }
+
+    fn peek_u8(&mut self) -> Result<u8> {
+        if self.fill()? {
+            Ok(self.buf[self.pos])
+        } else {
+            Err(Error::syntax(ErrorCode::Eof, self.bytes_read))
+        }
+    }
+
+    fn read_bytes<'s>(&'s mut self, length: usize) -> Result<Reference<'de, 's, [u8]>>{
+        if length == 0 {
+            return Err(Error::syntax(ErrorCode::ExpectedNonZeroReadLength, self.bytes_read))
+        }
+        if length <= self.len - self.pos {
+            let start = self.pos;
+            self.pos += length;
+            self.bytes_read += length;
+            return Ok(Reference::Copied(&self.buf[start..self.pos]))
+        }
+        // does not fit in what is buffered, assemble it in scratch space
+        self.scratch.clear();
+        self.scratch.extend_from_slice(&self.buf[self.pos..self.len]);
+        self.pos = self.len;
+        // lengths come from the input, so memory only grows with the bytes
+        // that actually arrive
+        let remaining = (length - self.scratch.len()) as u64;
+        let mut rest = io::Read::take(&mut self.rdr, remaining);
+        match io::Read::read_to_end(&mut rest, &mut self.scratch) {
+            Ok(_) if self.scratch.len() < length => {
+                return Err(Error::syntax(ErrorCode::Eof, self.bytes_read))
+            }
+            Ok(_) => {}
+            Err(err) => return self.io_error(err)
+        }
+        self.bytes_read += length;
+        Ok(Reference::Copied(&self.scratch))
+    }
+
+    /// skips through the buffer rather than assembling the bytes in scratch space
+    fn skip_bytes(&mut self, mut length: usize) -> Result<()> {
+        while 0 < length {
+            if !self.fill()? {
+                return Err(Error::syntax(ErrorCode::Eof, self.bytes_read))
+            }
+            let n = cmp::min(length, self.len - self.pos);
+            self.pos += n;
+            self.bytes_read += n;
+            length -= n;
+        }
+        Ok(())
+    }
+
+    fn get_bytes_read(&self) -> usize {
+        self.bytes_read
+    }
+
+    /// bytes already read cannot be read again, this only resets the count
+    fn reset(&mut self) {
+        self.bytes_read = 0
     }
 }
 
@@ -162,6 +335,102 @@ impl ByteWriter<Vec<u8>> {
     }
 }
 
+/// Appends to a caller owned vec. Bytes already in the vec are left alone and
+/// its spare capacity is reused, so one buffer can serve many serializers.
+impl IWriteBytes for ByteWriter<&mut Vec<u8>> {
+    fn write_u8(&mut self, byte: u8) -> Result<()> {
+        self.out.push(byte);
+        self.notify_bytes_written(1);
+        Ok(())
+    }
+
+    fn write_bytes(&mut self, bytes: &[u8], off: usize, len: usize) -> Result<()> {
+        self.out.extend_from_slice(&bytes[off .. off + len]);
+        self.notify_bytes_written(len);
+        Ok(())
+    }
+
+    fn get_bytes_written(&self) -> usize { self.bytes_written }
+
+    /// drops everything this writer appended
+    fn reset(&mut self){
+        let start = self.start();
+        self.out.truncate(start);
+        self.bytes_written = 0;
+    }
+}
+
+impl<'a> ByteWriter<&'a mut Vec<u8>> {
+    pub fn from_buffer(out: &'a mut Vec<u8>) -> Self {
+        ByteWriter {
+            bytes_written: 0,
+            out
+        }
+    }
+
+    /// where this writer started appending
+    fn start(&self) -> usize {
+        self.out.len() - self.bytes_written
+    }
+
+    /// the bytes written by this writer
+    pub fn written(&self) -> &[u8] {
+        &self.out[self.start()..]
+    }
+
+    pub fn notify_bytes_written(&mut self, count: usize) {
+        self.bytes_written += count;
+    }
+}
+
+/// Writes into a fixed caller provided slice, ie memory handed over by javascript.
+/// Running out of room is a BufferFull error at the position of the first byte
+/// that did not fit.
+impl IWriteBytes for ByteWriter<&mut [u8]> {
+    fn write_u8(&mut self, byte: u8) -> Result<()> {
+        match self.out.get_mut(self.bytes_written) {
+            Some(slot) => *slot = byte,
+            None => return Err(Error::syntax(ErrorCode::BufferFull, self.bytes_written))
+        }
+        self.notify_bytes_written(1);
+        Ok(())
+    }
+
+    fn write_bytes(&mut self, bytes: &[u8], off: usize, len: usize) -> Result<()> {
+        let end = self.bytes_written + len;
+        if self.out.len() < end {
+            return Err(Error::syntax(ErrorCode::BufferFull, self.out.len()))
+        }
+        self.out[self.bytes_written..end].copy_from_slice(&bytes[off .. off + len]);
+        self.notify_bytes_written(len);
+        Ok(())
+    }
+
+    fn get_bytes_written(&self) -> usize { self.bytes_written }
+
+    fn reset(&mut self){
+        self.bytes_written = 0;
+    }
+}
+
+impl<'a> ByteWriter<&'a mut [u8]> {
+    pub fn from_slice(out: &'a mut [u8]) -> Self {
+        ByteWriter {
+            bytes_written: 0,
+            out
+        }
+    }
+
+    /// the bytes written so far
+    pub fn written(&self) -> &[u8] {
+        &self.out[..self.bytes_written]
+    }
+
+    pub fn notify_bytes_written(&mut self, count: usize) {
+        self.bytes_written += count;
+    }
+}
+
 fn vec_write_byte(vec: &mut Vec<u8>, bytes_written: usize, byte: u8) {
     if bytes_written == 0 {
         if vec.len() == 0 {
@@ -196,6 +465,66 @@ fn vec_write_bytes(vec: &mut Vec<u8>, pos: usize, buf: &[u8]) {
 
 
 
+
+/// IWriteBytes over any io::Write. RawOutput writes a byte at a time, so
+/// unbuffered writers such as files and sockets should be wrapped in a BufWriter
+#[cfg(feature = "std")]
+pub struct IoWriter<W> {
+    out: W,
+    bytes_written: usize
+}
+
+#[cfg(feature = "std")]
+impl<W: io::Write> IWriteBytes for IoWriter<W> {
+    fn write_u8(&mut self, byte: u8) -> Result<()> {
+        self.write_all(&[byte])
+    }
+
+    fn write_bytes(&mut self, bytes: &[u8], off: usize, len: usize) -> Result<()> {
+        self.write_all(&bytes[off .. off + len])
+    }
+
+    fn get_bytes_written(&self) -> usize { self.bytes_written }
+
+    /// bytes already handed to the writer cannot be taken back, this only resets the count
+    fn reset(&mut self){
+        self.bytes_written = 0;
+    }
+}
+
+#[cfg(feature = "std")]
+impl<W: io::Write> IoWriter<W> {
+    pub fn new(out: W) -> Self {
+        IoWriter {
+            out,
+            bytes_written: 0
+        }
+    }
+
+    fn write_all(&mut self, buf: &[u8]) -> Result<()> {
+        match self.out.write_all(buf) {
+            Ok(()) => {
+                self.bytes_written += buf.len();
+                Ok(())
+            }
+            Err(err) => Err(Error::syntax(ErrorCode::Io(err), self.bytes_written))
+        }
+    }
+
+    pub fn flush(&mut self) -> Result<()> {
+        self.out.flush().map_err(|err| Error::syntax(ErrorCode::Io(err), self.bytes_written))
+    }
+
+    /// Gets a reference to the underlying writer
+    pub fn get_ref(&self) -> &W { &self.out }
+
+    /// Gets a mutable reference to the underlying writer
+    pub fn get_mut(&mut self) -> &mut W { &mut self.out }
+
+    pub fn into_inner(self) -> W { self.out }
+}
+
+
 mod test {
     #![allow(unused_imports)]
     use super::{*};
@@ -206,9 +535,9 @@ mod test {
         let mut rdr = ByteReader::from_vec(&data);
 
         assert_eq!(0, rdr.get_bytes_read());
-        assert_eq!(0, *rdr.read_u8().unwrap());
-        assert_eq!(1, *rdr.read_u8().unwrap());
-        assert_eq!(2, *rdr.read_u8().unwrap());
+        assert_eq!(0, rdr.read_u8().unwrap());
+        assert_eq!(1, rdr.read_u8().unwrap());
+        assert_eq!(2, rdr.read_u8().unwrap());
         assert_eq!(3, rdr.get_bytes_read());
         // assert_eq!(Err(Error::Eof(3)), rdr.read_u8());
         assert_eq!(3, rdr.get_bytes_read());
@@ -225,16 +554,16 @@ mod test {
         // assert_eq!(Err(Error::Eof(0)), rdr.read_bytes(6));
         let control: &[u8] = &[0,1];
         assert_eq!(0, rdr.get_bytes_read());
-        assert_eq!(control, rdr.read_bytes(2).unwrap());
+        assert_eq!(control, &*rdr.read_bytes(2).unwrap());
         assert_eq!(2, rdr.get_bytes_read());
         // assert_eq!(Err(Error::Eof(2)), rdr.read_bytes(4));
         let control: &[u8] = &[2,3,4];
-        assert_eq!(control, rdr.read_bytes(3).unwrap());
+        assert_eq!(control, &*rdr.read_bytes(3).unwrap());
         assert_eq!(5, rdr.get_bytes_read());
         // assert_eq!(Err(Error::Eof(5)), rdr.read_u8());
         rdr.reset();
         let control: &[u8] = &[0,1,2,3,4];
-        assert_eq!(control, rdr.read_bytes(5).unwrap());
+        assert_eq!(control, &*rdr.read_bytes(5).unwrap());
     }
 
     #[test]
@@ -1,6 +1,9 @@
 pub mod codes;
+pub mod compat;
 pub mod ranges;
 pub mod rawoutput;
 pub mod rawinput;
 pub mod io;
-pub mod cache;
\ No newline at end of file
+pub mod cache;
+pub mod walk;
+pub mod adler;
@@ -1,84 +1,85 @@
 #![allow(overflowing_literals)]
 
 use crate::imp::codes;
-use crate::imp::io::{ByteReader};
+use crate::imp::io::{IReadBytes, Reference};
 use crate::error::{Error, ErrorCode, Result};
+use crate::lib::*;
 
 use byteorder::*;
 
 #[derive(Clone, Debug)]
 pub struct RawInput;
 
-fn error<T>(rdr: &ByteReader, reason: ErrorCode) -> Result<T> {
+fn error<'de, R: IReadBytes<'de>, T>(rdr: &R, reason: ErrorCode) -> Result<T> {
     let position: usize = rdr.get_bytes_read();
     Err(Error::syntax(reason, position))
 }
 
-impl<'a> RawInput {
+impl<'de> RawInput {
 
-    fn read_raw_bytes(&mut self, reader: &'a mut ByteReader, length: usize) -> Result<&'a[u8]>
+    fn read_raw_bytes<'s, R: IReadBytes<'de>>(&mut self, reader: &'s mut R, length: usize) -> Result<Reference<'de, 's, [u8]>>
     {
         reader.read_bytes(length)
     }
 
-    fn read_raw_i16(&mut self, reader: &'a mut ByteReader) -> Result<i64>
+    fn read_raw_i16<R: IReadBytes<'de>>(&mut self, reader: &mut R) -> Result<i64>
     {
-        let high = *reader.read_u8()? as i64;
-        let low  = *reader.read_u8()? as i64;
+        let high = reader.read_u8()? as i64;
+        let low  = reader.read_u8()? as i64;
         Ok( (high << 8) + low)
     }
 
-    fn read_raw_i24(&mut self, reader: &'a mut ByteReader) -> Result<i64>
+    fn read_raw_i24<R: IReadBytes<'de>>(&mut self, reader: &mut R) -> Result<i64>
     {
-        let a = *reader.read_u8()? as i64;
-        let b = *reader.read_u8()? as i64;
-        let c = *reader.read_u8()? as i64;
+        let a = reader.read_u8()? as i64;
+        let b = reader.read_u8()? as i64;
+        let c = reader.read_u8()? as i64;
         Ok((a << 16) + (b << 8) + c)
     }
 
-    fn read_raw_i32(&mut self, reader: &'a mut ByteReader) -> Result<i64>
+    fn read_raw_i32<R: IReadBytes<'de>>(&mut self, reader: &mut R) -> Result<i64>
     {
-        let a = *reader.read_u8()? as i64 & std::u8::MAX as i64;
-        let b = *reader.read_u8()? as i64 & std::u8::MAX as i64;
-        let c = *reader.read_u8()? as i64 & std::u8::MAX as i64;
-        let d = *reader.read_u8()? as i64 & std::u8::MAX as i64;
-        Ok( ((a << 24) | (b << 16) | (c << 8) | d) & std::u32::MAX as i64)
+        let a = reader.read_u8()? as i64 & core::u8::MAX as i64;
+        let b = reader.read_u8()? as i64 & core::u8::MAX as i64;
+        let c = reader.read_u8()? as i64 & core::u8::MAX as i64;
+        let d = reader.read_u8()? as i64 & core::u8::MAX as i64;
+        Ok( ((a << 24) | (b << 16) | (c << 8) | d) & core::u32::MAX as i64)
     }
 
-    fn read_raw_i40(&mut self, reader: &'a mut ByteReader) -> Result<i64>
+    fn read_raw_i40<R: IReadBytes<'de>>(&mut self, reader: &mut R) -> Result<i64>
     {
-        let high = *reader.read_u8()? as i64;
+        let high = reader.read_u8()? as i64;
         let low = self.read_raw_i32(reader)?;
         Ok( (high << 32) + low )
     }
 
-    fn read_raw_i48(&mut self, reader: &'a mut ByteReader) -> Result<i64>
+    fn read_raw_i48<R: IReadBytes<'de>>(&mut self, reader: &mut R) -> Result<i64>
     {
-        let high = *reader.read_u8()? as i64;
+        let high = reader.read_u8()? as i64;
         let low = self.read_raw_i40(reader)?;
         Ok( (high << 40) + low )
     }
 
-    fn read_raw_i64(&mut self, reader: &'a mut ByteReader) -> Result<i64>
+    fn read_raw_i64<R: IReadBytes<'de>>(&mut self, reader: &mut R) -> Result<i64>
     {
-        let a = *reader.read_u8()? as i64 & std::u8::MAX as i64;
-        let b = *reader.read_u8()? as i64 & std::u8::MAX as i64;
-        let c = *reader.read_u8()? as i64 & std::u8::MAX as i64;
-        let d = *reader.read_u8()? as i64 & std::u8::MAX as i64;
-        let e = *reader.read_u8()? as i64 & std::u8::MAX as i64;
-        let f = *reader.read_u8()? as i64 & std::u8::MAX as i64;
-        let g = *reader.read_u8()? as i64 & std::u8::MAX as i64;
-        let h = *reader.read_u8()? as i64 & std::u8::MAX as i64;
+        let a = reader.read_u8()? as i64 & core::u8::MAX as i64;
+        let b = reader.read_u8()? as i64 & core::u8::MAX as i64;
+        let c = reader.read_u8()? as i64 & core::u8::MAX as i64;
+        let d = reader.read_u8()? as i64 & core::u8::MAX as i64;
+        let e = reader.read_u8()? as i64 & core::u8::MAX as i64;
+        let f = reader.read_u8()? as i64 & core::u8::MAX as i64;
+        let g = reader.read_u8()? as i64 & core::u8::MAX as i64;
+        let h = reader.read_u8()? as i64 & core::u8::MAX as i64;
         Ok( (a << 56) | (b << 48) | (c << 40) | (d << 32) | (e << 24) | (f << 16) | (g << 8) | h )
     }
 
-    pub fn read_int(&mut self, reader: &'a mut ByteReader) -> Result<i64>
+    pub fn read_int<R: IReadBytes<'de>>(&mut self, reader: &mut R) -> Result<i64>
     {
-        let code = *reader.read_u8()?;
+        let code = reader.read_u8()?;
         self.read_int_code(reader,code as i8)
     }
 
-    pub fn read_int_code(&mut self, reader: &'a mut ByteReader, code: i8) -> Result<i64>{
+    pub fn read_int_code<R: IReadBytes<'de>>(&mut self, reader: &mut R, code: i8) -> Result<i64>{
         match code {
             0xFF => {
                 Ok(-1)
@@ -90,7 +91,7 @@ impl<'a> RawInput {
             // 64 to 95
             0x40..=0x5F => {
                 let packing = ((code - codes::INT_PACKED_2_ZERO as i8) as i64) << 8;
-                let r = *reader.read_u8()? as i64;
+                let r = reader.read_u8()? as i64;
                 Ok(packing | r)
             }
             // 96 to 111
@@ -131,32 +132,51 @@ impl<'a> RawInput {
         }
     }
 
-    pub fn read_next_code(&mut self, reader: &'a mut ByteReader) -> Result<i8> {
+    pub fn read_next_code<R: IReadBytes<'de>>(&mut self, reader: &mut R) -> Result<i8> {
         reader.read_i8()
     }
 
-    pub fn peek_next_code(&mut self, reader: &'a mut ByteReader) -> Result<i8> {
-        Ok( *reader.peek_u8()? as i8)
+    pub fn peek_next_code<R: IReadBytes<'de>>(&mut self, reader: &mut R) -> Result<i8> {
+        Ok( reader.peek_u8()? as i8)
     }
 
-    pub fn read_count(&mut self, reader: &'a mut ByteReader) -> Result<i32> {
+    /// read a footer, starting at its first byte. Returns the length and checksum
+    pub fn read_footer<R: IReadBytes<'de>>(&mut self, reader: &mut R) -> Result<(i64, i64)> {
+        let position = reader.get_bytes_read();
+        let magic = self.read_raw_i32(reader)?;
+        if magic != codes::FOOTER_MAGIC {
+            return Err(Error::syntax(ErrorCode::InvalidFooter, position))
+        }
+        let length = self.read_raw_i32(reader)?;
+        let checksum = self.read_raw_i32(reader)?;
+        Ok((length, checksum))
+    }
+
+    /// lengths come from the input, so a negative one is an error rather than a huge usize
+    pub fn read_count<R: IReadBytes<'de>>(&mut self, reader: &mut R) -> Result<i32> {
         //////// coercion to i32 seems pointlessly complicated
-        Ok(self.read_int(reader)? as i32)
+        let position = reader.get_bytes_read();
+        let count = self.read_int(reader)?;
+        if count < 0 || (i32::MAX as i64) < count {
+            Err(Error::syntax(ErrorCode::Expectedi64, position))
+        } else {
+            Ok(count as i32)
+        }
     }
 
-    fn read_raw_float(&mut self, reader: &'a mut ByteReader) -> Result<f32> {
+    fn read_raw_float<R: IReadBytes<'de>>(&mut self, reader: &mut R) -> Result<f32> {
         let bytes = reader.read_bytes(4)?;
-        let f = byteorder::BigEndian::read_f32(bytes);
+        let f = byteorder::BigEndian::read_f32(&bytes);
         Ok(f)
     }
 
-    fn read_raw_double(&mut self, reader: &'a mut ByteReader) -> Result<f64> {
+    fn read_raw_double<R: IReadBytes<'de>>(&mut self, reader: &mut R) -> Result<f64> {
         let bytes = reader.read_bytes(8)?;
-        let d = byteorder::BigEndian::read_f64(bytes);
+        let d = byteorder::BigEndian::read_f64(&bytes);
         Ok(d)
     }
 
-    pub fn read_double_code(&mut self, reader: &'a mut ByteReader, code: i8) -> Result<f64> {
+    pub fn read_double_code<R: IReadBytes<'de>>(&mut self, reader: &mut R, code: i8) -> Result<f64> {
         match code as u8 {
             codes::DOUBLE => {
                 self.read_raw_double(reader)
@@ -172,12 +192,12 @@ impl<'a> RawInput {
     }
 
     #[allow(unused)]
-    pub fn read_double(&mut self, reader:&'a mut ByteReader) -> Result<f64> {
-        let code = *reader.read_u8()?;
+    pub fn read_double<R: IReadBytes<'de>>(&mut self, reader: &mut R) -> Result<f64> {
+        let code = reader.read_u8()?;
         self.read_double_code(reader, code as i8)
     }
 
-    pub fn read_float_code(&mut self, reader: &'a mut ByteReader, code: i8) -> Result<f32> {
+    pub fn read_float_code<R: IReadBytes<'de>>(&mut self, reader: &mut R, code: i8) -> Result<f32> {
         match code as u8 {
             codes::FLOAT => {
                 self.read_raw_float(reader)
@@ -187,12 +207,12 @@ impl<'a> RawInput {
     }
 
     #[allow(unused)]
-    pub fn read_float(&mut self, reader: &'a mut ByteReader) -> Result<f32> {
-        let code = *reader.read_u8()?;
+    pub fn read_float<R: IReadBytes<'de>>(&mut self, reader: &mut R) -> Result<f32> {
+        let code = reader.read_u8()?;
         self.read_float_code(reader,code as i8)
     }
 
-    pub fn read_boolean_code(&mut self, reader: &'a mut ByteReader, code: i8) -> Result<bool> {
+    pub fn read_boolean_code<R: IReadBytes<'de>>(&mut self, reader: &mut R, code: i8) -> Result<bool> {
         match code as u8 {
             codes::TRUE => {
                 Ok(true)
@@ -205,19 +225,19 @@ impl<'a> RawInput {
     }
 
     #[allow(unused)]
-    pub fn read_boolean(&mut self, reader: &'a mut ByteReader) -> Result<bool> {
+    pub fn read_boolean<R: IReadBytes<'de>>(&mut self, reader: &mut R) -> Result<bool> {
         let code = reader.read_i8()?;
         self.read_boolean_code(reader, code)
     }
 
     #[allow(unused)]
-    fn internal_read_chunked_bytes(&mut self, reader: &'a mut ByteReader) -> Result<Vec<u8>> {
+    fn internal_read_chunked_bytes<R: IReadBytes<'de>>(&mut self, reader: &mut R) -> Result<Vec<u8>> {
         let mut buffer: Vec<u8> = Vec::with_capacity(65536);
         let mut code: u8 = codes::BYTES_CHUNK;
         while code == codes::BYTES_CHUNK {
             let count = self.read_count(reader)?;
-            buffer.extend_from_slice(self.read_raw_bytes(reader, count as usize)?);
-            code = *reader.read_u8()?;
+            buffer.extend_from_slice(&self.read_raw_bytes(reader, count as usize)?);
+            code = reader.read_u8()?;
         }
         if code != codes::BYTES {
             error(reader, ErrorCode::ExpectedChunkBytesConclusion)
@@ -227,7 +247,7 @@ impl<'a> RawInput {
     }
 
     // this will need to return some wrapper over &[u8] + vec<u8> to support chunked bytes
-    pub fn read_bytes_code(&mut self, reader: &'a mut ByteReader, code: i8) -> Result<&'a[u8]> {
+    pub fn read_bytes_code<'s, R: IReadBytes<'de>>(&mut self, reader: &'s mut R, code: i8) -> Result<Reference<'de, 's, [u8]>> {
         match code as u8 {
             codes::BYTES_PACKED_LENGTH_START..=codes::BYTES_PACKED_LENGTH_END => {
                 self.read_raw_bytes(reader, (code as u8 - codes::BYTES_PACKED_LENGTH_START) as usize)
@@ -245,72 +265,86 @@ impl<'a> RawInput {
 
     // this reads of the `fressian bytes` value type, not literal bytes from the reader.
     #[inline]
-    pub fn read_bytes(&mut self, reader: &'a mut ByteReader) -> Result<&'a [u8]> {
-        let code = *reader.read_u8()?;
+    pub fn read_bytes<'s, R: IReadBytes<'de>>(&mut self, reader: &'s mut R) -> Result<Reference<'de, 's, [u8]>> {
+        let code = reader.read_u8()?;
         self.read_bytes_code(reader, code as i8)
     }
 
     /// could this be -> &'a str ?
     #[inline]
-    pub fn read_raw_utf8(&mut self, reader: &'a mut ByteReader, length: usize) -> Result<String> {
+    pub fn read_raw_utf8<R: IReadBytes<'de>>(&mut self, reader: &mut R, length: usize) -> Result<String> {
         // let length = self.read_count()?;
         if  length == 0 {
             Ok("".to_string())
         } else {
             let bytes = self.read_raw_bytes(reader, length)?;
             let s: &str = unsafe {
-                std::str::from_utf8_unchecked(bytes)
+                core::str::from_utf8_unchecked(&bytes)
             };
             Ok(s.to_string())
         }
     }
 
-    pub fn read_fressian_string(&mut self, reader: &'a mut ByteReader, length: usize) -> Result<String> {
+    pub fn read_fressian_string<R: IReadBytes<'de>>(&mut self, reader: &mut R, length: usize) -> Result<String> {
         if length == 0 {
             Ok("".to_string())
         } else {
             let bytes_read = reader.get_bytes_read();
             let bytes = self.read_raw_bytes(reader, length)?;
-            let length = bytes.len();
-            let mut buf: Vec<u16> = Vec::with_capacity(length); //prob min ascii is good guess?
-            let mut pos = 0;
-            // let mut res: Result<()> = Ok(());
-
-            while pos < length  { //&& res.is_ok()
-                let ch = bytes[pos] & 0xff;
-                pos += 1;
-                match ch >> 4 {
-                    0..=7 => {
-                        buf.push(ch as u16)
-                    }
-                    12 | 13 => {
-                        let ch0 = ch as u32;
-                        let ch1 = bytes[pos] as u32 & 0xff;
-                        pos += 1;
-                        let n =  (ch0 & 0x1f as u32) << 6 | (ch1 & 0x3f as u32);
-                        buf.push(n as u16)
-                    }
-                    14 => {
-                        let ch0 = ch as u32;
-                        let ch1 = bytes[pos] as u32;
-                        let ch2 = bytes[pos + 1] as u32;
-                        pos += 2;
-                        let n: u32 = (ch0 & 0x0f) << 12 | (ch1 & 0x3f) << 6 | ch2 & 0x3f;
-                        buf.push(n as u16)
+            self.decode_fressian_string(&bytes, bytes_read)
+        }
+    }
+
+    /// decode the modified utf8 payload of a STRING. `bytes_read` is the
+    /// position of the payload, for errors
+    pub fn decode_fressian_string(&mut self, bytes: &[u8], bytes_read: usize) -> Result<String> {
+        let length = bytes.len();
+        let mut buf: Vec<u16> = Vec::with_capacity(length); //prob min ascii is good guess?
+        let mut pos = 0;
+        // let mut res: Result<()> = Ok(());
+
+        let truncated = || Err(Error::syntax(ErrorCode::InvalidUTF8, length + bytes_read));
+
+        while pos < length  { //&& res.is_ok()
+            let ch = bytes[pos] & 0xff;
+            pos += 1;
+            match ch >> 4 {
+                0..=7 => {
+                    buf.push(ch as u16)
+                }
+                12 | 13 => {
+                    if length < pos + 1 {
+                        return truncated()
                     }
-                    _ => {
-                        return Err(Error::syntax(ErrorCode::InvalidUTF8, length + bytes_read))
+                    let ch0 = ch as u32;
+                    let ch1 = bytes[pos] as u32 & 0xff;
+                    pos += 1;
+                    let n =  (ch0 & 0x1f as u32) << 6 | (ch1 & 0x3f as u32);
+                    buf.push(n as u16)
+                }
+                14 => {
+                    if length < pos + 2 {
+                        return truncated()
                     }
+                    let ch0 = ch as u32;
+                    let ch1 = bytes[pos] as u32;
+                    let ch2 = bytes[pos + 1] as u32;
+                    pos += 2;
+                    let n: u32 = (ch0 & 0x0f) << 12 | (ch1 & 0x3f) << 6 | ch2 & 0x3f;
+                    buf.push(n as u16)
                 }
-            };
+                _ => {
+                    return Err(Error::syntax(ErrorCode::InvalidUTF8, length + bytes_read))
+                }
+            }
+        };
 
-            String::from_utf16(buf.as_slice())
-              .or(Err(Error::syntax(ErrorCode::InvalidUTF8, length + bytes_read)))
-        }
+        String::from_utf16(buf.as_slice())
+          .or(Err(Error::syntax(ErrorCode::InvalidUTF8, length + bytes_read)))
     }
 
-    pub fn read_string(&mut self, rdr: &'a mut ByteReader) -> Result<String> {
-        let code = *rdr.read_u8()?;
+    pub fn read_string<R: IReadBytes<'de>>(&mut self, rdr: &mut R) -> Result<String> {
+        let code = rdr.read_u8()?;
         match code {
             codes::STRING_PACKED_LENGTH_START..=codes::STRING_PACKED_LENGTH_END => {
                 RawInput.read_fressian_string(rdr, (code - codes::STRING_PACKED_LENGTH_START) as usize)
@@ -338,6 +372,8 @@ impl<'a> RawInput {
 mod test {
     #[allow(unused_imports)]
     use super::*;
+    #[allow(unused_imports)]
+    use crate::imp::io::ByteReader;
 
     #[test]
     fn read_raw_ints_test(){
@@ -506,14 +542,14 @@ mod test {
         let data: Vec<u8> = vec![213, 254, 255, 0, 1, 2];
         let control: &[u8] = &[254, 255, 0, 1, 2];
         let mut rdr = ByteReader::from_vec(&data);
-        assert_eq!(control, RawInput.read_bytes(&mut rdr).unwrap());
+        assert_eq!(control, &*RawInput.read_bytes(&mut rdr).unwrap());
 
         // {:form "(byte-array [-4 -3 -2 -1 0 1 2 3 4])", :bytes [-39 9 -4 -3 -2 -1 0 1 2 3 4], :ubytes [217 9 252 253 254 255 0 1 2 3 4], :byte-count 11, :footer false, :input [-4 -3 -2 -1 0 1 2 3 4]}
         // unpacked length
         let data: Vec<u8> = vec![217, 9, 252, 253, 254, 255, 0, 1, 2, 3, 4];
         let control: &[u8] = &[252, 253, 254, 255, 0, 1, 2, 3, 4];
         let mut rdr = ByteReader::from_vec(&data);
-        assert_eq!(control, RawInput.read_bytes(&mut rdr).unwrap());
+        assert_eq!(control, &*RawInput.read_bytes(&mut rdr).unwrap());
 
         //missing packed bytes
     }
@@ -11,7 +11,8 @@ use crate::error::{Result};
 use crate::imp::io::{IWriteBytes};
 use crate::imp::codes;
 use crate::imp::ranges;
-use std::cmp;
+use core::cmp;
+use crate::lib::*;
 
 #[derive(Clone, Debug)]
 pub struct RawOutput;
@@ -0,0 +1,337 @@
+use crate::imp::codes;
+use crate::imp::io::{ByteReader, IReadBytes};
+use crate::imp::rawinput::RawInput;
+use crate::error::{Error, ErrorCode, Result};
+use crate::lib::*;
+
+/// how deeply values may nest, so that malformed input cannot exhaust the stack
+pub(crate) const MAX_DEPTH: usize = 512;
+
+/// What the walker found. Every event is passed along with the bytes it covers.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Event {
+    /// a value without children ie numbers, strings, bytes, booleans and null
+    Scalar(u8),
+    /// a value with children. `count` is None for closed and open lists
+    Begin { code: u8, count: Option<usize> },
+    End(u8),
+    /// PUT_PRIORITY_CACHE, the next value is stored at `index`
+    PutCache(usize),
+    /// PRECACHE, the next value is stored at `index` and then skipped by readers
+    Precache(usize),
+    /// the value stored at `index` has been walked
+    Cached(usize),
+    /// a packed or GET_PRIORITY_CACHE reference
+    CacheRef(usize),
+    /// STRUCTTYPE. the tag value follows, then StructFields
+    StructType,
+    /// the field count of a new struct type, the fields follow and then End(STRUCTTYPE)
+    StructFields { index: usize, fields: usize },
+    /// a packed or STRUCT reference, the fields follow and then End(STRUCTTYPE)
+    StructRef { index: usize, fields: usize },
+    ResetCaches,
+    Footer { length: usize, checksum: u32 },
+}
+
+pub trait Visit<'a> {
+    fn visit(&mut self, event: Event, position: usize, bytes: &'a [u8]) -> Result<()>;
+}
+
+/// Walks the structure of fressian bytes without building values. The walker
+/// tracks how many entries the priority cache holds and the field counts of
+/// struct types, which is all it needs to know where each value ends. Values
+/// nested more than 512 deep are an error rather than a stack overflow.
+pub struct Walker<'a> {
+    input: &'a [u8],
+    rdr: ByteReader<'a>,
+    priority_count: usize,
+    struct_fields: Vec<usize>,
+    depth: usize,
+}
+
+impl<'a> Walker<'a> {
+    pub fn new(input: &'a [u8]) -> Self {
+        Walker {
+            input,
+            rdr: ByteReader::new(input),
+            priority_count: 0,
+            struct_fields: Vec::new(),
+            depth: 0,
+        }
+    }
+
+    pub fn position(&self) -> usize {
+        self.rdr.get_bytes_read()
+    }
+
+    pub fn is_done(&self) -> bool {
+        self.input.len() <= self.position()
+    }
+
+    /// walk every top level value and footer in the input
+    pub fn walk_all<V: Visit<'a>>(&mut self, v: &mut V) -> Result<()> {
+        while !self.is_done() {
+            self.walk_next(v)?;
+        }
+        Ok(())
+    }
+
+    /// walk a single top level value, or a footer
+    pub fn walk_next<V: Visit<'a>>(&mut self, v: &mut V) -> Result<()> {
+        if self.rdr.peek_u8()? == codes::FOOTER {
+            self.walk_footer(v)
+        } else {
+            self.walk_value(v)
+        }
+    }
+
+    fn walk_footer<V: Visit<'a>>(&mut self, v: &mut V) -> Result<()> {
+        let start = self.position();
+        let (length, checksum) = RawInput.read_footer(&mut self.rdr)?;
+        // java resets its caches after writing a footer
+        self.reset_caches();
+        self.visit(v, Event::Footer { length: length as usize, checksum: checksum as u32 }, start)
+    }
+
+    pub fn walk_value<V: Visit<'a>>(&mut self, v: &mut V) -> Result<()> {
+        if MAX_DEPTH <= self.depth {
+            return Err(Error::syntax(ErrorCode::DepthLimitExceeded, self.position()))
+        }
+        self.depth += 1;
+        let walked = self.walk_code(v);
+        self.depth -= 1;
+        walked
+    }
+
+    fn walk_code<V: Visit<'a>>(&mut self, v: &mut V) -> Result<()> {
+        let start = self.position();
+        let code = self.rdr.read_u8()?;
+        match code {
+            codes::NULL
+            | codes::TRUE
+            | codes::FALSE
+            | codes::DOUBLE_0
+            | codes::DOUBLE_1 => {
+                self.visit(v, Event::Scalar(code), start)
+            }
+
+            codes::INT | 0x00..=0x7F | 0xFF => {
+                RawInput.read_int_code(&mut self.rdr, code as i8)?;
+                self.visit(v, Event::Scalar(code), start)
+            }
+
+            codes::FLOAT => {
+                self.skip(4)?;
+                self.visit(v, Event::Scalar(code), start)
+            }
+
+            codes::DOUBLE => {
+                self.skip(8)?;
+                self.visit(v, Event::Scalar(code), start)
+            }
+
+            codes::BYTES_PACKED_LENGTH_START..=0xD7 => {
+                self.skip((code - codes::BYTES_PACKED_LENGTH_START) as usize)?;
+                self.visit(v, Event::Scalar(code), start)
+            }
+
+            codes::STRING_PACKED_LENGTH_START..=0xE1 => {
+                self.skip((code - codes::STRING_PACKED_LENGTH_START) as usize)?;
+                self.visit(v, Event::Scalar(code), start)
+            }
+
+            codes::BYTES | codes::STRING | codes::UTF8 => {
+                let length = self.read_count()?;
+                self.skip(length)?;
+                self.visit(v, Event::Scalar(code), start)
+            }
+
+            codes::BYTES_CHUNK => {
+                self.skip_chunks(codes::BYTES_CHUNK, codes::BYTES)?;
+                self.visit(v, Event::Scalar(code), start)
+            }
+
+            codes::STRING_CHUNK => {
+                self.skip_chunks(codes::STRING_CHUNK, codes::STRING)?;
+                self.visit(v, Event::Scalar(code), start)
+            }
+
+            codes::LIST_PACKED_LENGTH_START..=0xEB => {
+                let count = (code - codes::LIST_PACKED_LENGTH_START) as usize;
+                self.walk_children(v, code, count, start)
+            }
+
+            codes::LIST
+            | codes::INT_ARRAY
+            | codes::LONG_ARRAY
+            | codes::FLOAT_ARRAY
+            | codes::DOUBLE_ARRAY
+            | codes::BOOLEAN_ARRAY
+            | codes::OBJECT_ARRAY => {
+                let count = self.read_count()?;
+                self.walk_children(v, code, count, start)
+            }
+
+            codes::BEGIN_CLOSED_LIST | codes::BEGIN_OPEN_LIST => {
+                self.visit(v, Event::Begin { code, count: None }, start)?;
+                loop {
+                    if self.is_done() && code == codes::BEGIN_OPEN_LIST {
+                        let end = self.position();
+                        return self.visit(v, Event::End(code), end)
+                    }
+                    if self.rdr.peek_u8()? == codes::END_COLLECTION {
+                        let end = self.position();
+                        self.rdr.read_u8()?;
+                        return self.visit(v, Event::End(code), end)
+                    }
+                    self.walk_value(v)?;
+                }
+            }
+
+            codes::MAP
+            | codes::SET
+            | codes::INST
+            | codes::UUID
+            | codes::URI
+            | codes::REGEX
+            | codes::BIGINT
+            | codes::ERROR => {
+                self.walk_children(v, code, 1, start)
+            }
+
+            codes::KEY | codes::SYM | codes::BIGDEC | codes::META => {
+                self.walk_children(v, code, 2, start)
+            }
+
+            codes::PUT_PRIORITY_CACHE => {
+                // the index is reserved before the value is read, same as java
+                let index = self.priority_count;
+                self.priority_count += 1;
+                self.visit(v, Event::PutCache(index), start)?;
+                self.walk_value(v)?;
+                let end = self.position();
+                self.visit(v, Event::Cached(index), end)
+            }
+
+            codes::PRECACHE => {
+                let index = self.priority_count;
+                self.priority_count += 1;
+                self.visit(v, Event::Precache(index), start)?;
+                self.walk_value(v)?;
+                let end = self.position();
+                self.visit(v, Event::Cached(index), end)?;
+                self.walk_value(v)
+            }
+
+            codes::PRIORITY_CACHE_PACKED_START..=0x9F => {
+                let index = (code - codes::PRIORITY_CACHE_PACKED_START) as usize;
+                self.cache_ref(v, index, start)
+            }
+
+            codes::GET_PRIORITY_CACHE => {
+                let index = self.read_count()?;
+                self.cache_ref(v, index, start)
+            }
+
+            codes::STRUCTTYPE => {
+                self.visit(v, Event::StructType, start)?;
+                // tag
+                self.walk_value(v)?;
+                let fields_start = self.position();
+                let fields = self.read_count()?;
+                let index = self.struct_fields.len();
+                self.struct_fields.push(fields);
+                self.visit(v, Event::StructFields { index, fields }, fields_start)?;
+                self.walk_fields(v, fields)
+            }
+
+            codes::STRUCT_CACHE_PACKED_START..=0xAF => {
+                let index = (code - codes::STRUCT_CACHE_PACKED_START) as usize;
+                self.struct_ref(v, index, start)
+            }
+
+            codes::STRUCT => {
+                let index = self.read_count()?;
+                self.struct_ref(v, index, start)
+            }
+
+            codes::RESET_CACHES => {
+                self.reset_caches();
+                self.visit(v, Event::ResetCaches, start)?;
+                self.walk_value(v)
+            }
+
+            _ => Err(Error::unmatched_code(code, start))
+        }
+    }
+
+    fn visit<V: Visit<'a>>(&mut self, v: &mut V, event: Event, start: usize) -> Result<()> {
+        let end = self.position();
+        v.visit(event, start, &self.input[start..end])
+    }
+
+    fn walk_children<V: Visit<'a>>(&mut self, v: &mut V, code: u8, count: usize, start: usize) -> Result<()> {
+        self.visit(v, Event::Begin { code, count: Some(count) }, start)?;
+        for _ in 0..count {
+            self.walk_value(v)?;
+        }
+        let end = self.position();
+        self.visit(v, Event::End(code), end)
+    }
+
+    fn walk_fields<V: Visit<'a>>(&mut self, v: &mut V, fields: usize) -> Result<()> {
+        for _ in 0..fields {
+            self.walk_value(v)?;
+        }
+        let end = self.position();
+        self.visit(v, Event::End(codes::STRUCTTYPE), end)
+    }
+
+    fn cache_ref<V: Visit<'a>>(&mut self, v: &mut V, index: usize, start: usize) -> Result<()> {
+        if self.priority_count <= index {
+            return Err(Error::syntax(ErrorCode::UnknownCacheRef(index), start))
+        }
+        self.visit(v, Event::CacheRef(index), start)
+    }
+
+    fn struct_ref<V: Visit<'a>>(&mut self, v: &mut V, index: usize, start: usize) -> Result<()> {
+        let fields = match self.struct_fields.get(index) {
+            Some(fields) => *fields,
+            None => return Err(Error::syntax(ErrorCode::UnknownStructRef(index), start))
+        };
+        self.visit(v, Event::StructRef { index, fields }, start)?;
+        self.walk_fields(v, fields)
+    }
+
+    fn reset_caches(&mut self) {
+        self.priority_count = 0;
+        self.struct_fields.clear();
+    }
+
+    fn read_count(&mut self) -> Result<usize> {
+        Ok(RawInput.read_count(&mut self.rdr)? as usize)
+    }
+
+    fn skip(&mut self, length: usize) -> Result<()> {
+        if 0 < length {
+            self.rdr.read_bytes(length)?;
+        }
+        Ok(())
+    }
+
+    fn skip_chunks(&mut self, chunk: u8, last: u8) -> Result<()> {
+        let mut code = chunk;
+        while code == chunk {
+            let length = self.read_count()?;
+            self.skip(length)?;
+            code = self.rdr.read_u8()?;
+        }
+        if code == last {
+            let length = self.read_count()?;
+            self.skip(length)
+        } else {
+            let position = self.position() - 1;
+            Err(Error::syntax(ErrorCode::ExpectedChunkBytesConclusion, position))
+        }
+    }
+}
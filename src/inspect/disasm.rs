@@ -0,0 +1,344 @@
+use core::fmt::{self, Display, Write};
+
+use crate::imp::codes;
+use crate::imp::io::{ByteReader, IReadBytes};
+use crate::imp::rawinput::RawInput;
+use crate::imp::walk::{Event, Visit, Walker};
+use crate::error::Result;
+use crate::inspect::code_name;
+use crate::lib::*;
+
+/// strings and cached values longer than this are cut short in labels
+const LABEL_WIDTH: usize = 40;
+
+/// bytes per line of hex in `render`
+const HEX_WIDTH: usize = 8;
+
+/// A run of bytes and what they are. Displays as the first byte and the
+/// label, ie `0xE5 LIST_PACKED len=1`
+#[derive(Clone, Debug, PartialEq)]
+pub struct Annotated<'a> {
+    pub position: usize,
+    pub bytes: &'a [u8],
+    /// how many collections the bytes are nested in
+    pub depth: usize,
+    pub label: String,
+}
+
+impl<'a> Display for Annotated<'a> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self.bytes.first() {
+            Some(b) => write!(f, "0x{:02X} {}", b, self.label),
+            None => f.write_str(&self.label)
+        }
+    }
+}
+
+/// The exact name of a code. Unlike `code_name` packed codes keep their
+/// packing, so 0xE5 is LIST_PACKED and 0x05 INT_PACKED_1
+pub fn code_label(code: u8) -> &'static str {
+    match code {
+        0x00..=0x3F | codes::INT_PACKED_1_START => "INT_PACKED_1",
+        codes::INT_PACKED_2_START..=0x5F => "INT_PACKED_2",
+        codes::INT_PACKED_3_START..=0x6F => "INT_PACKED_3",
+        codes::INT_PACKED_4_START..=0x73 => "INT_PACKED_4",
+        codes::INT_PACKED_5_START..=0x77 => "INT_PACKED_5",
+        codes::INT_PACKED_6_START..=0x7B => "INT_PACKED_6",
+        codes::INT_PACKED_7_START..=0x7F => "INT_PACKED_7",
+        codes::PRIORITY_CACHE_PACKED_START..=0x9F => "PRIORITY_CACHE_PACKED",
+        codes::STRUCT_CACHE_PACKED_START..=0xAF => "STRUCT_CACHE_PACKED",
+        codes::BYTES_PACKED_LENGTH_START..=0xD7 => "BYTES_PACKED",
+        codes::STRING_PACKED_LENGTH_START..=0xE1 => "STRING_PACKED",
+        codes::LIST_PACKED_LENGTH_START..=0xEB => "LIST_PACKED",
+        codes::BYTES_CHUNK => "BYTES_CHUNK",
+        codes::STRING_CHUNK => "STRING_CHUNK",
+        codes::UTF8 => "UTF8",
+        codes::GET_PRIORITY_CACHE => "GET_PRIORITY_CACHE",
+        codes::BEGIN_CLOSED_LIST => "BEGIN_CLOSED_LIST",
+        codes::BEGIN_OPEN_LIST => "BEGIN_OPEN_LIST",
+        codes::TRUE => "TRUE",
+        codes::FALSE => "FALSE",
+        codes::DOUBLE_0 => "DOUBLE_0",
+        codes::DOUBLE_1 => "DOUBLE_1",
+        code => code_name(code)
+    }
+}
+
+fn truncate(s: String) -> String {
+    match s.char_indices().nth(LABEL_WIDTH) {
+        Some((i, _)) => {
+            let mut short = s[..i].to_string();
+            short.push('…');
+            short
+        }
+        None => s
+    }
+}
+
+/// the value of a scalar, as it would be printed in EDN
+fn read_scalar(code: u8, bytes: &[u8]) -> Result<String> {
+    let mut rdr = ByteReader::new(bytes);
+    rdr.read_u8()?;
+    let s = match code {
+        codes::NULL => "nil".to_string(),
+        codes::TRUE | codes::FALSE => RawInput.read_boolean_code(&mut rdr, code as i8)?.to_string(),
+        codes::FLOAT => format!("{:?}", RawInput.read_float_code(&mut rdr, code as i8)?),
+        codes::DOUBLE | codes::DOUBLE_0 | codes::DOUBLE_1 => {
+            format!("{:?}", RawInput.read_double_code(&mut rdr, code as i8)?)
+        }
+        codes::INT | 0x00..=0x7F | 0xFF => RawInput.read_int_code(&mut rdr, code as i8)?.to_string(),
+        codes::STRING_PACKED_LENGTH_START..=0xE1 | codes::STRING | codes::UTF8 => {
+            let mut rdr = ByteReader::new(bytes);
+            format!("{:?}", RawInput.read_string(&mut rdr)?)
+        }
+        _ => format!("#{}", code_label(code))
+    };
+    Ok(s)
+}
+
+/// a value that was walked, or is being walked
+struct Frame {
+    code: u8,
+    items: Vec<String>,
+}
+
+/// Labels each event, and prints values as they are walked so that cache
+/// references can show what they point at
+struct Disassembler<'a> {
+    out: Vec<Annotated<'a>>,
+    stack: Vec<Frame>,
+    cache: BTreeMap<usize, String>,
+    precached: BTreeSet<usize>,
+    struct_tags: Vec<String>,
+}
+
+impl<'a> Disassembler<'a> {
+    fn push(&mut self, position: usize, bytes: &'a [u8], label: String) {
+        let depth = self.stack.len() - 1;
+        self.out.push(Annotated { position, bytes, depth, label });
+    }
+
+    fn item(&mut self, s: String) {
+        if let Some(frame) = self.stack.last_mut() {
+            frame.items.push(s);
+        }
+    }
+
+    fn open(&mut self, code: u8, items: Vec<String>) {
+        self.stack.push(Frame { code, items });
+    }
+
+    fn close(&mut self) {
+        if 1 < self.stack.len() {
+            let frame = self.stack.pop().unwrap();
+            let s = print_frame(frame);
+            self.item(s);
+        }
+    }
+}
+
+/// the innards of a printed list, ie `1 2` from `[1 2]`
+fn inner(items: &[String]) -> &str {
+    let s = items.first().map_or("", String::as_str);
+    if s.starts_with('[') && s.ends_with(']') {
+        &s[1..s.len() - 1]
+    } else {
+        s
+    }
+}
+
+fn print_frame(frame: Frame) -> String {
+    let items = frame.items;
+    match frame.code {
+        codes::KEY | codes::SYM => {
+            let prefix = if frame.code == codes::KEY { ":" } else { "" };
+            match (items.first().map(String::as_str), items.get(1)) {
+                (Some("nil"), Some(name)) => format!("{}{}", prefix, name.trim_matches('"')),
+                (Some(ns), Some(name)) => format!("{}{}/{}", prefix, ns.trim_matches('"'), name.trim_matches('"')),
+                _ => prefix.to_string()
+            }
+        }
+        codes::MAP => format!("{{{}}}", inner(&items)),
+        codes::SET => format!("#{{{}}}", inner(&items)),
+        codes::INST => format!("#inst {}", items.join(" ")),
+        codes::UUID => "#uuid".to_string(),
+        codes::URI => format!("#uri {}", items.join(" ")),
+        codes::REGEX => format!("#regex {}", items.join(" ")),
+        codes::STRUCTTYPE => {
+            let mut items = items.into_iter();
+            let tag = items.next().unwrap_or_default();
+            format!("#{} [{}]", tag.trim_matches('"'), items.collect::<Vec<_>>().join(" "))
+        }
+        codes::LIST_PACKED_LENGTH_START..=codes::LIST
+        | codes::BEGIN_CLOSED_LIST
+        | codes::BEGIN_OPEN_LIST => format!("[{}]", items.join(" ")),
+        code => format!("#{} [{}]", code_name(code), items.join(" "))
+    }
+}
+
+impl<'a> Visit<'a> for Disassembler<'a> {
+    fn visit(&mut self, event: Event, position: usize, bytes: &'a [u8]) -> Result<()> {
+        let code = bytes.first().cloned().unwrap_or(codes::END_COLLECTION);
+        // top level values are not needed once they are walked, apart from
+        // the last one, which a cache put around it still has to record
+        if self.stack.len() == 1 && !matches!(event, Event::Cached(_)) {
+            self.stack[0].items.clear();
+        }
+        match event {
+            Event::Scalar(code) => {
+                let value = read_scalar(code, bytes)?;
+                let label = match code {
+                    codes::STRING_PACKED_LENGTH_START..=0xE1
+                    | codes::BYTES_PACKED_LENGTH_START..=0xD7
+                    | codes::STRING
+                    | codes::BYTES
+                    | codes::UTF8 => {
+                        let mut rdr = ByteReader::new(bytes);
+                        rdr.read_u8()?;
+                        let length = match code {
+                            codes::STRING | codes::BYTES | codes::UTF8 => RawInput.read_int(&mut rdr)? as usize,
+                            _ => bytes.len() - 1
+                        };
+                        match code_name(code) {
+                            "BYTES" => format!("{} len={}", code_label(code), length),
+                            _ => format!("{} len={} {}", code_label(code), length, truncate(value.clone()))
+                        }
+                    }
+                    codes::BYTES_CHUNK | codes::STRING_CHUNK => {
+                        format!("{} total={}", code_label(code), bytes.len())
+                    }
+                    _ => format!("{} {}", code_label(code), truncate(value.clone()))
+                };
+                self.push(position, bytes, label);
+                self.item(value);
+            }
+            Event::Begin { code, count } => {
+                let label = match (code, count) {
+                    (codes::LIST_PACKED_LENGTH_START..=codes::LIST, Some(count))
+                    | (codes::LONG_ARRAY..=codes::OBJECT_ARRAY, Some(count)) => {
+                        format!("{} len={}", code_label(code), count)
+                    }
+                    _ => code_label(code).to_string()
+                };
+                self.push(position, bytes, label);
+                self.open(code, Vec::new());
+            }
+            Event::End(_) => {
+                self.close();
+                // only closed and open lists end with a code
+                if !bytes.is_empty() {
+                    self.push(position, bytes, "END_COLLECTION".to_string());
+                }
+            }
+            Event::PutCache(index) => {
+                self.push(position, bytes, format!("PUT_PRIORITY_CACHE #{}", index));
+            }
+            Event::Precache(index) => {
+                self.precached.insert(index);
+                self.push(position, bytes, format!("PRECACHE #{}", index));
+            }
+            Event::Cached(index) => {
+                let frame = self.stack.last_mut().unwrap();
+                let value = if self.precached.remove(&index) {
+                    // readers skip a precached value
+                    frame.items.pop()
+                } else {
+                    frame.items.last().cloned()
+                };
+                self.cache.insert(index, value.unwrap_or_default());
+            }
+            Event::CacheRef(index) => {
+                let value = self.cache.get(&index).cloned().unwrap_or_default();
+                let label = if code == codes::GET_PRIORITY_CACHE {
+                    format!("GET_PRIORITY_CACHE #{} → {}", index, truncate(value.clone()))
+                } else {
+                    format!("cache ref #{} → {}", index, truncate(value.clone()))
+                };
+                self.push(position, bytes, label);
+                self.item(value);
+            }
+            Event::StructType => {
+                self.push(position, bytes, "STRUCTTYPE".to_string());
+                self.open(codes::STRUCTTYPE, Vec::new());
+            }
+            Event::StructFields { index, fields } => {
+                let tag = self.stack.last().and_then(|frame| frame.items.first()).cloned().unwrap_or_default();
+                self.push(position, bytes, format!("struct #{} {} fields={}", index, truncate(tag.clone()), fields));
+                if self.struct_tags.len() <= index {
+                    self.struct_tags.resize(index + 1, String::new());
+                }
+                self.struct_tags[index] = tag;
+            }
+            Event::StructRef { index, fields } => {
+                let tag = self.struct_tags.get(index).cloned().unwrap_or_default();
+                self.push(position, bytes, format!("{} #{} {} fields={}", code_label(code), index, truncate(tag.clone()), fields));
+                self.open(codes::STRUCTTYPE, vec![tag]);
+            }
+            Event::ResetCaches => {
+                self.cache.clear();
+                self.struct_tags.clear();
+                self.push(position, bytes, "RESET_CACHES".to_string());
+            }
+            Event::Footer { length, checksum } => {
+                self.cache.clear();
+                self.struct_tags.clear();
+                self.push(position, bytes, format!("FOOTER length={} checksum=0x{:08X}", length, checksum));
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Label every byte of `bytes`, in order. Bytes that cannot be read end the
+/// listing with a single annotation naming the error, so a bad tail still
+/// shows up
+pub fn disassemble(bytes: &[u8]) -> Vec<Annotated<'_>> {
+    let mut d = Disassembler {
+        out: Vec::new(),
+        stack: vec![Frame { code: codes::BEGIN_OPEN_LIST, items: Vec::new() }],
+        cache: BTreeMap::new(),
+        precached: BTreeSet::new(),
+        struct_tags: Vec::new(),
+    };
+    let mut walker = Walker::new(bytes);
+    if let Err(err) = walker.walk_all(&mut d) {
+        let covered = d.out.last().map_or(0, |a| a.position + a.bytes.len());
+        let depth = d.out.last().map_or(0, |a| a.depth);
+        let label = format!("error: {} at byte {}", err.err.code, err.err.position);
+        d.out.push(Annotated { position: covered, bytes: &bytes[covered..], depth, label });
+    }
+    d.out
+}
+
+/// Lay the annotations out as an offset, the bytes in hex and the label,
+/// indented by depth. Long runs of bytes carry on over several lines
+pub fn render(annotated: &[Annotated]) -> String {
+    let mut s = String::new();
+    for a in annotated {
+        let mut lines = a.bytes.chunks(HEX_WIDTH);
+        let first = lines.next().unwrap_or(&[]);
+        write_hex_line(&mut s, a.position, first);
+        for _ in 0..a.depth {
+            s.push_str("  ");
+        }
+        s.push_str(&a.label);
+        s.push('\n');
+        for (i, line) in lines.enumerate() {
+            write_hex_line(&mut s, a.position + (i + 1) * HEX_WIDTH, line);
+            s.truncate(s.trim_end().len());
+            s.push('\n');
+        }
+    }
+    s
+}
+
+fn write_hex_line(s: &mut String, position: usize, bytes: &[u8]) {
+    let _ = write!(s, "{:08x}  ", position);
+    for b in bytes {
+        let _ = write!(s, "{:02x} ", b);
+    }
+    for _ in bytes.len()..HEX_WIDTH {
+        s.push_str("   ");
+    }
+    s.push(' ');
+}
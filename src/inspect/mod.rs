@@ -0,0 +1,138 @@
+//! Looking at fressian bytes without deserializing them
+
+use crate::imp::codes;
+use crate::imp::walk::{Event, Visit, Walker};
+use crate::error::Result;
+use crate::lib::*;
+
+mod disasm;
+mod validate;
+
+pub use self::disasm::{code_label, disassemble, render, Annotated};
+pub use self::validate::{validate, Summary};
+
+/// The name of the type a code begins. Packed codes are named after the type
+/// they pack, so 0xE5 is a LIST and 0x05 an INT. Cache references are CACHE_REF
+/// and struct references STRUCT.
+pub fn code_name(code: u8) -> &'static str {
+    match code {
+        0x00..=0x7F | codes::INT_PACKED_1_START | codes::INT => "INT",
+        codes::PRIORITY_CACHE_PACKED_START..=0x9F | codes::GET_PRIORITY_CACHE => "CACHE_REF",
+        codes::STRUCT_CACHE_PACKED_START..=0xAF | codes::STRUCT => "STRUCT",
+        codes::LONG_ARRAY => "LONG_ARRAY",
+        codes::DOUBLE_ARRAY => "DOUBLE_ARRAY",
+        codes::BOOLEAN_ARRAY => "BOOLEAN_ARRAY",
+        codes::INT_ARRAY => "INT_ARRAY",
+        codes::FLOAT_ARRAY => "FLOAT_ARRAY",
+        codes::OBJECT_ARRAY => "OBJECT_ARRAY",
+        codes::MAP => "MAP",
+        codes::SET => "SET",
+        codes::UUID => "UUID",
+        codes::REGEX => "REGEX",
+        codes::URI => "URI",
+        codes::BIGINT => "BIGINT",
+        codes::BIGDEC => "BIGDEC",
+        codes::INST => "INST",
+        codes::SYM => "SYM",
+        codes::KEY => "KEY",
+        codes::ERROR => "ERROR",
+        codes::PUT_PRIORITY_CACHE => "PUT_PRIORITY_CACHE",
+        codes::PRECACHE => "PRECACHE",
+        codes::FOOTER => "FOOTER",
+        codes::BYTES_PACKED_LENGTH_START..=codes::BYTES_CHUNK | codes::BYTES => "BYTES",
+        codes::UTF8
+        | codes::STRING_PACKED_LENGTH_START..=codes::STRING_CHUNK
+        | codes::STRING => "STRING",
+        codes::LIST_PACKED_LENGTH_START..=codes::LIST
+        | codes::BEGIN_CLOSED_LIST
+        | codes::BEGIN_OPEN_LIST => "LIST",
+        codes::STRUCTTYPE => "STRUCTTYPE",
+        codes::META => "META",
+        codes::STR => "STR",
+        codes::ANY => "ANY",
+        codes::TRUE | codes::FALSE => "BOOL",
+        codes::NULL => "NULL",
+        codes::FLOAT => "FLOAT",
+        codes::DOUBLE | codes::DOUBLE_0 | codes::DOUBLE_1 => "DOUBLE",
+        codes::END_COLLECTION => "END_COLLECTION",
+        codes::RESET_CACHES => "RESET_CACHES",
+        _ => "UNKNOWN"
+    }
+}
+
+#[derive(Clone, Copy, Default, Debug, PartialEq)]
+pub struct TypeStats {
+    /// how many values of the type were written
+    pub count: usize,
+    /// bytes written for the type itself. A collection only counts its
+    /// header and END_COLLECTION, its children count toward their own types
+    pub bytes: usize,
+}
+
+/// What a run of fressian bytes is made of, see `stats`
+#[derive(Clone, Default, Debug, PartialEq)]
+pub struct Stats {
+    /// top level values, not counting footers
+    pub values: usize,
+    pub footers: usize,
+    pub bytes: usize,
+    /// keyed by `code_name`. Every byte belongs to exactly one type, so the
+    /// bytes add up to the input length
+    pub types: BTreeMap<&'static str, TypeStats>,
+    /// PUT_PRIORITY_CACHE and PRECACHE codes
+    pub cache_puts: usize,
+    /// references to each priority cache index. Caches reset after a footer
+    /// or RESET_CACHES, so an index can stand for several values
+    pub cache_hits: BTreeMap<usize, usize>,
+}
+
+impl Stats {
+    fn add(&mut self, name: &'static str, count: usize, bytes: usize) {
+        let stats = self.types.entry(name).or_default();
+        stats.count += count;
+        stats.bytes += bytes;
+    }
+}
+
+impl<'a> Visit<'a> for Stats {
+    fn visit(&mut self, event: Event, _position: usize, bytes: &'a [u8]) -> Result<()> {
+        let code = bytes.first().cloned().unwrap_or(codes::END_COLLECTION);
+        match event {
+            Event::Scalar(code) | Event::Begin { code, .. } => self.add(code_name(code), 1, bytes.len()),
+            Event::End(code) => self.add(code_name(code), 0, bytes.len()),
+            Event::PutCache(_) | Event::Precache(_) => {
+                self.cache_puts += 1;
+                self.add(code_name(code), 1, bytes.len());
+            }
+            Event::CacheRef(index) => {
+                *self.cache_hits.entry(index).or_insert(0) += 1;
+                self.add(code_name(code), 1, bytes.len());
+            }
+            Event::StructType => self.add("STRUCTTYPE", 1, bytes.len()),
+            Event::StructFields { .. } => self.add("STRUCTTYPE", 0, bytes.len()),
+            Event::StructRef { .. } => self.add("STRUCT", 1, bytes.len()),
+            Event::Cached(_) => {}
+            Event::ResetCaches => self.add("RESET_CACHES", 1, bytes.len()),
+            Event::Footer { .. } => {
+                self.footers += 1;
+                self.add("FOOTER", 1, bytes.len());
+            }
+        }
+        Ok(())
+    }
+}
+
+/// count the values in `bytes` by type, along with the bytes each type takes
+/// up and how often cached values are referenced
+pub fn stats(bytes: &[u8]) -> Result<Stats> {
+    let mut stats = Stats::default();
+    let mut walker = Walker::new(bytes);
+    let mut walked = 0;
+    while !walker.is_done() {
+        walker.walk_next(&mut stats)?;
+        walked += 1;
+    }
+    stats.values = walked - stats.footers;
+    stats.bytes = bytes.len();
+    Ok(stats)
+}
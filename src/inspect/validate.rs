@@ -0,0 +1,185 @@
+use serde::de::Deserialize;
+
+use crate::de::Deserializer;
+use crate::imp::adler;
+use crate::imp::codes;
+use crate::imp::walk::{Event, Visit, Walker};
+use crate::error::{Error, ErrorCode, Result};
+use crate::lib::*;
+
+/// What `validate` found in well formed bytes
+#[derive(Clone, Copy, Default, Debug, PartialEq)]
+pub struct Summary {
+    /// top level values, not counting footers
+    pub values: usize,
+    /// every value, nested ones included. A cache reference counts as a value,
+    /// the cache put around a value does not
+    pub objects: usize,
+    pub footers: usize,
+    pub bytes: usize,
+}
+
+/// a collection being walked
+struct Frame {
+    code: u8,
+    position: usize,
+    children: usize,
+}
+
+/// Checks the structure the walker does not: maps hold a list of even length,
+/// and footers count and checksum the bytes before them. Only the shape of
+/// values is kept, never the values themselves
+struct Validator<'a> {
+    input: &'a [u8],
+    summary: Summary,
+    stack: Vec<Frame>,
+    /// the length of the value that was just walked, if it was a list
+    last_list: Option<usize>,
+    /// the list lengths of cached values, so that maps can be checked
+    /// when they hold a cache reference
+    cache_lists: Vec<Option<usize>>,
+    precached: BTreeSet<usize>,
+    /// where the bytes a footer covers begin
+    segment: usize,
+}
+
+fn is_list(code: u8) -> bool {
+    matches!(code, codes::LIST_PACKED_LENGTH_START..=codes::LIST
+                   | codes::BEGIN_CLOSED_LIST
+                   | codes::BEGIN_OPEN_LIST)
+}
+
+fn is_string(code: u8) -> bool {
+    matches!(code, codes::STRING_PACKED_LENGTH_START..=codes::STRING_CHUNK
+                   | codes::STRING
+                   | codes::UTF8)
+}
+
+/// the walker only checks that a string's bytes are there, this decodes them
+fn check_string(position: usize, bytes: &[u8]) -> Result<()> {
+    match String::deserialize(&mut Deserializer::from_bytes(bytes)) {
+        Ok(_) => Ok(()),
+        Err(_) => Err(Error::syntax(ErrorCode::InvalidUTF8, position))
+    }
+}
+
+impl<'a> Validator<'a> {
+    /// a value is done. `list` is its length if it is a list
+    fn walked(&mut self, list: Option<usize>) -> Result<()> {
+        self.last_list = list;
+        if let Some(frame) = self.stack.last_mut() {
+            frame.children += 1;
+            if frame.code == codes::MAP {
+                match list {
+                    Some(n) if n % 2 == 0 => {}
+                    Some(_) => return Err(Error::syntax(ErrorCode::OddMapLength, frame.position)),
+                    None => return Err(Error::syntax(ErrorCode::MapExpectedListCode, frame.position))
+                }
+            }
+        }
+        Ok(())
+    }
+
+    fn reset_caches(&mut self) {
+        self.cache_lists.clear();
+    }
+
+    fn check_footer(&mut self, position: usize, length: usize, checksum: u32) -> Result<()> {
+        let covered = &self.input[self.segment..position];
+        if length != covered.len() {
+            return Err(Error::syntax(ErrorCode::InvalidFooter, position))
+        }
+        // the writer here leaves the checksum at 0, java fills it in
+        if checksum != 0 {
+            let mut state = adler::State32::new();
+            state.feed(covered);
+            if state.result() != checksum {
+                return Err(Error::syntax(ErrorCode::InvalidChecksum, position))
+            }
+        }
+        Ok(())
+    }
+}
+
+impl<'a> Visit<'a> for Validator<'a> {
+    fn visit(&mut self, event: Event, position: usize, bytes: &'a [u8]) -> Result<()> {
+        match event {
+            Event::Scalar(code) => {
+                if is_string(code) {
+                    check_string(position, bytes)?;
+                }
+                self.summary.objects += 1;
+                self.walked(None)?;
+            }
+            Event::Begin { code, .. } => {
+                self.summary.objects += 1;
+                self.stack.push(Frame { code, position, children: 0 });
+            }
+            Event::End(_) => {
+                let frame = self.stack.pop().unwrap();
+                let list = if is_list(frame.code) { Some(frame.children) } else { None };
+                self.walked(list)?;
+            }
+            Event::PutCache(_) => {}
+            Event::Precache(index) => {
+                self.precached.insert(index);
+            }
+            Event::Cached(index) => {
+                if self.cache_lists.len() <= index {
+                    self.cache_lists.resize(index + 1, None);
+                }
+                self.cache_lists[index] = self.last_list;
+                // readers skip a precached value, so it is not a child
+                if self.precached.remove(&index) {
+                    if let Some(frame) = self.stack.last_mut() {
+                        frame.children -= 1;
+                    }
+                }
+            }
+            Event::CacheRef(index) => {
+                self.summary.objects += 1;
+                let list = self.cache_lists.get(index).cloned().unwrap_or(None);
+                self.walked(list)?;
+            }
+            Event::StructType | Event::StructRef { .. } => {
+                self.summary.objects += 1;
+                self.stack.push(Frame { code: codes::STRUCTTYPE, position, children: 0 });
+            }
+            Event::StructFields { .. } => {}
+            Event::ResetCaches => self.reset_caches(),
+            Event::Footer { length, checksum } => {
+                self.check_footer(position, length, checksum)?;
+                self.summary.footers += 1;
+                self.segment = position + bytes.len();
+                self.reset_caches();
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Check that `bytes` are well formed fressian without deserializing them.
+/// Every length must fit in the input, lists must be closed, cache and
+/// struct references must point at something, maps must hold an even number
+/// of keys and values, strings must decode, and footers must match the bytes
+/// before them. The error's position is the first problem found
+pub fn validate(bytes: &[u8]) -> Result<Summary> {
+    let mut v = Validator {
+        input: bytes,
+        summary: Summary::default(),
+        stack: Vec::new(),
+        last_list: None,
+        cache_lists: Vec::new(),
+        precached: BTreeSet::new(),
+        segment: 0,
+    };
+    let mut walker = Walker::new(bytes);
+    let mut walked = 0;
+    while !walker.is_done() {
+        walker.walk_next(&mut v)?;
+        walked += 1;
+    }
+    v.summary.values = walked - v.summary.footers;
+    v.summary.bytes = bytes.len();
+    Ok(v.summary)
+}
@@ -0,0 +1,167 @@
+use serde::de::Deserialize;
+
+use crate::de::Deserializer;
+use crate::error::Result;
+use crate::imp::codes;
+use crate::value::{keyword, PathSegment, Value};
+use crate::lib::*;
+
+/// where a step of the path ended up
+enum Step {
+    /// the reader is at the value the step leads to
+    Found,
+    /// the value was read to find it, as when looking up a SET element
+    Read(Value),
+    Missing,
+}
+
+/// how the elements of a list are counted off
+enum Length {
+    Counted(usize),
+    /// ends at END_COLLECTION
+    Closed,
+    /// ends at END_COLLECTION or the end of input
+    Open,
+}
+
+/// the length of the list that starts with `code`, None when it is not a list
+fn list_length<'de>(de: &mut Deserializer<'de>, code: u8) -> Result<Option<Length>> {
+    let length = match code {
+        codes::LIST_PACKED_LENGTH_START..=0xEB => {
+            Length::Counted((code - codes::LIST_PACKED_LENGTH_START) as usize)
+        }
+        codes::LIST => Length::Counted(de.read_count()?),
+        codes::BEGIN_CLOSED_LIST => Length::Closed,
+        codes::BEGIN_OPEN_LIST => Length::Open,
+        _ => return Ok(None)
+    };
+    Ok(Some(length))
+}
+
+/// true when the list has another element, which is then counted off
+fn has_next<'de>(de: &mut Deserializer<'de>, length: &mut Length) -> Result<bool> {
+    let open = match length {
+        Length::Counted(0) => return Ok(false),
+        Length::Counted(n) => {
+            *n -= 1;
+            return Ok(true)
+        }
+        Length::Closed => false,
+        Length::Open => true
+    };
+    match de.peek_next_code() {
+        Err(ref err) if open && err.is_eof() => Ok(false),
+        Err(err) => Err(err),
+        Ok(code) => Ok(code as u8 != codes::END_COLLECTION)
+    }
+}
+
+/// skip to the element at `index`
+fn nth<'de>(de: &mut Deserializer<'de>, code: u8, index: usize) -> Result<Step> {
+    let mut length = match list_length(de, code)? {
+        Some(length) => length,
+        None => return Ok(Step::Missing)
+    };
+    for _ in 0..index {
+        if !has_next(de, &mut length)? {
+            return Ok(Step::Missing)
+        }
+        de.skip_value()?;
+    }
+    if has_next(de, &mut length)? { Ok(Step::Found) } else { Ok(Step::Missing) }
+}
+
+/// Read keys until one matches, skipping the values of those that do not.
+/// Keys are small, so they are read as Values
+fn find_entry<'de, F>(de: &mut Deserializer<'de>, matches: F) -> Result<Step>
+where
+    F: Fn(&Value) -> bool,
+{
+    let code = de.read_value_code()?;
+    let mut length = match list_length(de, code)? {
+        // a key and its value
+        Some(Length::Counted(n)) => Length::Counted(n / 2),
+        Some(length) => length,
+        None => return Ok(Step::Missing)
+    };
+    while has_next(de, &mut length)? {
+        let key = Value::deserialize(&mut *de)?;
+        if matches(&key) {
+            return Ok(Step::Found)
+        }
+        de.skip_value()?;
+    }
+    Ok(Step::Missing)
+}
+
+/// find a SET element equal to `v`
+fn find_element<'de>(de: &mut Deserializer<'de>, v: &Value) -> Result<Step> {
+    let code = de.read_value_code()?;
+    let mut length = match list_length(de, code)? {
+        Some(length) => length,
+        None => return Ok(Step::Missing)
+    };
+    while has_next(de, &mut length)? {
+        let element = Value::deserialize(&mut *de)?;
+        if &element == v {
+            return Ok(Step::Read(element))
+        }
+    }
+    Ok(Step::Missing)
+}
+
+/// take one step of the path, the same way `Value::get_in` does
+fn step<'de>(de: &mut Deserializer<'de>, segment: &PathSegment) -> Result<Step> {
+    // a cached value, or one just put in the cache, has already been read
+    if let Some(v) = de.read_cache_codes()? {
+        return match v.get_in(core::slice::from_ref(segment)) {
+            Some(v) => Ok(Step::Read(v.clone())),
+            None => Ok(Step::Missing)
+        }
+    }
+    let code = de.read_next_code()? as u8;
+    match (code, segment) {
+        (codes::MAP, PathSegment::Key(k)) => {
+            let (s, kw) = (Value::STRING(k.to_string()), Value::KEY(keyword(k)));
+            find_entry(de, |key| *key == s || *key == kw)
+        }
+        (codes::MAP, PathSegment::Value(v)) => find_entry(de, |key| key == *v),
+        (codes::SET, PathSegment::Value(v)) => find_element(de, v),
+        (_, PathSegment::Index(i)) => nth(de, code, *i),
+        (_, PathSegment::Value(Value::INT(i))) if 0 <= *i => nth(de, code, *i as usize),
+        _ => Ok(Step::Missing)
+    }
+}
+
+/// Follow `path` through the first value in `bytes` and read only what it
+/// leads to, like `Value::get_in` without reading the rest. Values beside the
+/// path are skipped by their lengths, map keys along the way are read to
+/// compare them. A map holding both "a" and :a gives whichever comes first
+/// for the `"a"` segment.
+///
+/// None when the path leads nowhere: a key or index that is missing, or a
+/// step into something that is not a collection
+pub fn get_in(bytes: &[u8], path: &[PathSegment]) -> Result<Option<Value>> {
+    get_in_as(bytes, path)
+}
+
+/// `get_in`, reading the value the path leads to as a `T`
+pub fn get_in_as<'a, T>(bytes: &'a [u8], path: &[PathSegment]) -> Result<Option<T>>
+where
+    T: Deserialize<'a>,
+{
+    let mut de = Deserializer::from_bytes(bytes);
+    for (i, segment) in path.iter().enumerate() {
+        match step(&mut de, segment)? {
+            Step::Found => {}
+            Step::Read(v) => {
+                return match v.get_in(&path[i + 1..]) {
+                    Some(v) => T::deserialize(v.clone()).map(Some),
+                    None => Ok(None)
+                }
+            }
+            Step::Missing => return Ok(None)
+        }
+    }
+    T::deserialize(&mut de).map(Some)
+}
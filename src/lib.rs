@@ -1,6 +1,16 @@
 #![cfg_attr(feature= "use_regex_crate", use_regex_crate)]
 #![cfg_attr(feature= "use_uuid_crate", use_uuid_crate)]
 #![cfg_attr(feature= "use_url_crate", use_url_crate)]
+#![cfg_attr(not(feature = "std"), no_std)]
+
+#[cfg(not(feature = "std"))]
+#[macro_use]
+extern crate alloc;
+#[cfg(feature = "std")]
+extern crate alloc;
+// Shrinkwrap derives refer to ::std::ops etc, which core provides
+#[cfg(not(feature = "std"))]
+extern crate core as std;
 
 extern crate itertools;
 
@@ -10,8 +20,10 @@ extern crate shrinkwraprs;
 #[macro_use]
 extern crate serde_derive;
 extern crate serde;
+#[cfg(feature = "std")]
 extern crate serde_bytes;
 extern crate byteorder;
+#[cfg(feature = "std")]
 extern crate ordered_float;
 
 #[cfg(use_regex_crate)]
@@ -25,13 +37,31 @@ extern crate url;
 
 // extern crate chrono;
 
+/// the std prelude items the crate uses, from alloc so that they are there
+/// without std too
+mod lib {
+    pub use alloc::boxed::Box;
+    pub use alloc::collections::{BTreeMap, BTreeSet};
+    pub use alloc::string::{String, ToString};
+    pub use alloc::vec::Vec;
+}
+
+#[macro_use]
+mod macros;
+
 mod imp;
 pub mod error;
 pub mod ser;
 pub mod de;
 pub mod value;
 pub mod types;
+#[cfg(feature = "std")]
 pub mod wasm;
+pub mod session;
+pub mod inspect;
+pub mod lazy;
+#[cfg(feature = "std")]
+pub mod pool;
 
 pub use crate::types::{
     inst,
@@ -0,0 +1,103 @@
+/// Build a `Value` from EDN-like syntax.
+///
+/// ```
+/// # #[macro_use] extern crate serde_fressian;
+/// # fn main() {
+/// let n = 42;
+/// let v = fress!({:user/name "x", :tags #{:a :b}, :n (n + 1), :xs [1 -2.5 nil true]});
+/// assert_eq!(v[":user/name"].as_str(), Some("x"));
+/// assert_eq!(v["n"].as_i64(), Some(43));
+/// # }
+/// ```
+///
+///   + `nil`, `true` and `false`
+///   + `:name` and `:ns/name` keywords. Names must be rust identifiers, build
+///     others with `key::KEY`
+///   + `[..]` lists, `{..}` maps and `#{..}` sets. Commas are optional
+///   + anything else is a rust expression converted with `Into<Value>`. Inside
+///     a collection each one must be a single token, so wrap expressions in parens
+///
+/// It can also be called by its path, without importing it:
+///
+/// ```
+/// let v = serde_fressian::fress!({:a [1 #{:b}]});
+/// assert_eq!(v["a"][0].as_i64(), Some(1));
+/// ```
+#[macro_export]
+macro_rules! fress {
+    // munch the elements of a collection into [(elem) ..]
+    (@coll $kind:ident [$($out:tt)*]) => {
+        $crate::fress!(@end $kind [$($out)*])
+    };
+    (@coll $kind:ident [$($out:tt)*] , $($rest:tt)*) => {
+        $crate::fress!(@coll $kind [$($out)*] $($rest)*)
+    };
+    (@coll $kind:ident [$($out:tt)*] : $ns:ident / $name:ident $($rest:tt)*) => {
+        $crate::fress!(@coll $kind [$($out)* ($crate::fress!(: $ns / $name))] $($rest)*)
+    };
+    (@coll $kind:ident [$($out:tt)*] : $name:ident $($rest:tt)*) => {
+        $crate::fress!(@coll $kind [$($out)* ($crate::fress!(: $name))] $($rest)*)
+    };
+    (@coll $kind:ident [$($out:tt)*] # {$($set:tt)*} $($rest:tt)*) => {
+        $crate::fress!(@coll $kind [$($out)* ($crate::fress!(# {$($set)*}))] $($rest)*)
+    };
+    (@coll $kind:ident [$($out:tt)*] - $num:literal $($rest:tt)*) => {
+        $crate::fress!(@coll $kind [$($out)* ($crate::fress!(- $num))] $($rest)*)
+    };
+    (@coll $kind:ident [$($out:tt)*] $elem:tt $($rest:tt)*) => {
+        $crate::fress!(@coll $kind [$($out)* ($crate::fress!($elem))] $($rest)*)
+    };
+
+    (@end list [$($out:tt)*]) => {
+        $crate::value::Value::LIST(::core::iter::IntoIterator::into_iter([$($out),*]).collect())
+    };
+    (@end set [$($out:tt)*]) => {
+        $crate::value::Value::SET(::core::iter::IntoIterator::into_iter([$($out),*]).collect())
+    };
+    (@end map [$($out:tt)*]) => {
+        $crate::fress!(@pairs [] $($out)*)
+    };
+
+    (@pairs [$($pair:tt)*]) => {
+        $crate::value::Value::MAP(::core::iter::IntoIterator::into_iter([$($pair),*]).collect())
+    };
+    (@pairs [$($pair:tt)*] $k:tt $v:tt $($rest:tt)*) => {
+        $crate::fress!(@pairs [$($pair)* ($k, $v)] $($rest)*)
+    };
+    (@pairs [$($pair:tt)*] $k:tt) => {
+        compile_error!("fress! map literal needs an even number of forms")
+    };
+
+    (nil) => {
+        $crate::value::Value::NULL
+    };
+    (true) => {
+        $crate::value::Value::BOOL(true)
+    };
+    (false) => {
+        $crate::value::Value::BOOL(false)
+    };
+    ([$($tt:tt)*]) => {
+        $crate::fress!(@coll list [] $($tt)*)
+    };
+    ({$($tt:tt)*}) => {
+        $crate::fress!(@coll map [] $($tt)*)
+    };
+    (# {$($tt:tt)*}) => {
+        $crate::fress!(@coll set [] $($tt)*)
+    };
+    (: $ns:ident / $name:ident) => {
+        $crate::value::Value::KEY($crate::key::KEY::namespaced(
+            ::core::convert::Into::into(stringify!($ns)),
+            ::core::convert::Into::into(stringify!($name))))
+    };
+    (: $name:ident) => {
+        $crate::value::Value::KEY($crate::key::KEY::simple(::core::convert::Into::into(stringify!($name))))
+    };
+    (- $num:literal) => {
+        $crate::value::Value::from(- $num)
+    };
+    ($other:expr) => {
+        $crate::value::Value::from($other)
+    };
+}
@@ -0,0 +1,110 @@
+use std::ops::{Deref, DerefMut};
+use std::sync::Mutex;
+
+use serde::ser::Serialize;
+
+use crate::error::Result;
+use crate::ser;
+
+/// A thread safe pool of output buffers. Buffers keep their capacity between
+/// uses, so serializing many values does not allocate a new vec every time.
+pub struct BufferPool {
+    buffers: Mutex<Vec<Vec<u8>>>,
+    max_pooled: usize,
+    capacity: usize,
+}
+
+impl BufferPool {
+    /// `max_pooled` is the most idle buffers kept around, `capacity` is the
+    /// capacity given to new buffers
+    pub fn new(max_pooled: usize, capacity: usize) -> Self {
+        BufferPool {
+            buffers: Mutex::new(Vec::new()),
+            max_pooled,
+            capacity
+        }
+    }
+
+    /// take an empty buffer from the pool, or allocate one if the pool is empty
+    pub fn get(&self) -> PooledBuffer<'_> {
+        let buf = self.lock().pop().unwrap_or_else(|| Vec::with_capacity(self.capacity));
+        PooledBuffer {
+            pool: self,
+            buf: Some(buf)
+        }
+    }
+
+    /// serialize a value into a pooled buffer
+    pub fn serialize<T>(&self, value: &T) -> Result<PooledBuffer<'_>>
+    where
+        T: Serialize,
+    {
+        let mut buf = self.get();
+        ser::to_vec_into(&mut buf, value)?;
+        Ok(buf)
+    }
+
+    /// the number of idle buffers in the pool
+    pub fn len(&self) -> usize {
+        self.lock().len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    fn put(&self, mut buf: Vec<u8>) {
+        let mut buffers = self.lock();
+        if buffers.len() < self.max_pooled {
+            buf.clear();
+            buffers.push(buf);
+        }
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, Vec<Vec<u8>>> {
+        // a buffer list is still usable after a panic elsewhere
+        match self.buffers.lock() {
+            Ok(guard) => guard,
+            Err(poisoned) => poisoned.into_inner()
+        }
+    }
+}
+
+impl Default for BufferPool {
+    fn default() -> Self { BufferPool::new(16, 1024) }
+}
+
+/// A buffer borrowed from a BufferPool. It goes back to the pool when dropped.
+pub struct PooledBuffer<'a> {
+    pool: &'a BufferPool,
+    buf: Option<Vec<u8>>
+}
+
+impl<'a> PooledBuffer<'a> {
+    /// keep the buffer instead of returning it to the pool
+    pub fn into_inner(mut self) -> Vec<u8> {
+        self.buf.take().expect("pooled buffer")
+    }
+}
+
+impl<'a> Deref for PooledBuffer<'a> {
+    type Target = Vec<u8>;
+
+    fn deref(&self) -> &Vec<u8> {
+        self.buf.as_ref().expect("pooled buffer")
+    }
+}
+
+impl<'a> DerefMut for PooledBuffer<'a> {
+    fn deref_mut(&mut self) -> &mut Vec<u8> {
+        self.buf.as_mut().expect("pooled buffer")
+    }
+}
+
+impl<'a> Drop for PooledBuffer<'a> {
+    fn drop(&mut self) {
+        if let Some(buf) = self.buf.take() {
+            self.pool.put(buf);
+        }
+    }
+}
@@ -3,11 +3,16 @@ use serde::ser::{self, Serialize};
 use crate::imp::rawoutput::{RawOutput};
 use crate::imp::codes;
 use crate::imp::io::{ByteWriter, IWriteBytes};
+#[cfg(feature = "std")]
+pub use crate::imp::io::IoWriter;
 use crate::imp::ranges;
 use crate::imp::cache::{Cache, ICache};
 use crate::error::{Error, ErrorCode, Result};
-use std::hash::{Hash};
-use ordered_float::OrderedFloat;
+use core::hash::{Hash};
+#[cfg(feature = "std")]
+use std::io;
+use crate::imp::compat::OrderedFloat;
+use crate::lib::*;
 
 pub struct Serializer<W, C: ICache> {
     writer: W,
@@ -23,6 +28,12 @@ impl<W,C> Serializer<W,C>
         self.writer.reset();
         self.cache.reset();
     }
+
+    /// reset the writer but keep the cache, so that subsequent writes can
+    /// refer to values cached by earlier ones
+    pub fn reset_writer(&mut self) {
+        self.writer.reset();
+    }
 }
 
 impl<C> Serializer<ByteWriter<Vec<u8>>, C>
@@ -68,6 +79,83 @@ impl Serializer<ByteWriter<Vec<u8>>, Cache> {
     }
 }
 
+impl<'a> Serializer<ByteWriter<&'a mut Vec<u8>>, Cache> {
+    /// create a serializer that appends to a caller owned buffer
+    pub fn with_buffer(buf: &'a mut Vec<u8>) -> Self {
+        Serializer::with_buffer_and_cache(buf, Cache::new())
+    }
+}
+
+impl<'a, C> Serializer<ByteWriter<&'a mut Vec<u8>>, C>
+    where C: ICache,
+{
+    pub fn with_buffer_and_cache(buf: &'a mut Vec<u8>, cache: C) -> Self {
+        Serializer {
+            writer: ByteWriter::from_buffer(buf),
+            cache,
+        }
+    }
+
+    /// the bytes this serializer has appended to the buffer
+    pub fn written(&self) -> &[u8] {
+        self.writer.written()
+    }
+}
+
+impl<'a> Serializer<ByteWriter<&'a mut [u8]>, Cache> {
+    /// create a serializer that writes into a fixed size slice
+    pub fn from_slice(buf: &'a mut [u8]) -> Self {
+        Serializer {
+            writer: ByteWriter::from_slice(buf),
+            cache: Cache::new(),
+        }
+    }
+
+    /// the bytes written into the slice so far
+    pub fn written(&self) -> &[u8] {
+        self.writer.written()
+    }
+}
+
+#[cfg(feature = "std")]
+impl<W: io::Write> Serializer<IoWriter<W>, Cache> {
+    /// create a serializer that writes straight to an io::Write
+    pub fn from_writer(writer: W) -> Self {
+        Serializer::from_writer_with_cache(writer, Cache::new())
+    }
+}
+
+#[cfg(feature = "std")]
+impl<W, C> Serializer<IoWriter<W>, C>
+    where W: io::Write,
+          C: ICache,
+{
+    pub fn from_writer_with_cache(writer: W, cache: C) -> Self {
+        Serializer {
+            writer: IoWriter::new(writer),
+            cache,
+        }
+    }
+
+    pub fn flush(&mut self) -> Result<()> {
+        self.writer.flush()
+    }
+
+    /// Gets a reference to the underlying writer
+    pub fn get_ref(&self) -> &W {
+        self.writer.get_ref()
+    }
+
+    /// Gets a mutable reference to the underlying writer
+    pub fn get_mut(&mut self) -> &mut W {
+        self.writer.get_mut()
+    }
+
+    pub fn into_inner(self) -> W {
+        self.writer.into_inner()
+    }
+}
+
 /// serialize value to Vec<u8>
 pub fn to_vec<T>(value: &T) -> Result<Vec<u8>>
 where
@@ -78,6 +166,33 @@ where
     Ok(serializer.into_inner())
 }
 
+/// serialize value onto the end of `buf`, reusing its capacity. If serialization
+/// fails `buf` is left as it was
+pub fn to_vec_into<T>(buf: &mut Vec<u8>, value: &T) -> Result<()>
+where
+    T: Serialize,
+{
+    let mut serializer = Serializer::with_buffer(buf);
+    match value.serialize(&mut serializer) {
+        Ok(()) => Ok(()),
+        Err(err) => {
+            serializer.reset();
+            Err(err)
+        }
+    }
+}
+
+/// serialize value into `buf`, returning the number of bytes written. Fails with
+/// BufferFull if the value does not fit
+pub fn to_slice<T>(value: &T, buf: &mut [u8]) -> Result<usize>
+where
+    T: Serialize,
+{
+    let mut serializer = Serializer::from_slice(buf);
+    value.serialize(&mut serializer)?;
+    Ok(serializer.writer.get_bytes_written())
+}
+
 /// serialize value to Vec<u8> with provided cache
 pub fn to_vec_cache<T,C>(value: &T, cache: C) -> Result<Vec<u8>>
 where
@@ -100,6 +215,18 @@ where
     Ok(serializer.into_inner())
 }
 
+/// serialize value to an io::Write and flush it
+#[cfg(feature = "std")]
+pub fn to_writer<W, T>(writer: W, value: &T) -> Result<()>
+where
+    W: io::Write,
+    T: Serialize,
+{
+    let mut serializer = Serializer::from_writer(writer);
+    value.serialize(&mut serializer)?;
+    serializer.flush()
+}
+
 impl<W,C> Serializer<W,C>
 where
     W: IWriteBytes,
@@ -252,7 +379,7 @@ where
     }
 
     pub fn write_set<I, V>(&mut self, iter: I) -> Result<()>
-    where V: Serialize + std::cmp::Eq + std::hash::Hash,
+    where V: Serialize + core::cmp::Eq + core::hash::Hash,
           I: IntoIterator<Item = V>,
     {
         self.write_code(codes::SET)?;
@@ -303,7 +430,7 @@ where
     fn serialize_u32(self, v: u32) -> Result<()> { self.write_int(i64::from(v)) }
 
     fn serialize_u64(self, v: u64) -> Result<()> {
-        if (std::i64::MAX as u64) < v {
+        if (core::i64::MAX as u64) < v {
             error(self, ErrorCode::IntTooLargeFori64)
         } else {
             self.write_int(v as i64)
@@ -491,10 +618,12 @@ where
         _name: &'static str,
         _variant_index: u32,
         variant: &'static str,
-        _len: usize,
+        len: usize,
     ) -> Result<Self::SerializeStructVariant> {
+        // [variant {fields}], the same shape as a newtype variant
+        self.write_list_header(2)?;
         variant.serialize(&mut *self)?;
-        Ok(Compound::LIST{ser: self, cache_elements: false, list_type: ListType::Fixed})
+        self.serialize_map(Some(len))
     }
 
     fn serialize_tuple_variant(
@@ -502,10 +631,12 @@ where
         _name: &'static str,
         _variant_index: u32,
         variant: &'static str,
-        _len: usize,
+        len: usize,
     ) -> Result<Self::SerializeTupleVariant> {
+        // [variant [fields]], the same shape as a newtype variant
+        self.write_list_header(2)?;
         variant.serialize(&mut *self)?;
-        Ok(Compound::LIST{ser: self, cache_elements: false, list_type: ListType::Fixed})
+        self.serialize_seq(Some(len))
     }
 
 }
@@ -952,7 +1083,7 @@ where
 
     #[inline]
     fn serialize_u64(self, _value: u64) -> Result<()> {
-         if (std::i64::MAX as u64) < _value {
+         if (core::i64::MAX as u64) < _value {
              error(self.ser, ErrorCode::IntTooLargeFori64)
          } else {
              self.ser.caching_serialize(_value as i64)
@@ -0,0 +1,166 @@
+use core::mem;
+
+use serde::de::Deserialize;
+use serde::ser::Serialize;
+
+use crate::de::Deserializer;
+use crate::error::Result;
+use crate::imp::cache::{Cache, ICache};
+use crate::imp::codes;
+use crate::imp::io::ByteWriter;
+use crate::ser::Serializer;
+use crate::value::Value;
+use crate::lib::*;
+
+/// The writing half of a session. Every message is written by the same serializer
+/// so its priority cache survives from one message to the next, just like a java
+/// FressianWriter making many `writeObject` calls on one stream.
+pub struct SessionWriter<C: ICache = Cache> {
+    ser: Serializer<ByteWriter<Vec<u8>>, C>,
+    reset_pending: bool
+}
+
+impl SessionWriter<Cache> {
+    pub fn new() -> Self {
+        SessionWriter::with_cache(Cache::new())
+    }
+}
+
+impl Default for SessionWriter<Cache> {
+    fn default() -> Self { SessionWriter::new() }
+}
+
+impl<C: ICache> SessionWriter<C> {
+    /// create a session writer with a custom cache
+    pub fn with_cache(cache: C) -> Self {
+        SessionWriter {
+            ser: Serializer::with_cache(cache),
+            reset_pending: false
+        }
+    }
+
+    /// serialize a single message. The returned bytes are one frame; frames must be
+    /// read by the peer's SessionReader in the order they were written.
+    pub fn write_message<T>(&mut self, value: &T) -> Result<Vec<u8>>
+        where T: Serialize,
+    {
+        if self.reset_pending {
+            self.ser.write_code(codes::RESET_CACHES)?;
+            self.reset_pending = false;
+        }
+        match value.serialize(&mut self.ser) {
+            Ok(()) => {
+                let bytes = self.ser.to_vec();
+                self.ser.reset_writer();
+                Ok(bytes)
+            }
+            Err(err) => {
+                // the cache may hold entries the peer never saw, so start over
+                self.reset_caches();
+                Err(err)
+            }
+        }
+    }
+
+    /// clear the cache. The next message starts with RESET_CACHES so the peer
+    /// clears its cache too.
+    pub fn reset_caches(&mut self) {
+        self.ser.reset();
+        self.reset_pending = true;
+    }
+}
+
+/// The reading half of a session. Cached values from earlier messages stay
+/// available to later ones.
+pub struct SessionReader {
+    cache: Vec<Value>
+}
+
+impl SessionReader {
+    pub fn new() -> Self {
+        SessionReader::with_cache(Vec::new())
+    }
+
+    /// create a session reader seeded with a pre-agreed cache
+    pub fn with_cache(cache: Vec<Value>) -> Self {
+        SessionReader { cache }
+    }
+
+    /// deserialize a single frame written by the peer's SessionWriter.
+    /// If this fails partway through a message the cache may no longer match the
+    /// peer's, and both sides should be reset.
+    pub fn read_message<'a, T>(&mut self, bytes: &'a [u8]) -> Result<T>
+        where T: Deserialize<'a>,
+    {
+        let bytes = match bytes.first() {
+            Some(&codes::RESET_CACHES) => {
+                self.cache.clear();
+                &bytes[1..]
+            }
+            _ => bytes
+        };
+        let cache = mem::take(&mut self.cache);
+        let mut deserializer = Deserializer::with_cache(bytes, cache);
+        let res = T::deserialize(&mut deserializer);
+        self.cache = deserializer.into_cache();
+        res
+    }
+
+    pub fn reset(&mut self) {
+        self.cache.clear()
+    }
+}
+
+impl Default for SessionReader {
+    fn default() -> Self { SessionReader::new() }
+}
+
+/// A writer half plus a reader half, for channels where both peers talk.
+/// Outgoing messages are written with `write_message`, incoming ones are read with
+/// `read_message`, and each half keeps its cache in step with the peer.
+pub struct FressianSession<C: ICache = Cache> {
+    writer: SessionWriter<C>,
+    reader: SessionReader
+}
+
+impl FressianSession<Cache> {
+    pub fn new() -> Self {
+        FressianSession {
+            writer: SessionWriter::new(),
+            reader: SessionReader::new()
+        }
+    }
+}
+
+impl Default for FressianSession<Cache> {
+    fn default() -> Self { FressianSession::new() }
+}
+
+impl<C: ICache> FressianSession<C> {
+    pub fn from_parts(writer: SessionWriter<C>, reader: SessionReader) -> Self {
+        FressianSession { writer, reader }
+    }
+
+    pub fn split(self) -> (SessionWriter<C>, SessionReader) {
+        (self.writer, self.reader)
+    }
+
+    pub fn write_message<T>(&mut self, value: &T) -> Result<Vec<u8>>
+        where T: Serialize,
+    {
+        self.writer.write_message(value)
+    }
+
+    pub fn read_message<'a, T>(&mut self, bytes: &'a [u8]) -> Result<T>
+        where T: Deserialize<'a>,
+    {
+        self.reader.read_message(bytes)
+    }
+
+    /// reset both halves. The reader can only be reset locally, so the peer
+    /// must reset its writer at the same point in the conversation.
+    pub fn reset(&mut self) {
+        self.writer.reset_caches();
+        self.reader.reset();
+    }
+}
@@ -74,7 +74,7 @@ pub mod inst {
 pub mod uuid {
     use serde::de::{Deserializer, Deserialize};
     use serde::ser::{Serialize, Serializer};
-    use serde_bytes::ByteBuf;
+    use crate::imp::compat::ByteBuf;
 
     #[derive(Shrinkwrap, Clone, PartialEq, PartialOrd, Ord, Eq, Hash, Debug)]
     pub struct UUID (ByteBuf);
@@ -118,7 +118,8 @@ pub mod uuid {
 
     use serde::de::{Deserializer, Deserialize};
     use serde::ser::{Serialize, Serializer};
-    use serde_bytes::ByteBuf;
+    use crate::lib::*;
+    use crate::imp::compat::ByteBuf;
 
     use _uuid::Uuid;
 
@@ -165,6 +166,7 @@ pub mod uuid {
 pub mod uri {
     use serde::de::{Deserializer, Deserialize};
     use serde::ser::{Serialize, Serializer};
+    use crate::lib::*;
 
     #[derive(Shrinkwrap, Clone, PartialEq, PartialOrd, Ord, Eq, Hash, Debug)]
     pub struct URI (String);
@@ -207,6 +209,7 @@ pub mod uri {
 pub mod uri {
     use serde::de::{Deserializer, Deserialize, Error};
     use serde::ser::{Serialize, Serializer};
+    use crate::lib::*;
     use url::{Url};
 
     #[derive(Shrinkwrap, Clone, PartialEq, PartialOrd, Ord, Eq, Hash, Debug)]
@@ -263,6 +266,7 @@ pub mod regex {
 
     use serde::de::{Deserializer, Deserialize};
     use serde::ser::{Serialize, Serializer};
+    use crate::lib::*;
 
     impl<'de> Deserialize<'de> for REGEX {
         fn deserialize<D>(deserializer: D) -> Result<REGEX, D::Error>
@@ -287,7 +291,7 @@ pub mod regex {
 #[cfg(use_regex_crate)]
 pub mod regex {
     use _regex::Regex;
-    use std::cmp::{Eq,PartialOrd, Ordering};
+    use core::cmp::{Eq,PartialOrd, Ordering};
 
     #[derive(Shrinkwrap, Clone, Debug)]
     pub struct REGEX (Regex);
@@ -327,7 +331,7 @@ pub mod regex {
 
     impl Eq for REGEX {}
 
-    use std::hash::{Hash, Hasher};
+    use core::hash::{Hash, Hasher};
 
     impl Hash for REGEX {
         fn hash<H: Hasher>(&self, state: &mut H) {
@@ -337,6 +341,7 @@ pub mod regex {
 
     use serde::de::{Deserializer, Deserialize, Error};
     use serde::ser::{Serialize, Serializer};
+    use crate::lib::*;
 
     impl<'de> Deserialize<'de> for REGEX {
         fn deserialize<D>(deserializer: D) -> Result<REGEX, D::Error>
@@ -363,6 +368,7 @@ pub mod regex {
 pub mod sym {
 
     use serde::ser::{Serialize, Serializer};
+    use crate::lib::*;
 
     #[derive(Clone,Debug,Eq,Hash,Ord,PartialOrd,PartialEq,Deserialize)]
     pub struct SYM(Option<String>,String);
@@ -377,6 +383,12 @@ pub mod sym {
         pub fn namespaced(namespace: String, name: String) -> Self {
             SYM(Some(namespace), name)
         }
+        pub fn namespace(&self) -> Option<&str> {
+            self.0.as_deref()
+        }
+        pub fn name(&self) -> &str {
+            &self.1
+        }
     }
 
     use serde::ser::SerializeTupleStruct;
@@ -400,6 +412,7 @@ pub mod sym {
 pub mod key {
 
     use serde::ser::{Serialize, Serializer};
+    use crate::lib::*;
 
     //same as SYM above
     #[derive(Clone,Debug,Eq,Hash,Ord,PartialOrd,PartialEq, Deserialize)]
@@ -415,6 +428,12 @@ pub mod key {
         pub fn namespaced(namespace: String, name: String) -> Self {
             KEY(Some(namespace), name)
         }
+        pub fn namespace(&self) -> Option<&str> {
+            self.0.as_deref()
+        }
+        pub fn name(&self) -> &str {
+            &self.1
+        }
     }
 
     use serde::ser::SerializeTupleStruct;
@@ -436,6 +455,7 @@ pub mod typed_arrays {
 
     use serde::de::{Deserializer, Deserialize};
     use serde::ser::{Serialize, Serializer};
+    use crate::lib::*;
 
     #[derive(Shrinkwrap,Clone,Debug,Eq,Hash,Ord,PartialOrd,PartialEq)]
     pub struct IntArray (Vec<i32>);
@@ -474,6 +494,7 @@ pub mod typed_arrays {
 
     pub mod int_array {
         use serde::ser::{Serializer};
+        use crate::lib::*;
 
         pub fn serialize<S>(vec: &Vec<i32>, serializer: S) -> Result<S::Ok, S::Error>
         where
@@ -520,6 +541,7 @@ pub mod typed_arrays {
 
     pub mod long_array {
         use serde::ser::{Serializer};
+        use crate::lib::*;
 
         pub fn serialize<S>(vec: &Vec<i64>, serializer: S) -> Result<S::Ok, S::Error>
         where
@@ -529,7 +551,7 @@ pub mod typed_arrays {
         }
     }
 
-    use ordered_float::OrderedFloat;
+    use crate::imp::compat::OrderedFloat;
 
     #[derive(Shrinkwrap, Clone, PartialEq, PartialOrd, Ord, Eq, Hash, Debug)]
     pub struct FloatArray (Vec<OrderedFloat<f32>>);
@@ -571,6 +593,7 @@ pub mod typed_arrays {
 
     pub mod float_array {
         use serde::ser::{Serializer};
+        use crate::lib::*;
 
         pub fn serialize<S>(vec: &Vec<f32>, serializer: S) -> Result<S::Ok, S::Error>
         where
@@ -620,6 +643,7 @@ pub mod typed_arrays {
 
     pub mod double_array {
         use serde::ser::{Serializer};
+        use crate::lib::*;
 
         pub fn serialize<S>(vec: &Vec<f64>, serializer: S) -> Result<S::Ok, S::Error>
         where
@@ -667,6 +691,7 @@ pub mod typed_arrays {
 
     pub mod boolean_array {
         use serde::ser::{Serializer};
+        use crate::lib::*;
 
         pub fn serialize<S>(vec: &Vec<bool>, serializer: S) -> Result<S::Ok, S::Error>
         where
@@ -680,9 +705,12 @@ pub mod typed_arrays {
 pub mod set {
 
     use serde::ser::{Serialize, Serializer};
-    use std::collections::{BTreeSet, HashSet};
-    use std::hash::Hash;
-    use std::cmp::{Ord};
+    use crate::lib::*;
+    #[cfg(feature = "std")]
+    use std::collections::HashSet;
+    use core::hash::Hash;
+    use core::cmp::{Ord};
+    use core::iter::FromIterator;
 
     pub fn serialize<I, V, S>(set: &I, serializer: S) -> Result<S::Ok, S::Error>
     where
@@ -711,6 +739,12 @@ pub mod set {
         }
     }
 
+    impl<T: Ord> FromIterator<T> for SET<T> {
+        fn from_iter<I: IntoIterator<Item = T>>(iter: I) -> SET<T> {
+            SET(iter.into_iter().collect())
+        }
+    }
+
     impl<T> Serialize for SET<T>
         where T: Serialize + Ord,
     {
@@ -723,15 +757,18 @@ pub mod set {
         }
     }
 
+    #[cfg(feature = "std")]
     #[derive(Shrinkwrap,Clone,Deserialize)]
     pub struct HASHSET<T: Ord + Hash>(HashSet<T>);
 
+    #[cfg(feature = "std")]
     impl<T: Ord + Hash> HASHSET<T> {
         pub fn into_inner(self) -> HashSet<T> {
             self.0
         }
     }
 
+    #[cfg(feature = "std")]
     impl<T: Ord + Hash> From<HashSet<T>> for HASHSET<T>
     {
         #[inline]
@@ -740,8 +777,9 @@ pub mod set {
         }
     }
 
+    #[cfg(feature = "std")]
     impl<T> Serialize for HASHSET<T>
-        where T: Serialize + Ord + std::hash::Hash,
+        where T: Serialize + Ord + core::hash::Hash,
     {
         fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
         where
@@ -0,0 +1,315 @@
+#![allow(non_camel_case_types)]
+
+use alloc::borrow::Cow;
+use core::fmt;
+
+use serde::de::{self, Deserialize, DeserializeSeed, SeqAccess, Visitor};
+use serde::ser::Serialize;
+
+use crate::imp::codes;
+use crate::imp::compat::{ByteBuf, OrderedFloat};
+use crate::value::Value;
+use crate::inst::{INST};
+use crate::uuid::{UUID};
+use crate::uri::{URI};
+use crate::regex::{REGEX};
+use crate::sym::{SYM};
+use crate::key::{KEY};
+use crate::typed_arrays::*;
+use crate::set::{SET};
+use crate::lib::*;
+
+/// Like Value, but strings and bytes borrow from the input wherever the
+/// deserializer allows it. Strings that need decoding, and anything read
+/// through an io::Read, are owned.
+#[derive(Clone, PartialEq, PartialOrd, Ord, Eq, Hash, Debug)]
+pub enum ValueRef<'a> {
+    NULL,
+    BOOL(bool),
+    INT(i64),
+    FLOAT(OrderedFloat<f32>),
+    DOUBLE(OrderedFloat<f64>),
+    STRING(Cow<'a, str>),
+    BYTES(Cow<'a, [u8]>),
+    LIST(Vec<ValueRef<'a>>),
+    MAP(BTreeMap<ValueRef<'a>, ValueRef<'a>>),
+    SET(SET<ValueRef<'a>>),
+    SYM(SYM),
+    KEY(KEY),
+    INST(INST),
+    UUID(UUID),
+    REGEX(REGEX),
+    URI(URI),
+    IntArray(IntArray),
+    LongArray(LongArray),
+    FloatArray(FloatArray),
+    DoubleArray(DoubleArray),
+    BooleanArray(BooleanArray)
+}
+
+impl<'a> ValueRef<'a> {
+    /// copy everything borrowed into an owned Value
+    pub fn into_owned(self) -> Value {
+        match self {
+            ValueRef::NULL => Value::NULL,
+            ValueRef::BOOL(b) => Value::BOOL(b),
+            ValueRef::INT(i) => Value::INT(i),
+            ValueRef::FLOAT(f) => Value::FLOAT(f),
+            ValueRef::DOUBLE(d) => Value::DOUBLE(d),
+            ValueRef::STRING(s) => Value::STRING(s.into_owned()),
+            ValueRef::BYTES(b) => Value::BYTES(ByteBuf::from(b.into_owned())),
+            ValueRef::LIST(v) => Value::LIST(v.into_iter().map(ValueRef::into_owned).collect()),
+            ValueRef::MAP(m) => {
+                Value::MAP(m.into_iter().map(|(k, v)| (k.into_owned(), v.into_owned())).collect())
+            }
+            ValueRef::SET(s) => {
+                let set: BTreeSet<Value> = s.into_inner().into_iter().map(ValueRef::into_owned).collect();
+                Value::SET(SET::from(set))
+            }
+            ValueRef::SYM(v) => Value::SYM(v),
+            ValueRef::KEY(v) => Value::KEY(v),
+            ValueRef::INST(v) => Value::INST(v),
+            ValueRef::UUID(v) => Value::UUID(v),
+            ValueRef::REGEX(v) => Value::REGEX(v),
+            ValueRef::URI(v) => Value::URI(v),
+            ValueRef::IntArray(v) => Value::IntArray(v),
+            ValueRef::LongArray(v) => Value::LongArray(v),
+            ValueRef::FloatArray(v) => Value::FloatArray(v),
+            ValueRef::DoubleArray(v) => Value::DoubleArray(v),
+            ValueRef::BooleanArray(v) => Value::BooleanArray(v),
+        }
+    }
+
+    /// true if no part of this value borrows from the input
+    pub fn is_owned(&self) -> bool {
+        match self {
+            ValueRef::STRING(Cow::Borrowed(_)) | ValueRef::BYTES(Cow::Borrowed(_)) => false,
+            ValueRef::LIST(v) => v.iter().all(ValueRef::is_owned),
+            ValueRef::MAP(m) => m.iter().all(|(k, v)| k.is_owned() && v.is_owned()),
+            ValueRef::SET(s) => s.iter().all(ValueRef::is_owned),
+            _ => true
+        }
+    }
+}
+
+impl<'a> From<ValueRef<'a>> for Value {
+    fn from(val: ValueRef<'a>) -> Value {
+        val.into_owned()
+    }
+}
+
+impl<'a> Serialize for ValueRef<'a> {
+    #[inline]
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: ::serde::Serializer,
+    {
+        match *self {
+            ValueRef::NULL => serializer.serialize_unit(),
+            ValueRef::BOOL(b) => serializer.serialize_bool(b),
+            ValueRef::INT(ref i) => i.serialize(serializer),
+            ValueRef::FLOAT(ref f) => f.serialize(serializer),
+            ValueRef::DOUBLE(ref d) => d.serialize(serializer),
+            ValueRef::STRING(ref s) => serializer.serialize_str(s),
+            ValueRef::BYTES(ref b) => serializer.serialize_bytes(b),
+            ValueRef::LIST(ref v) => v.serialize(serializer),
+            ValueRef::MAP(ref m) => m.serialize(serializer),
+            ValueRef::SET(ref s) => s.serialize(serializer),
+            ValueRef::KEY(ref v) => v.serialize(serializer),
+            ValueRef::SYM(ref v) => v.serialize(serializer),
+            ValueRef::INST(ref v) => v.serialize(serializer),
+            ValueRef::REGEX(ref v) => v.serialize(serializer),
+            ValueRef::URI(ref v) => v.serialize(serializer),
+            ValueRef::UUID(ref v) => v.serialize(serializer),
+            ValueRef::IntArray(ref v) => v.serialize(serializer),
+            ValueRef::LongArray(ref v) => v.serialize(serializer),
+            ValueRef::FloatArray(ref v) => v.serialize(serializer),
+            ValueRef::DoubleArray(ref v) => v.serialize(serializer),
+            ValueRef::BooleanArray(ref v) => v.serialize(serializer),
+        }
+    }
+}
+
+/// reads a string, borrowing when the deserializer hands out borrowed strs
+struct STR_SEED;
+
+impl<'de> DeserializeSeed<'de> for STR_SEED {
+    type Value = Cow<'de, str>;
+
+    fn deserialize<D>(self, deserializer: D) -> Result<Self::Value, D::Error>
+        where D: serde::Deserializer<'de>
+    {
+        struct StrVisitor;
+
+        impl<'de> Visitor<'de> for StrVisitor {
+            type Value = Cow<'de, str>;
+
+            fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+                formatter.write_str("a string")
+            }
+
+            fn visit_borrowed_str<E>(self, v: &'de str) -> Result<Self::Value, E> {
+                Ok(Cow::Borrowed(v))
+            }
+
+            fn visit_str<E>(self, v: &str) -> Result<Self::Value, E> {
+                Ok(Cow::Owned(v.to_string()))
+            }
+
+            fn visit_string<E>(self, v: String) -> Result<Self::Value, E> {
+                Ok(Cow::Owned(v))
+            }
+        }
+
+        deserializer.deserialize_str(StrVisitor)
+    }
+}
+
+/// reads bytes, borrowing when the deserializer hands out borrowed bytes
+struct BYTES_SEED;
+
+impl<'de> DeserializeSeed<'de> for BYTES_SEED {
+    type Value = Cow<'de, [u8]>;
+
+    fn deserialize<D>(self, deserializer: D) -> Result<Self::Value, D::Error>
+        where D: serde::Deserializer<'de>
+    {
+        struct BytesVisitor;
+
+        impl<'de> Visitor<'de> for BytesVisitor {
+            type Value = Cow<'de, [u8]>;
+
+            fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+                formatter.write_str("bytes")
+            }
+
+            fn visit_borrowed_bytes<E>(self, v: &'de [u8]) -> Result<Self::Value, E> {
+                Ok(Cow::Borrowed(v))
+            }
+
+            fn visit_bytes<E>(self, v: &[u8]) -> Result<Self::Value, E> {
+                Ok(Cow::Owned(v.to_vec()))
+            }
+
+            fn visit_byte_buf<E>(self, v: Vec<u8>) -> Result<Self::Value, E> {
+                Ok(Cow::Owned(v))
+            }
+        }
+
+        deserializer.deserialize_bytes(BytesVisitor)
+    }
+}
+
+/// reads the code of the next value without consuming it, see Value
+struct CODE_SEED;
+
+impl<'de> DeserializeSeed<'de> for CODE_SEED {
+    type Value = i8;
+
+    fn deserialize<D>(self, deserializer: D) -> Result<i8, D::Error>
+        where D: serde::Deserializer<'de>
+    {
+        struct CodeVisitor;
+
+        impl<'de> Visitor<'de> for CodeVisitor {
+            type Value = i8;
+
+            fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+                formatter.write_str("code as i8")
+            }
+
+            fn visit_i8<E>(self, value: i8) -> Result<i8, E> {
+                Ok(value)
+            }
+        }
+
+        deserializer.deserialize_tuple_struct("CODE", 1, CodeVisitor)
+    }
+}
+
+fn next<'de, V, T>(seq: &mut V, expected: &'static str) -> Result<T, V::Error>
+    where
+        V: SeqAccess<'de>,
+        T: Deserialize<'de>,
+{
+    match seq.next_element()? {
+        Some(val) => Ok(val),
+        None => Err(de::Error::custom(expected))
+    }
+}
+
+fn next_seed<'de, V, S>(seq: &mut V, seed: S, expected: &'static str) -> Result<S::Value, V::Error>
+    where
+        V: SeqAccess<'de>,
+        S: DeserializeSeed<'de>,
+{
+    match seq.next_element_seed(seed)? {
+        Some(val) => Ok(val),
+        None => Err(de::Error::custom(expected))
+    }
+}
+
+impl<'de> Deserialize<'de> for ValueRef<'de> {
+    #[inline]
+    fn deserialize<D>(deserializer: D) -> Result<ValueRef<'de>, D::Error>
+        where D: serde::Deserializer<'de>
+    {
+        struct ValueRefVisitor;
+
+        impl<'de> Visitor<'de> for ValueRefVisitor {
+            type Value = ValueRef<'de>;
+
+            fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+                formatter.write_str("any valid fressian value")
+            }
+
+            fn visit_seq<V>(self, mut seq: V) -> Result<ValueRef<'de>, V::Error>
+            where
+                V: SeqAccess<'de>,
+            {
+                let code = next_seed(&mut seq, CODE_SEED, "code == None")?;
+
+                match code as u8 {
+                    codes::NULL => {
+                        let _: Option<()> = seq.next_element()?;
+                        Ok(ValueRef::NULL)
+                    }
+                    codes::TRUE | codes::FALSE => Ok(ValueRef::BOOL(next(&mut seq, "expected BOOL")?)),
+                    0xFF | 0x00..=0x7f | codes::INT => Ok(ValueRef::INT(next(&mut seq, "expected INT")?)),
+                    codes::FLOAT => {
+                        let f: f32 = next(&mut seq, "missing float")?;
+                        Ok(ValueRef::FLOAT(OrderedFloat::from(f)))
+                    }
+                    codes::DOUBLE | codes::DOUBLE_0 | codes::DOUBLE_1 => {
+                        let f: f64 = next(&mut seq, "missing double")?;
+                        Ok(ValueRef::DOUBLE(OrderedFloat::from(f)))
+                    }
+                    codes::UTF8
+                    | codes::STRING_PACKED_LENGTH_START..=codes::STRING_PACKED_LENGTH_END
+                    | codes::STRING => Ok(ValueRef::STRING(next_seed(&mut seq, STR_SEED, "missing STRING")?)),
+                    codes::BYTES_PACKED_LENGTH_START..=codes::BYTES_PACKED_LENGTH_END
+                    | codes::BYTES => Ok(ValueRef::BYTES(next_seed(&mut seq, BYTES_SEED, "missing BYTES")?)),
+                    codes::LIST_PACKED_LENGTH_START..=235
+                    | codes::LIST
+                    | codes::BEGIN_CLOSED_LIST
+                    | codes::BEGIN_OPEN_LIST => Ok(ValueRef::LIST(next(&mut seq, "missing LIST")?)),
+                    codes::SET => Ok(ValueRef::SET(next(&mut seq, "missing SET")?)),
+                    codes::MAP => Ok(ValueRef::MAP(next(&mut seq, "missing MAP")?)),
+                    codes::KEY => Ok(ValueRef::KEY(next(&mut seq, "missing KEY")?)),
+                    codes::SYM => Ok(ValueRef::SYM(next(&mut seq, "missing SYM")?)),
+                    codes::INST => Ok(ValueRef::INST(next(&mut seq, "missing INST")?)),
+                    codes::REGEX => Ok(ValueRef::REGEX(next(&mut seq, "missing REGEX")?)),
+                    codes::UUID => Ok(ValueRef::UUID(next(&mut seq, "missing UUID")?)),
+                    codes::URI => Ok(ValueRef::URI(next(&mut seq, "missing URI")?)),
+                    codes::INT_ARRAY => Ok(ValueRef::IntArray(next(&mut seq, "missing INT_ARRAY")?)),
+                    codes::LONG_ARRAY => Ok(ValueRef::LongArray(next(&mut seq, "missing LONG_ARRAY")?)),
+                    codes::FLOAT_ARRAY => Ok(ValueRef::FloatArray(next(&mut seq, "missing FLOAT_ARRAY")?)),
+                    codes::DOUBLE_ARRAY => Ok(ValueRef::DoubleArray(next(&mut seq, "missing DOUBLE_ARRAY")?)),
+                    codes::BOOLEAN_ARRAY => Ok(ValueRef::BooleanArray(next(&mut seq, "missing BOOLEAN_ARRAY")?)),
+                    _ => Err(de::Error::custom(format!("ValueRef UnmatchedCode:: {}", code as u8)))
+                }
+            }
+        }
+        deserializer.deserialize_tuple_struct("VALUE", 2, ValueRefVisitor)
+    }
+}
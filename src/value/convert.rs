@@ -0,0 +1,309 @@
+use core::convert::TryFrom;
+
+use crate::error::Error;
+use crate::imp::compat::ByteBuf;
+use crate::value::Value;
+use crate::inst::{INST};
+use crate::uuid::{UUID};
+use crate::uri::{URI};
+use crate::regex::{REGEX};
+use crate::sym::{SYM};
+use crate::key::{KEY};
+use crate::typed_arrays::*;
+use crate::set::{SET};
+use crate::lib::*;
+
+impl Value {
+    /// the name of this value's variant, as used in conversion errors
+    pub fn type_name(&self) -> &'static str {
+        match *self {
+            Value::NULL => "NULL",
+            Value::BOOL(_) => "BOOL",
+            Value::INT(_) => "INT",
+            Value::FLOAT(_) => "FLOAT",
+            Value::DOUBLE(_) => "DOUBLE",
+            Value::STRING(_) => "STRING",
+            Value::BYTES(_) => "BYTES",
+            Value::LIST(_) => "LIST",
+            Value::MAP(_) => "MAP",
+            Value::SET(_) => "SET",
+            Value::SYM(_) => "SYM",
+            Value::KEY(_) => "KEY",
+            Value::INST(_) => "INST",
+            Value::UUID(_) => "UUID",
+            Value::REGEX(_) => "REGEX",
+            Value::URI(_) => "URI",
+            Value::IntArray(_) => "INT_ARRAY",
+            Value::LongArray(_) => "LONG_ARRAY",
+            Value::FloatArray(_) => "FLOAT_ARRAY",
+            Value::DoubleArray(_) => "DOUBLE_ARRAY",
+            Value::BooleanArray(_) => "BOOLEAN_ARRAY",
+        }
+    }
+
+    pub fn is_null(&self) -> bool { matches!(self, Value::NULL) }
+
+    pub fn is_bool(&self) -> bool { matches!(self, Value::BOOL(_)) }
+
+    pub fn is_int(&self) -> bool { matches!(self, Value::INT(_)) }
+
+    pub fn is_float(&self) -> bool { matches!(self, Value::FLOAT(_)) }
+
+    pub fn is_double(&self) -> bool { matches!(self, Value::DOUBLE(_)) }
+
+    /// INT, FLOAT or DOUBLE
+    pub fn is_number(&self) -> bool {
+        matches!(self, Value::INT(_) | Value::FLOAT(_) | Value::DOUBLE(_))
+    }
+
+    pub fn is_string(&self) -> bool { matches!(self, Value::STRING(_)) }
+
+    pub fn is_bytes(&self) -> bool { matches!(self, Value::BYTES(_)) }
+
+    pub fn is_list(&self) -> bool { matches!(self, Value::LIST(_)) }
+
+    pub fn is_map(&self) -> bool { matches!(self, Value::MAP(_)) }
+
+    pub fn is_set(&self) -> bool { matches!(self, Value::SET(_)) }
+
+    /// LIST, MAP or SET
+    pub fn is_coll(&self) -> bool {
+        matches!(self, Value::LIST(_) | Value::MAP(_) | Value::SET(_))
+    }
+
+    pub fn is_symbol(&self) -> bool { matches!(self, Value::SYM(_)) }
+
+    pub fn is_keyword(&self) -> bool { matches!(self, Value::KEY(_)) }
+
+    pub fn is_inst(&self) -> bool { matches!(self, Value::INST(_)) }
+
+    pub fn is_uuid(&self) -> bool { matches!(self, Value::UUID(_)) }
+
+    pub fn is_regex(&self) -> bool { matches!(self, Value::REGEX(_)) }
+
+    pub fn is_uri(&self) -> bool { matches!(self, Value::URI(_)) }
+
+    /// any of the typed arrays
+    pub fn is_typed_array(&self) -> bool {
+        matches!(self,
+                 Value::IntArray(_)
+                 | Value::LongArray(_)
+                 | Value::FloatArray(_)
+                 | Value::DoubleArray(_)
+                 | Value::BooleanArray(_))
+    }
+
+    pub fn as_bool(&self) -> Option<bool> {
+        match *self {
+            Value::BOOL(b) => Some(b),
+            _ => None
+        }
+    }
+
+    pub fn as_i64(&self) -> Option<i64> {
+        match *self {
+            Value::INT(i) => Some(i),
+            _ => None
+        }
+    }
+
+    pub fn as_f32(&self) -> Option<f32> {
+        match *self {
+            Value::FLOAT(f) => Some(f.into_inner()),
+            _ => None
+        }
+    }
+
+    /// DOUBLE, or a FLOAT widened to f64
+    pub fn as_f64(&self) -> Option<f64> {
+        match *self {
+            Value::DOUBLE(d) => Some(d.into_inner()),
+            Value::FLOAT(f) => Some(f64::from(f.into_inner())),
+            _ => None
+        }
+    }
+
+    pub fn as_str(&self) -> Option<&str> {
+        match *self {
+            Value::STRING(ref s) => Some(s),
+            _ => None
+        }
+    }
+
+    pub fn as_bytes(&self) -> Option<&[u8]> {
+        match *self {
+            Value::BYTES(ref b) => Some(b),
+            _ => None
+        }
+    }
+
+    pub fn as_list(&self) -> Option<&Vec<Value>> {
+        match *self {
+            Value::LIST(ref v) => Some(v),
+            _ => None
+        }
+    }
+
+    pub fn as_list_mut(&mut self) -> Option<&mut Vec<Value>> {
+        match *self {
+            Value::LIST(ref mut v) => Some(v),
+            _ => None
+        }
+    }
+
+    pub fn as_map(&self) -> Option<&BTreeMap<Value, Value>> {
+        match *self {
+            Value::MAP(ref m) => Some(m),
+            _ => None
+        }
+    }
+
+    pub fn as_map_mut(&mut self) -> Option<&mut BTreeMap<Value, Value>> {
+        match *self {
+            Value::MAP(ref mut m) => Some(m),
+            _ => None
+        }
+    }
+
+    pub fn as_set(&self) -> Option<&BTreeSet<Value>> {
+        match *self {
+            Value::SET(ref s) => Some(s),
+            _ => None
+        }
+    }
+
+    pub fn as_symbol(&self) -> Option<&SYM> {
+        match *self {
+            Value::SYM(ref s) => Some(s),
+            _ => None
+        }
+    }
+
+    pub fn as_keyword(&self) -> Option<&KEY> {
+        match *self {
+            Value::KEY(ref k) => Some(k),
+            _ => None
+        }
+    }
+
+    pub fn as_inst(&self) -> Option<&INST> {
+        match *self {
+            Value::INST(ref inst) => Some(inst),
+            _ => None
+        }
+    }
+
+    pub fn as_uuid(&self) -> Option<&UUID> {
+        match *self {
+            Value::UUID(ref u) => Some(u),
+            _ => None
+        }
+    }
+
+    pub fn as_regex(&self) -> Option<&REGEX> {
+        match *self {
+            Value::REGEX(ref re) => Some(re),
+            _ => None
+        }
+    }
+
+    pub fn as_uri(&self) -> Option<&URI> {
+        match *self {
+            Value::URI(ref uri) => Some(uri),
+            _ => None
+        }
+    }
+}
+
+macro_rules! impl_try_from_value {
+    ($variant:ident : $T:ty, $expected:expr) => {
+        impl TryFrom<Value> for $T {
+            type Error = Error;
+
+            fn try_from(val: Value) -> Result<$T, Error> {
+                match val {
+                    Value::$variant(v) => Ok(v),
+                    other => Err(Error::unexpected_value_type($expected, other.type_name()))
+                }
+            }
+        }
+    }
+}
+
+impl_try_from_value!(BOOL: bool, "BOOL");
+impl_try_from_value!(STRING: String, "STRING");
+impl_try_from_value!(BYTES: ByteBuf, "BYTES");
+impl_try_from_value!(LIST: Vec<Value>, "LIST");
+impl_try_from_value!(MAP: BTreeMap<Value, Value>, "MAP");
+impl_try_from_value!(SET: SET<Value>, "SET");
+impl_try_from_value!(SYM: SYM, "SYM");
+impl_try_from_value!(KEY: KEY, "KEY");
+impl_try_from_value!(INST: INST, "INST");
+impl_try_from_value!(UUID: UUID, "UUID");
+impl_try_from_value!(REGEX: REGEX, "REGEX");
+impl_try_from_value!(URI: URI, "URI");
+impl_try_from_value!(IntArray: IntArray, "INT_ARRAY");
+impl_try_from_value!(LongArray: LongArray, "LONG_ARRAY");
+impl_try_from_value!(FloatArray: FloatArray, "FLOAT_ARRAY");
+impl_try_from_value!(DoubleArray: DoubleArray, "DOUBLE_ARRAY");
+impl_try_from_value!(BooleanArray: BooleanArray, "BOOLEAN_ARRAY");
+
+// ints that do not fit are reported as the rust type expected
+macro_rules! impl_try_from_int {
+    ($T:ty) => {
+        impl TryFrom<Value> for $T {
+            type Error = Error;
+
+            fn try_from(val: Value) -> Result<$T, Error> {
+                match val {
+                    Value::INT(i) => {
+                        <$T>::try_from(i).map_err(|_| Error::unexpected_value_type(stringify!($T), "INT"))
+                    }
+                    other => Err(Error::unexpected_value_type("INT", other.type_name()))
+                }
+            }
+        }
+    }
+}
+
+impl_try_from_int!(i8);
+impl_try_from_int!(i16);
+impl_try_from_int!(i32);
+impl_try_from_int!(u8);
+impl_try_from_int!(u16);
+impl_try_from_int!(u32);
+impl_try_from_int!(u64);
+impl_try_from_int!(usize);
+
+impl TryFrom<Value> for i64 {
+    type Error = Error;
+
+    fn try_from(val: Value) -> Result<i64, Error> {
+        match val {
+            Value::INT(i) => Ok(i),
+            other => Err(Error::unexpected_value_type("INT", other.type_name()))
+        }
+    }
+}
+
+impl TryFrom<Value> for f32 {
+    type Error = Error;
+
+    fn try_from(val: Value) -> Result<f32, Error> {
+        match val {
+            Value::FLOAT(f) => Ok(f.into_inner()),
+            other => Err(Error::unexpected_value_type("FLOAT", other.type_name()))
+        }
+    }
+}
+
+impl TryFrom<Value> for f64 {
+    type Error = Error;
+
+    fn try_from(val: Value) -> Result<f64, Error> {
+        match val.as_f64() {
+            Some(d) => Ok(d),
+            None => Err(Error::unexpected_value_type("DOUBLE", val.type_name()))
+        }
+    }
+}
@@ -1,13 +1,16 @@
 #![allow(non_camel_case_types, non_snake_case)]
 
-use std::collections::{BTreeMap};
-use std::fmt;
+use core::fmt;
 
-use serde::de::{ self, Deserialize, SeqAccess, Visitor};
+use serde::de::{
+    self, Deserialize, DeserializeSeed, EnumAccess, IntoDeserializer, SeqAccess,
+    VariantAccess, Visitor,
+};
+use serde::de::value::{MapDeserializer, SeqDeserializer};
 
-use ordered_float::OrderedFloat;
-use serde_bytes::ByteBuf;
+use crate::imp::compat::{ByteBuf, OrderedFloat};
 
+use crate::error::{Error, Result};
 use crate::imp::codes;
 use crate::value::Value;
 use crate::inst::{INST};
@@ -18,6 +21,7 @@ use crate::sym::{SYM};
 use crate::key::KEY;
 use crate::typed_arrays::*;
 use crate::set::{SET};
+use crate::lib::*;
 
 macro_rules! impl_seed {
     ($variant:ident : $T:ident) => {
@@ -27,7 +31,7 @@ macro_rules! impl_seed {
         impl<'de> de::DeserializeSeed<'de> for $variant {
             type Value = $T;
 
-            fn deserialize<D>(self, deserializer: D) -> Result<$T, D::Error>
+            fn deserialize<D>(self, deserializer: D) -> ::core::result::Result<$T, D::Error>
                 where D: serde::Deserializer<'de>
             {
                 $T::deserialize(deserializer)
@@ -52,7 +56,7 @@ impl_seed!(BOOLEAN_ARRAY_SEED: BooleanArray);
 
 impl<'de> Deserialize<'de> for Value {
     #[inline]
-    fn deserialize<D>(deserializer: D) -> Result<Value, D::Error>
+    fn deserialize<D>(deserializer: D) -> ::core::result::Result<Value, D::Error>
         where D: serde::Deserializer<'de>
     {
         struct ValueVisitor;
@@ -60,7 +64,7 @@ impl<'de> Deserialize<'de> for Value {
         impl<'de> de::DeserializeSeed<'de>  for ValueVisitor {
             type Value = i8;
 
-            fn deserialize<D>(self, deserializer: D) -> Result<i8, D::Error>
+            fn deserialize<D>(self, deserializer: D) -> ::core::result::Result<i8, D::Error>
                 where D: serde::Deserializer<'de>
             {
                 struct CodeVisitor;
@@ -73,7 +77,7 @@ impl<'de> Deserialize<'de> for Value {
                         formatter.write_str("code as i8")
                     }
 
-                    fn visit_i8<E>(self, value: i8) -> Result<i8, E> {
+                    fn visit_i8<E>(self, value: i8) -> ::core::result::Result<i8, E> {
                         Ok(value)
                     }
                 }
@@ -91,7 +95,7 @@ impl<'de> Deserialize<'de> for Value {
             }
 
             #[inline]
-            fn visit_seq<V>(self, mut seq: V) -> Result<Value, V::Error>
+            fn visit_seq<V>(self, mut seq: V) -> ::core::result::Result<Value, V::Error>
             where
                 V: SeqAccess<'de>,
             {
@@ -293,7 +297,244 @@ impl<'de> Deserialize<'de> for Value {
                 }
             }
         }
-        deserializer.deserialize_tuple(2, ValueVisitor)
+        deserializer.deserialize_tuple_struct("VALUE", 2, ValueVisitor)
     }
 }
 
+
+impl Value {
+    /// the fressian code this value is written with, for the VALUE protocol
+    fn code(&self) -> u8 {
+        match *self {
+            Value::NULL => codes::NULL,
+            Value::BOOL(true) => codes::TRUE,
+            Value::BOOL(false) => codes::FALSE,
+            Value::INT(_) => codes::INT,
+            Value::FLOAT(_) => codes::FLOAT,
+            Value::DOUBLE(_) => codes::DOUBLE,
+            Value::STRING(_) => codes::STRING,
+            Value::BYTES(_) => codes::BYTES,
+            Value::LIST(_) => codes::LIST,
+            Value::MAP(_) => codes::MAP,
+            Value::SET(_) => codes::SET,
+            Value::SYM(_) => codes::SYM,
+            Value::KEY(_) => codes::KEY,
+            Value::INST(_) => codes::INST,
+            Value::UUID(_) => codes::UUID,
+            Value::REGEX(_) => codes::REGEX,
+            Value::URI(_) => codes::URI,
+            Value::IntArray(_) => codes::INT_ARRAY,
+            Value::LongArray(_) => codes::LONG_ARRAY,
+            Value::FloatArray(_) => codes::FLOAT_ARRAY,
+            Value::DoubleArray(_) => codes::DOUBLE_ARRAY,
+            Value::BooleanArray(_) => codes::BOOLEAN_ARRAY,
+        }
+    }
+}
+
+/// a namespace and name pair, read the same way as the components of a KEY or SYM
+fn named(namespace: Option<&str>, name: &str) -> Vec<Value> {
+    let namespace = match namespace {
+        Some(ns) => Value::STRING(ns.to_string()),
+        None => Value::NULL
+    };
+    vec![namespace, Value::STRING(name.to_string())]
+}
+
+fn visit_values<'de, V, I>(values: I, visitor: V) -> Result<V::Value>
+    where
+        V: Visitor<'de>,
+        I: IntoIterator<Item = Value>,
+{
+    let mut seq = SeqDeserializer::new(values.into_iter());
+    let value = visitor.visit_seq(&mut seq)?;
+    seq.end()?;
+    Ok(value)
+}
+
+/// Values are read the same way the byte deserializer reads fressian, so a
+/// Value can be turned into anything that could have been read from its bytes
+impl<'de> serde::Deserializer<'de> for Value {
+    type Error = Error;
+
+    fn deserialize_any<V>(self, visitor: V) -> Result<V::Value>
+    where
+        V: Visitor<'de>,
+    {
+        match self {
+            Value::NULL => visitor.visit_unit(),
+            Value::BOOL(b) => visitor.visit_bool(b),
+            Value::INT(i) => visitor.visit_i64(i),
+            Value::FLOAT(f) => visitor.visit_f32(f.into_inner()),
+            Value::DOUBLE(d) => visitor.visit_f64(d.into_inner()),
+            Value::STRING(s) => visitor.visit_string(s),
+            Value::BYTES(b) => visitor.visit_byte_buf(Vec::from(b)),
+            Value::LIST(v) => visit_values(v, visitor),
+            Value::MAP(m) => {
+                let mut map = MapDeserializer::new(m.into_iter());
+                let value = visitor.visit_map(&mut map)?;
+                map.end()?;
+                Ok(value)
+            }
+            Value::SET(s) => visit_values(s.into_inner(), visitor),
+            Value::SYM(s) => visit_values(named(s.namespace(), s.name()), visitor),
+            Value::KEY(k) => visit_values(named(k.namespace(), k.name()), visitor),
+            Value::INST(inst) => visitor.visit_i64(inst.to_millis()),
+            Value::UUID(u) => visitor.visit_byte_buf(Vec::from(u.into_inner())),
+            Value::REGEX(re) => visitor.visit_string(re.into_inner()),
+            Value::URI(uri) => visitor.visit_string(uri.into_inner()),
+            Value::IntArray(a) => visit_values(a.iter().map(|i| Value::INT(*i as i64)), visitor),
+            Value::LongArray(a) => visit_values(a.iter().map(|i| Value::INT(*i)), visitor),
+            Value::FloatArray(a) => visit_values(a.iter().map(|f| Value::FLOAT(*f)), visitor),
+            Value::DoubleArray(a) => visit_values(a.iter().map(|d| Value::DOUBLE(*d)), visitor),
+            Value::BooleanArray(a) => visit_values(a.iter().map(|b| Value::BOOL(*b)), visitor),
+        }
+    }
+
+    fn deserialize_option<V>(self, visitor: V) -> Result<V::Value>
+    where
+        V: Visitor<'de>,
+    {
+        match self {
+            Value::NULL => visitor.visit_none(),
+            _ => visitor.visit_some(self)
+        }
+    }
+
+    fn deserialize_newtype_struct<V>(self, _name: &'static str, visitor: V) -> Result<V::Value>
+    where
+        V: Visitor<'de>,
+    {
+        visitor.visit_newtype_struct(self)
+    }
+
+    fn deserialize_tuple_struct<V>(self, name: &'static str, _len: usize, visitor: V) -> Result<V::Value>
+    where
+        V: Visitor<'de>,
+    {
+        match name {
+            // Value and ValueRef peek the code before reading the value itself
+            "VALUE" => {
+                visitor.visit_seq(ValueSeq {
+                    code: Some(self.code() as i8),
+                    value: Some(self)
+                })
+            }
+            _ => self.deserialize_any(visitor)
+        }
+    }
+
+    fn deserialize_enum<V>(self, _name: &'static str, _variants: &'static [&'static str], visitor: V) -> Result<V::Value>
+    where
+        V: Visitor<'de>,
+    {
+        // unit variants are written as their name, the others as [name value]
+        match self {
+            Value::STRING(variant) => visitor.visit_enum(VariantDeserializer { variant, value: None }),
+            Value::LIST(mut v) if v.len() == 2 => {
+                let value = v.pop();
+                match v.pop() {
+                    Some(Value::STRING(variant)) => visitor.visit_enum(VariantDeserializer { variant, value }),
+                    _ => Err(Error::msg("expected an enum variant name".to_string()))
+                }
+            }
+            _ => Err(Error::msg("expected an enum variant".to_string()))
+        }
+    }
+
+    serde::forward_to_deserialize_any! {
+        bool i8 i16 i32 i64 i128 u8 u16 u32 u64 u128 f32 f64 char str string
+        bytes byte_buf unit unit_struct seq tuple map struct identifier ignored_any
+    }
+}
+
+impl<'de> IntoDeserializer<'de, Error> for Value {
+    type Deserializer = Value;
+
+    fn into_deserializer(self) -> Value {
+        self
+    }
+}
+
+struct ValueSeq {
+    code: Option<i8>,
+    value: Option<Value>
+}
+
+impl<'de> SeqAccess<'de> for ValueSeq {
+    type Error = Error;
+
+    fn next_element_seed<T>(&mut self, seed: T) -> Result<Option<T::Value>>
+    where
+        T: DeserializeSeed<'de>,
+    {
+        if let Some(code) = self.code.take() {
+            let code: de::value::I8Deserializer<Error> = code.into_deserializer();
+            seed.deserialize(code).map(Some)
+        } else if let Some(value) = self.value.take() {
+            seed.deserialize(value).map(Some)
+        } else {
+            Ok(None)
+        }
+    }
+}
+
+struct VariantDeserializer {
+    variant: String,
+    value: Option<Value>
+}
+
+impl<'de> EnumAccess<'de> for VariantDeserializer {
+    type Error = Error;
+    type Variant = Self;
+
+    fn variant_seed<V>(self, seed: V) -> Result<(V::Value, Self)>
+    where
+        V: DeserializeSeed<'de>,
+    {
+        let variant: de::value::StringDeserializer<Error> = self.variant.clone().into_deserializer();
+        let variant = seed.deserialize(variant)?;
+        Ok((variant, self))
+    }
+}
+
+impl<'de> VariantAccess<'de> for VariantDeserializer {
+    type Error = Error;
+
+    fn unit_variant(self) -> Result<()> {
+        match self.value {
+            None => Ok(()),
+            Some(_) => Err(Error::msg("expected a unit variant".to_string()))
+        }
+    }
+
+    fn newtype_variant_seed<T>(self, seed: T) -> Result<T::Value>
+    where
+        T: DeserializeSeed<'de>,
+    {
+        match self.value {
+            Some(value) => seed.deserialize(value),
+            None => Err(Error::msg("expected a newtype variant".to_string()))
+        }
+    }
+
+    fn tuple_variant<V>(self, _len: usize, visitor: V) -> Result<V::Value>
+    where
+        V: Visitor<'de>,
+    {
+        match self.value {
+            Some(value) => serde::Deserializer::deserialize_seq(value, visitor),
+            None => Err(Error::msg("expected a tuple variant".to_string()))
+        }
+    }
+
+    fn struct_variant<V>(self, _fields: &'static [&'static str], visitor: V) -> Result<V::Value>
+    where
+        V: Visitor<'de>,
+    {
+        match self.value {
+            Some(value) => serde::Deserializer::deserialize_any(value, visitor),
+            None => Err(Error::msg("expected a struct variant".to_string()))
+        }
+    }
+}
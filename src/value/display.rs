@@ -0,0 +1,271 @@
+use core::fmt::{self, Display, Write};
+
+use crate::value::Value;
+use crate::inst::{INST};
+use crate::uuid::{UUID};
+
+/// collections longer than this are broken over several lines when pretty printing
+const PRETTY_WIDTH: usize = 80;
+
+/// Writes the value as EDN. `{:#}` pretty prints, breaking collections that
+/// do not fit on a line into one element (or map entry) per line.
+///
+/// Fressian types without an EDN literal are written as tagged literals:
+/// `#bytes [..]`, `#regex ".."`, `#uri ".."` and `#ints`, `#longs`, `#floats`,
+/// `#doubles` and `#booleans` for the typed arrays
+impl Display for Value {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        if f.alternate() {
+            write_pretty(self, f, 0)
+        } else {
+            write_edn(self, f)
+        }
+    }
+}
+
+fn write_str<W: Write>(s: &str, w: &mut W) -> fmt::Result {
+    w.write_char('"')?;
+    for c in s.chars() {
+        match c {
+            '"' => w.write_str("\\\"")?,
+            '\\' => w.write_str("\\\\")?,
+            '\n' => w.write_str("\\n")?,
+            '\r' => w.write_str("\\r")?,
+            '\t' => w.write_str("\\t")?,
+            c if c.is_control() => write!(w, "\\u{:04x}", c as u32)?,
+            c => w.write_char(c)?,
+        }
+    }
+    w.write_char('"')
+}
+
+/// EDN floats always have a fraction or exponent, and name the non finite values
+fn write_double<W: Write>(d: f64, w: &mut W) -> fmt::Result {
+    if d.is_nan() {
+        w.write_str("##NaN")
+    } else if d.is_infinite() {
+        w.write_str(if d < 0.0 { "##-Inf" } else { "##Inf" })
+    } else {
+        write!(w, "{:?}", d)
+    }
+}
+
+fn write_float<W: Write>(f: f32, w: &mut W) -> fmt::Result {
+    if f.is_finite() {
+        write!(w, "{:?}", f)
+    } else {
+        write_double(f64::from(f), w)
+    }
+}
+
+fn write_named<W: Write>(namespace: Option<&str>, name: &str, w: &mut W) -> fmt::Result {
+    match namespace {
+        Some(ns) => write!(w, "{}/{}", ns, name),
+        None => w.write_str(name)
+    }
+}
+
+/// days since 1970-01-01 to (year, month, day), see
+/// http://howardhinnant.github.io/date_algorithms.html#civil_from_days
+fn civil_from_days(days: i64) -> (i64, i64, i64) {
+    let z = days + 719_468;
+    let era = z.div_euclid(146_097);
+    let doe = z - era * 146_097;
+    let yoe = (doe - doe / 1460 + doe / 36_524 - doe / 146_096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = doy - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = yoe + era * 400 + if month <= 2 { 1 } else { 0 };
+    (year, month, day)
+}
+
+/// RFC3339 in UTC, the way clojure prints an inst
+fn write_inst<W: Write>(inst: &INST, w: &mut W) -> fmt::Result {
+    let ms = inst.to_millis();
+    let (days, ms_of_day) = (ms.div_euclid(86_400_000), ms.rem_euclid(86_400_000));
+    let (year, month, day) = civil_from_days(days);
+    let secs = ms_of_day / 1000;
+    write!(w, "#inst \"{:04}-{:02}-{:02}T{:02}:{:02}:{:02}.{:03}-00:00\"",
+           year, month, day, secs / 3600, secs / 60 % 60, secs % 60, ms_of_day % 1000)
+}
+
+fn write_uuid<W: Write>(uuid: &UUID, w: &mut W) -> fmt::Result {
+    let bytes: &[u8] = uuid.as_ref();
+    w.write_str("#uuid \"")?;
+    for (i, b) in bytes.iter().enumerate() {
+        if bytes.len() == 16 && (i == 4 || i == 6 || i == 8 || i == 10) {
+            w.write_char('-')?;
+        }
+        write!(w, "{:02x}", b)?;
+    }
+    w.write_char('"')
+}
+
+fn write_all<W, I, F>(items: I, w: &mut W, f: F) -> fmt::Result
+where
+    W: Write,
+    I: IntoIterator,
+    F: Fn(I::Item, &mut W) -> fmt::Result,
+{
+    for (i, item) in items.into_iter().enumerate() {
+        if 0 < i {
+            w.write_char(' ')?;
+        }
+        f(item, w)?;
+    }
+    Ok(())
+}
+
+fn write_edn<W: Write>(value: &Value, w: &mut W) -> fmt::Result {
+    match *value {
+        Value::NULL => w.write_str("nil"),
+        Value::BOOL(b) => write!(w, "{}", b),
+        Value::INT(i) => write!(w, "{}", i),
+        Value::FLOAT(f) => write_float(f.into_inner(), w),
+        Value::DOUBLE(d) => write_double(d.into_inner(), w),
+        Value::STRING(ref s) => write_str(s, w),
+        Value::BYTES(ref b) => {
+            w.write_str("#bytes [")?;
+            write_all(b.iter(), w, |b, w| write!(w, "{}", b))?;
+            w.write_char(']')
+        }
+        Value::LIST(ref v) => {
+            w.write_char('[')?;
+            write_all(v, w, write_edn)?;
+            w.write_char(']')
+        }
+        Value::MAP(ref m) => {
+            w.write_char('{')?;
+            for (i, (k, v)) in m.iter().enumerate() {
+                if 0 < i {
+                    w.write_str(", ")?;
+                }
+                write_edn(k, w)?;
+                w.write_char(' ')?;
+                write_edn(v, w)?;
+            }
+            w.write_char('}')
+        }
+        Value::SET(ref s) => {
+            w.write_str("#{")?;
+            write_all(s.iter(), w, write_edn)?;
+            w.write_char('}')
+        }
+        Value::SYM(ref s) => write_named(s.namespace(), s.name(), w),
+        Value::KEY(ref k) => {
+            w.write_char(':')?;
+            write_named(k.namespace(), k.name(), w)
+        }
+        Value::INST(ref inst) => write_inst(inst, w),
+        Value::UUID(ref uuid) => write_uuid(uuid, w),
+        Value::REGEX(ref re) => {
+            w.write_str("#regex ")?;
+            write_str(re.as_str(), w)
+        }
+        Value::URI(ref uri) => {
+            w.write_str("#uri ")?;
+            write_str(uri.as_str(), w)
+        }
+        Value::IntArray(ref a) => {
+            w.write_str("#ints [")?;
+            write_all(a.iter(), w, |i, w| write!(w, "{}", i))?;
+            w.write_char(']')
+        }
+        Value::LongArray(ref a) => {
+            w.write_str("#longs [")?;
+            write_all(a.iter(), w, |i, w| write!(w, "{}", i))?;
+            w.write_char(']')
+        }
+        Value::FloatArray(ref a) => {
+            w.write_str("#floats [")?;
+            write_all(a.iter(), w, |f, w| write_float(f.into_inner(), w))?;
+            w.write_char(']')
+        }
+        Value::DoubleArray(ref a) => {
+            w.write_str("#doubles [")?;
+            write_all(a.iter(), w, |d, w| write_double(d.into_inner(), w))?;
+            w.write_char(']')
+        }
+        Value::BooleanArray(ref a) => {
+            w.write_str("#booleans [")?;
+            write_all(a.iter(), w, |b, w| write!(w, "{}", b))?;
+            w.write_char(']')
+        }
+    }
+}
+
+fn write_newline(indent: usize, f: &mut fmt::Formatter) -> fmt::Result {
+    f.write_char('\n')?;
+    for _ in 0..indent {
+        f.write_char(' ')?;
+    }
+    Ok(())
+}
+
+/// counts what is written, and gives up once there is more than `room`
+struct Measure {
+    len: usize,
+    room: usize,
+}
+
+impl Write for Measure {
+    fn write_str(&mut self, s: &str) -> fmt::Result {
+        self.len += s.len();
+        if self.room < self.len { Err(fmt::Error) } else { Ok(()) }
+    }
+}
+
+/// the compact length of `value`, or None when it is longer than `room`.
+/// Only `room` bytes are rendered, so checking every level stays linear
+fn measure(value: &Value, room: usize) -> Option<usize> {
+    let mut m = Measure { len: 0, room };
+    match write_edn(value, &mut m) {
+        Ok(()) => Some(m.len),
+        Err(_) => None
+    }
+}
+
+/// `column` is where the value starts, so that broken collections line up
+fn write_pretty(value: &Value, f: &mut fmt::Formatter, column: usize) -> fmt::Result {
+    if measure(value, PRETTY_WIDTH.saturating_sub(column)).is_some() {
+        return write_edn(value, f)
+    }
+    match *value {
+        Value::LIST(ref v) => {
+            f.write_char('[')?;
+            for (i, item) in v.iter().enumerate() {
+                if 0 < i {
+                    write_newline(column + 1, f)?;
+                }
+                write_pretty(item, f, column + 1)?;
+            }
+            f.write_char(']')
+        }
+        Value::SET(ref s) => {
+            f.write_str("#{")?;
+            for (i, item) in s.iter().enumerate() {
+                if 0 < i {
+                    write_newline(column + 2, f)?;
+                }
+                write_pretty(item, f, column + 2)?;
+            }
+            f.write_char('}')
+        }
+        Value::MAP(ref m) => {
+            f.write_char('{')?;
+            for (i, (k, v)) in m.iter().enumerate() {
+                if 0 < i {
+                    f.write_char(',')?;
+                    write_newline(column + 1, f)?;
+                }
+                let key = measure(k, usize::MAX).unwrap_or(0);
+                write_edn(k, f)?;
+                f.write_char(' ')?;
+                write_pretty(v, f, column + 1 + key + 1)?;
+            }
+            f.write_char('}')
+        }
+        _ => write_edn(value, f)
+    }
+}
@@ -0,0 +1,579 @@
+use core::char;
+use core::convert::TryFrom;
+
+use crate::error::{Error, ErrorCode, Result};
+use crate::imp::compat::{ByteBuf, OrderedFloat};
+use crate::value::Value;
+use crate::inst::{INST};
+use crate::uuid::{UUID};
+use crate::uri::{URI};
+use crate::regex::{REGEX};
+use crate::sym::{SYM};
+use crate::key::{KEY};
+use crate::typed_arrays::*;
+use crate::set::{SET};
+use crate::lib::*;
+
+/// Read a single EDN form as a Value.
+///
+///   + lists and vectors are both read as LIST
+///   + chars are read as one character STRINGs, which is how they are written
+///   + `N` ints are read as INT, failing when they do not fit in an i64
+///   + `M` decimals are read as DOUBLE, there is no BIGDEC yet. One that a
+///     double cannot hold exactly, such as `0.1000000000000000001M`, is an
+///     error rather than being rounded. The shortest digits of the double
+///     have to match, so `0.1M` is fine
+///   + collections and tagged forms nest at most 512 deep
+///   + besides `#inst` and `#uuid`, the tags written by Value's Display are
+///     read back: `#bytes`, `#regex`, `#uri`, `#ints`, `#longs`, `#floats`,
+///     `#doubles` and `#booleans`. `#"re"` is read as a REGEX
+///
+/// Errors are `ErrorCode::InvalidEdn` at the byte offset of the problem.
+pub fn from_edn(s: &str) -> Result<Value> {
+    let mut reader = EdnReader { input: s, pos: 0, depth: 0 };
+    let value = reader.read_form()?;
+    reader.skip_whitespace();
+    if reader.pos < s.len() {
+        return Err(Error::syntax(ErrorCode::TrailingBytes, reader.pos))
+    }
+    Ok(value)
+}
+
+/// how deeply forms may nest, the same limit the Walker has for bytes
+const MAX_DEPTH: usize = 512;
+
+struct EdnReader<'a> {
+    input: &'a str,
+    pos: usize,
+    depth: usize,
+}
+
+fn is_delimiter(c: char) -> bool {
+    c.is_whitespace() || matches!(c, ',' | '(' | ')' | '[' | ']' | '{' | '}' | '"' | ';')
+}
+
+impl<'a> EdnReader<'a> {
+    fn error<T>(&self, msg: &'static str) -> Result<T> {
+        Err(Error::syntax(ErrorCode::InvalidEdn(msg), self.pos))
+    }
+
+    fn peek(&self) -> Option<char> {
+        self.input[self.pos..].chars().next()
+    }
+
+    fn next_char(&mut self) -> Option<char> {
+        let c = self.peek()?;
+        self.pos += c.len_utf8();
+        Some(c)
+    }
+
+    /// whitespace, commas and ; comments
+    fn skip_whitespace(&mut self) {
+        while let Some(c) = self.peek() {
+            if c.is_whitespace() || c == ',' {
+                self.pos += c.len_utf8();
+            } else if c == ';' {
+                while let Some(c) = self.next_char() {
+                    if c == '\n' {
+                        break
+                    }
+                }
+            } else {
+                break
+            }
+        }
+    }
+
+    /// everything up to the next delimiter
+    fn read_token(&mut self) -> &'a str {
+        let start = self.pos;
+        while let Some(c) = self.peek() {
+            if is_delimiter(c) {
+                break
+            }
+            self.pos += c.len_utf8();
+        }
+        &self.input[start..self.pos]
+    }
+
+    /// one form, failing past MAX_DEPTH so that input cannot exhaust the stack
+    fn read_form(&mut self) -> Result<Value> {
+        if MAX_DEPTH <= self.depth {
+            return self.error("nesting too deep")
+        }
+        self.depth += 1;
+        let form = self.read_form_at();
+        self.depth -= 1;
+        form
+    }
+
+    fn read_form_at(&mut self) -> Result<Value> {
+        self.skip_whitespace();
+        let start = self.pos;
+        match self.next_char() {
+            None => self.error("unexpected end of input"),
+            Some('(') => self.read_coll(')').map(Value::LIST),
+            Some('[') => self.read_coll(']').map(Value::LIST),
+            Some('{') => self.read_map(),
+            Some(')') | Some(']') | Some('}') => {
+                self.pos = start;
+                self.error("unmatched delimiter")
+            }
+            Some('"') => self.read_string().map(Value::STRING),
+            Some('\\') => self.read_char(),
+            Some(':') => {
+                let token = self.read_token();
+                match named(token) {
+                    Some((ns, name)) if !name.is_empty() => Ok(Value::KEY(KEY::new(ns, name))),
+                    _ => self.error("invalid keyword")
+                }
+            }
+            Some('#') => self.read_dispatch(),
+            Some(_) => {
+                self.pos = start;
+                let token = self.read_token();
+                self.read_atom(token, start)
+            }
+        }
+    }
+
+    fn read_coll(&mut self, close: char) -> Result<Vec<Value>> {
+        let mut items = Vec::new();
+        loop {
+            self.skip_whitespace();
+            match self.peek() {
+                None => return self.error("unclosed collection"),
+                Some(c) if c == close => {
+                    self.pos += 1;
+                    return Ok(items)
+                }
+                Some(_) => {
+                    if let Some(item) = self.read_item()? {
+                        items.push(item);
+                    }
+                }
+            }
+        }
+    }
+
+    /// a form inside a collection, None when it was #_ discarded
+    fn read_item(&mut self) -> Result<Option<Value>> {
+        if self.input[self.pos..].starts_with("#_") {
+            self.pos += 2;
+            self.read_form()?;
+            Ok(None)
+        } else {
+            self.read_form().map(Some)
+        }
+    }
+
+    fn read_map(&mut self) -> Result<Value> {
+        let start = self.pos;
+        let items = self.read_coll('}')?;
+        if items.len() % 2 != 0 {
+            self.pos = start;
+            return self.error("map literal needs an even number of forms")
+        }
+        let mut map = BTreeMap::new();
+        let mut items = items.into_iter();
+        while let (Some(k), Some(v)) = (items.next(), items.next()) {
+            if map.insert(k, v).is_some() {
+                self.pos = start;
+                return self.error("duplicate map key")
+            }
+        }
+        Ok(Value::MAP(map))
+    }
+
+    fn read_set(&mut self) -> Result<Value> {
+        let start = self.pos;
+        let items = self.read_coll('}')?;
+        let len = items.len();
+        let set: BTreeSet<Value> = items.into_iter().collect();
+        if set.len() != len {
+            self.pos = start;
+            return self.error("duplicate set element")
+        }
+        Ok(Value::SET(SET::from(set)))
+    }
+
+    fn read_hex4(&mut self) -> Result<u32> {
+        let end = self.pos + 4;
+        let code = self.input.get(self.pos..end)
+            .and_then(|hex| u32::from_str_radix(hex, 16).ok());
+        match code {
+            Some(code) => {
+                self.pos = end;
+                Ok(code)
+            }
+            None => self.error("invalid unicode escape")
+        }
+    }
+
+    fn read_string(&mut self) -> Result<String> {
+        let mut s = String::new();
+        loop {
+            match self.next_char() {
+                None => return self.error("unterminated string"),
+                Some('"') => return Ok(s),
+                Some('\\') => {
+                    let c = match self.next_char() {
+                        Some('"') => '"',
+                        Some('\\') => '\\',
+                        Some('n') => '\n',
+                        Some('t') => '\t',
+                        Some('r') => '\r',
+                        Some('b') => '\u{8}',
+                        Some('f') => '\u{c}',
+                        Some('u') => {
+                            match char::from_u32(self.read_hex4()?) {
+                                Some(c) => c,
+                                None => return self.error("invalid unicode escape")
+                            }
+                        }
+                        _ => return self.error("invalid string escape")
+                    };
+                    s.push(c);
+                }
+                Some(c) => s.push(c),
+            }
+        }
+    }
+
+    fn read_char(&mut self) -> Result<Value> {
+        // the first char is taken as is, so that \( and \space both work
+        let start = self.pos;
+        if self.next_char().is_none() {
+            return self.error("unexpected end of input")
+        }
+        self.read_token();
+        let token = &self.input[start..self.pos];
+        let c = match token {
+            "newline" => '\n',
+            "space" => ' ',
+            "tab" => '\t',
+            "return" => '\r',
+            "backspace" => '\u{8}',
+            "formfeed" => '\u{c}',
+            _ if token.chars().count() == 1 => token.chars().next().unwrap_or(' '),
+            _ if token.starts_with('u') && token.len() == 5 => {
+                match u32::from_str_radix(&token[1..], 16).ok().and_then(char::from_u32) {
+                    Some(c) => c,
+                    None => return self.error("invalid unicode character")
+                }
+            }
+            _ => {
+                self.pos = start;
+                return self.error("invalid character")
+            }
+        };
+        Ok(Value::STRING(c.to_string()))
+    }
+
+    fn read_atom(&mut self, token: &'a str, start: usize) -> Result<Value> {
+        match token {
+            "nil" => return Ok(Value::NULL),
+            "true" => return Ok(Value::BOOL(true)),
+            "false" => return Ok(Value::BOOL(false)),
+            _ => {}
+        }
+        let mut chars = token.chars();
+        let first = chars.next();
+        let second = chars.next();
+        let numeric = match (first, second) {
+            (Some(c), _) if c.is_ascii_digit() => true,
+            (Some('+'), Some(c)) | (Some('-'), Some(c)) => c.is_ascii_digit(),
+            _ => false
+        };
+        if numeric {
+            self.pos = start;
+            return self.read_number(token)
+        }
+        match named(token) {
+            Some((ns, name)) if !name.is_empty() => Ok(Value::SYM(SYM::new(ns, name))),
+            _ => {
+                self.pos = start;
+                self.error("invalid symbol")
+            }
+        }
+    }
+
+    fn read_number(&mut self, token: &'a str) -> Result<Value> {
+        let value = if let Some(int) = token.strip_suffix('N') {
+            int.parse::<i64>().ok().map(Value::INT)
+        } else if let Some(dec) = token.strip_suffix('M') {
+            dec.parse::<f64>().ok()
+                .filter(|d| decimal_digits(dec).is_some() && decimal_digits(dec) == decimal_digits(&format!("{:e}", d)))
+                .map(|d| Value::DOUBLE(OrderedFloat::from(d)))
+        } else if token.contains(['.', 'e', 'E'].as_ref()) {
+            token.parse::<f64>().ok().map(|d| Value::DOUBLE(OrderedFloat::from(d)))
+        } else {
+            token.parse::<i64>().ok().map(Value::INT)
+        };
+        match value {
+            Some(value) => {
+                self.pos += token.len();
+                Ok(value)
+            }
+            None => self.error("invalid number")
+        }
+    }
+
+    fn read_dispatch(&mut self) -> Result<Value> {
+        match self.peek() {
+            Some('{') => {
+                self.pos += 1;
+                self.read_set()
+            }
+            Some('_') => {
+                self.pos += 1;
+                self.read_form()?;
+                self.read_form()
+            }
+            Some('"') => {
+                self.pos += 1;
+                let re = self.read_string()?;
+                REGEX::from_str(&re).map(Value::REGEX).or_else(|_| self.error("invalid regex"))
+            }
+            Some('#') => {
+                self.pos += 1;
+                match self.read_token() {
+                    "NaN" => Ok(Value::DOUBLE(OrderedFloat::from(f64::NAN))),
+                    "Inf" => Ok(Value::DOUBLE(OrderedFloat::from(f64::INFINITY))),
+                    "-Inf" => Ok(Value::DOUBLE(OrderedFloat::from(f64::NEG_INFINITY))),
+                    _ => self.error("unknown symbolic value")
+                }
+            }
+            _ => {
+                let start = self.pos;
+                let tag = self.read_token();
+                let form = self.read_form()?;
+                read_tagged(tag, form).map_err(|msg| Error::syntax(ErrorCode::InvalidEdn(msg), start))
+            }
+        }
+    }
+}
+
+/// the value of a tagged literal, or what is wrong with it
+fn read_tagged(tag: &str, form: Value) -> ::core::result::Result<Value, &'static str> {
+    let value = match (tag, form) {
+        ("inst", Value::STRING(s)) => {
+            match parse_inst(&s) {
+                Some(ms) => Value::INST(INST::from_millis(ms)),
+                None => return Err("invalid #inst")
+            }
+        }
+        ("uuid", Value::STRING(s)) => {
+            match parse_uuid(&s).and_then(|bytes| UUID::from_bytes(&bytes).ok()) {
+                Some(uuid) => Value::UUID(uuid),
+                None => return Err("invalid #uuid")
+            }
+        }
+        ("regex", Value::STRING(s)) => {
+            match REGEX::from_str(&s) {
+                Ok(re) => Value::REGEX(re),
+                Err(_) => return Err("invalid #regex")
+            }
+        }
+        ("uri", Value::STRING(s)) => {
+            match URI::from_str(&s) {
+                Ok(uri) => Value::URI(uri),
+                Err(_) => return Err("invalid #uri")
+            }
+        }
+        ("bytes", Value::LIST(v)) => {
+            let bytes: Option<Vec<u8>> = v.into_iter()
+                .map(|b| b.as_i64().and_then(|b| u8::try_from(b).ok()))
+                .collect();
+            match bytes {
+                Some(bytes) => Value::BYTES(ByteBuf::from(bytes)),
+                None => return Err("invalid #bytes")
+            }
+        }
+        ("ints", Value::LIST(v)) => {
+            let ints: Option<Vec<i32>> = v.into_iter()
+                .map(|i| i.as_i64().and_then(|i| i32::try_from(i).ok()))
+                .collect();
+            match ints {
+                Some(ints) => Value::IntArray(IntArray::from_vec(ints)),
+                None => return Err("invalid #ints")
+            }
+        }
+        ("longs", Value::LIST(v)) => {
+            let longs: Option<Vec<i64>> = v.iter().map(Value::as_i64).collect();
+            match longs {
+                Some(longs) => Value::LongArray(LongArray::from_vec(longs)),
+                None => return Err("invalid #longs")
+            }
+        }
+        ("floats", Value::LIST(v)) => {
+            let floats: Option<Vec<f32>> = v.iter().map(|f| f.as_f64().map(|f| f as f32)).collect();
+            match floats {
+                Some(floats) => Value::FloatArray(FloatArray::from_vec(floats)),
+                None => return Err("invalid #floats")
+            }
+        }
+        ("doubles", Value::LIST(v)) => {
+            let doubles: Option<Vec<f64>> = v.iter().map(Value::as_f64).collect();
+            match doubles {
+                Some(doubles) => Value::DoubleArray(DoubleArray::from_vec(doubles)),
+                None => return Err("invalid #doubles")
+            }
+        }
+        ("booleans", Value::LIST(v)) => {
+            let bools: Option<Vec<bool>> = v.iter().map(Value::as_bool).collect();
+            match bools {
+                Some(bools) => Value::BooleanArray(BooleanArray::from_vec(bools)),
+                None => return Err("invalid #booleans")
+            }
+        }
+        _ => return Err("unknown tag")
+    };
+    Ok(value)
+}
+
+/// The sign, significant digits and exponent of a decimal like `-1.50e3`,
+/// with the zeros that do not change its value taken off: `(true, "15", 2)`.
+/// None when it is not a plain decimal
+fn decimal_digits(s: &str) -> Option<(bool, String, i64)> {
+    let (negative, s) = match s.strip_prefix('-') {
+        Some(s) => (true, s),
+        None => (false, s.strip_prefix('+').unwrap_or(s))
+    };
+    let (mantissa, exponent) = match s.find(['e', 'E'].as_ref()) {
+        Some(i) => (&s[..i], s[i + 1..].parse::<i64>().ok()?),
+        None => (s, 0)
+    };
+    let (int, frac) = mantissa.split_once('.').unwrap_or((mantissa, ""));
+    if int.is_empty() && frac.is_empty() || !int.chars().chain(frac.chars()).all(|c| c.is_ascii_digit()) {
+        return None
+    }
+    let digits = format!("{}{}", int, frac);
+    let digits = digits.trim_start_matches('0');
+    let trimmed = digits.trim_end_matches('0');
+    if trimmed.is_empty() {
+        return Some((false, String::new(), 0))
+    }
+    let exponent = exponent - frac.len() as i64 + (digits.len() - trimmed.len()) as i64;
+    Some((negative, trimmed.to_string(), exponent))
+}
+
+/// split `ns/name` the way clojure does, a lone `/` is a name
+fn named(token: &str) -> Option<(Option<String>, String)> {
+    if token.is_empty() || token.starts_with(':') {
+        return None
+    }
+    match token.find('/') {
+        Some(i) if 0 < i && i < token.len() - 1 => {
+            Some((Some(token[..i].to_string()), token[i + 1..].to_string()))
+        }
+        Some(_) if token != "/" => None,
+        _ => Some((None, token.to_string()))
+    }
+}
+
+/// (year, month, day) to days since 1970-01-01, the inverse of civil_from_days
+fn days_from_civil(year: i64, month: i64, day: i64) -> i64 {
+    let y = if month <= 2 { year - 1 } else { year };
+    let era = y.div_euclid(400);
+    let yoe = y - era * 400;
+    let mp = if month > 2 { month - 3 } else { month + 9 };
+    let doy = (153 * mp + 2) / 5 + day - 1;
+    let doe = yoe * 365 + yoe / 4 - yoe / 100 + doy;
+    era * 146_097 + doe - 719_468
+}
+
+fn days_in_month(year: i64, month: i64) -> i64 {
+    match month {
+        2 if year % 4 == 0 && (year % 100 != 0 || year % 400 == 0) => 29,
+        2 => 28,
+        4 | 6 | 9 | 11 => 30,
+        _ => 31
+    }
+}
+
+/// RFC3339 timestamps as read by clojure, where everything after the year
+/// is optional: `yyyy-mm-ddThh:mm:ss.fff+hh:mm`
+fn parse_inst(s: &str) -> Option<i64> {
+    let b = s.as_bytes();
+    let mut pos = 0;
+
+    let digits = |n: usize, pos: &mut usize| -> Option<i64> {
+        let field = s.get(*pos..*pos + n)?;
+        if !field.bytes().all(|c| c.is_ascii_digit()) {
+            return None
+        }
+        *pos += n;
+        field.parse().ok()
+    };
+
+    let year = digits(4, &mut pos)?;
+    let mut fields = [1i64, 1, 0, 0, 0];
+    let seps = [b'-', b'-', b'T', b':', b':'];
+    for (field, sep) in fields.iter_mut().zip(seps.iter()) {
+        if b.get(pos) != Some(sep) {
+            break
+        }
+        pos += 1;
+        *field = digits(2, &mut pos)?;
+    }
+    let [month, day, hour, minute, second] = fields;
+
+    let mut millis = 0;
+    if b.get(pos) == Some(&b'.') {
+        pos += 1;
+        let start = pos;
+        while matches!(b.get(pos), Some(c) if c.is_ascii_digit()) {
+            pos += 1;
+        }
+        let frac = s.get(start..pos)?;
+        if frac.is_empty() {
+            return None
+        }
+        // only millisecond precision is kept
+        millis = frac.bytes().chain(core::iter::repeat(b'0')).take(3)
+            .fold(0, |ms, c| ms * 10 + i64::from(c - b'0'));
+    }
+
+    let offset = match b.get(pos) {
+        None => 0,
+        Some(b'Z') | Some(b'z') if pos + 1 == b.len() => 0,
+        Some(sign) if *sign == b'+' || *sign == b'-' => {
+            pos += 1;
+            let h = digits(2, &mut pos)?;
+            if b.get(pos) != Some(&b':') {
+                return None
+            }
+            pos += 1;
+            let m = digits(2, &mut pos)?;
+            if pos != b.len() {
+                return None
+            }
+            let minutes = h * 60 + m;
+            if *sign == b'-' { -minutes } else { minutes }
+        }
+        _ => return None
+    };
+
+    if !(1..=12).contains(&month) || !(1..=days_in_month(year, month)).contains(&day)
+        || 23 < hour || 59 < minute || 60 < second {
+        return None
+    }
+    let days = days_from_civil(year, month, day);
+    let secs = days * 86_400 + hour * 3600 + minute * 60 + second - offset * 60;
+    Some(secs * 1000 + millis)
+}
+
+/// 32 hex digits, dashes anywhere
+fn parse_uuid(s: &str) -> Option<Vec<u8>> {
+    let hex: Vec<u8> = s.bytes().filter(|c| *c != b'-').collect();
+    if hex.len() != 32 {
+        return None
+    }
+    hex.chunks(2)
+        .map(|pair| {
+            let pair = core::str::from_utf8(pair).ok()?;
+            u8::from_str_radix(pair, 16).ok()
+        })
+        .collect()
+}
@@ -0,0 +1,147 @@
+use core::convert::TryFrom;
+use core::ops::Index;
+
+use crate::value::Value;
+use crate::key::{KEY};
+use crate::lib::*;
+
+/// Something that can look up a value inside a MAP, LIST or SET.
+///
+///   + usize indexes into a LIST
+///   + strs look up a MAP entry keyed by that string, and failing that by the
+///     keyword it spells, so `"user/name"` and `":user/name"` both find `:user/name`
+///   + a KEY looks up a MAP entry keyed by that keyword
+///   + a Value looks up a MAP entry, an INT indexes a LIST, and a SET returns
+///     its own element when it has one
+pub trait ValueIndex {
+    fn index_into<'v>(&self, v: &'v Value) -> Option<&'v Value>;
+}
+
+impl ValueIndex for usize {
+    fn index_into<'v>(&self, v: &'v Value) -> Option<&'v Value> {
+        match v {
+            Value::LIST(list) => list.get(*self),
+            _ => None
+        }
+    }
+}
+
+/// the keyword a str spells, `ns/name` or `name` with an optional leading colon
+pub(crate) fn keyword(s: &str) -> KEY {
+    let s = s.strip_prefix(':').unwrap_or(s);
+    match s.find('/') {
+        Some(i) if 0 < i && i < s.len() - 1 => {
+            KEY::namespaced(s[..i].to_string(), s[i + 1..].to_string())
+        }
+        _ => KEY::simple(s.to_string())
+    }
+}
+
+impl ValueIndex for str {
+    fn index_into<'v>(&self, v: &'v Value) -> Option<&'v Value> {
+        match v {
+            Value::MAP(map) => {
+                map.get(&Value::STRING(self.to_string()))
+                    .or_else(|| map.get(&Value::KEY(keyword(self))))
+            }
+            _ => None
+        }
+    }
+}
+
+impl ValueIndex for String {
+    fn index_into<'v>(&self, v: &'v Value) -> Option<&'v Value> {
+        self.as_str().index_into(v)
+    }
+}
+
+impl ValueIndex for KEY {
+    fn index_into<'v>(&self, v: &'v Value) -> Option<&'v Value> {
+        match v {
+            Value::MAP(map) => map.get(&Value::KEY(self.clone())),
+            _ => None
+        }
+    }
+}
+
+impl ValueIndex for Value {
+    fn index_into<'v>(&self, v: &'v Value) -> Option<&'v Value> {
+        match (v, self) {
+            (Value::MAP(map), _) => map.get(self),
+            (Value::LIST(list), Value::INT(i)) => {
+                usize::try_from(*i).ok().and_then(|i| list.get(i))
+            }
+            (Value::SET(set), _) => set.get(self),
+            _ => None
+        }
+    }
+}
+
+impl<T> ValueIndex for &T
+where
+    T: ?Sized + ValueIndex,
+{
+    fn index_into<'v>(&self, v: &'v Value) -> Option<&'v Value> {
+        (**self).index_into(v)
+    }
+}
+
+/// One step of a path for `Value::get_in`
+#[derive(Clone, PartialEq, Debug)]
+pub enum PathSegment<'a> {
+    Key(&'a str),
+    Index(usize),
+    Value(&'a Value),
+}
+
+impl<'a> ValueIndex for PathSegment<'a> {
+    fn index_into<'v>(&self, v: &'v Value) -> Option<&'v Value> {
+        match *self {
+            PathSegment::Key(k) => k.index_into(v),
+            PathSegment::Index(i) => i.index_into(v),
+            PathSegment::Value(val) => val.index_into(v),
+        }
+    }
+}
+
+impl<'a> From<&'a str> for PathSegment<'a> {
+    fn from(k: &'a str) -> Self {
+        PathSegment::Key(k)
+    }
+}
+
+impl<'a> From<usize> for PathSegment<'a> {
+    fn from(i: usize) -> Self {
+        PathSegment::Index(i)
+    }
+}
+
+impl<'a> From<&'a Value> for PathSegment<'a> {
+    fn from(v: &'a Value) -> Self {
+        PathSegment::Value(v)
+    }
+}
+
+static NULL: Value = Value::NULL;
+
+impl Value {
+    /// look up `index` in a MAP, LIST or SET. None when it is missing or
+    /// this is not a collection
+    pub fn get<I: ValueIndex>(&self, index: I) -> Option<&Value> {
+        index.index_into(self)
+    }
+
+    /// follow `path` through nested collections, like clojure's get-in
+    pub fn get_in(&self, path: &[PathSegment]) -> Option<&Value> {
+        path.iter().try_fold(self, |v, segment| segment.index_into(v))
+    }
+}
+
+/// Same as `get`, but missing entries are `Value::NULL` rather than None
+impl<I: ValueIndex> Index<I> for Value {
+    type Output = Value;
+
+    fn index(&self, index: I) -> &Value {
+        index.index_into(self).unwrap_or(&NULL)
+    }
+}
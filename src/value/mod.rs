@@ -1,7 +1,4 @@
-use std::collections::{BTreeSet, BTreeMap};
 use serde::ser::Serialize;
-use serde_bytes::ByteBuf;
-use ordered_float::OrderedFloat;
 
 use crate::inst::{INST};
 use crate::uuid::{UUID};
@@ -11,8 +8,40 @@ use crate::sym::{SYM};
 use crate::key::{KEY};
 use crate::typed_arrays::*;
 use crate::set::{SET};
+use crate::lib::*;
+
+pub use crate::imp::compat::{ByteBuf, OrderedFloat};
 
 mod de;
+mod ser;
+mod index;
+mod convert;
+mod display;
+mod edn;
+mod borrowed;
+
+pub use self::borrowed::ValueRef;
+pub use self::index::{ValueIndex, PathSegment};
+pub(crate) use self::index::keyword;
+pub use self::edn::from_edn;
+pub use self::ser::Serializer;
+
+/// interpret a Value as an instance of type T, the same way T would have been
+/// read from the Value's fressian bytes
+pub fn from_value<T>(value: Value) -> crate::error::Result<T>
+where
+    T: serde::de::DeserializeOwned,
+{
+    T::deserialize(value)
+}
+
+/// build the Value that `value` would be read back as from its fressian bytes
+pub fn to_value<T>(value: &T) -> crate::error::Result<Value>
+where
+    T: ?Sized + Serialize,
+{
+    value.serialize(Serializer)
+}
 
 /// Represents a Fressian value
 #[derive(Clone, PartialEq, PartialOrd, Ord, Eq, Hash, Debug)]
@@ -25,7 +54,7 @@ pub enum Value {
     // CHAR(char)
     STRING(String),
     // UTF8(&'a str),
-    BYTES(ByteBuf), // ideally should be &'a [u8]
+    BYTES(ByteBuf), // ValueRef borrows these
     LIST(Vec<Value>),
     MAP(BTreeMap<Value, Value>),
     SET(SET<Value>),
@@ -113,6 +142,12 @@ impl<K,V> From<BTreeMap<K,V>> for Value
     }
 }
 
+impl<'a> From<&'a str> for Value {
+    fn from(val: &'a str) -> Value {
+        Value::STRING(val.to_string())
+    }
+}
+
 impl<'a> From<&'a[u8]> for Value {
     fn from(val: &'a[u8]) -> Value {
         Value::BYTES(ByteBuf::from(val))
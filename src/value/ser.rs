@@ -0,0 +1,418 @@
+use core::convert::TryFrom;
+
+use serde::ser::{self, Serialize};
+
+use crate::error::{Error, ErrorCode, Result};
+use crate::imp::compat::{ByteBuf, OrderedFloat};
+use crate::value::Value;
+use crate::inst::{INST};
+use crate::uuid::{UUID};
+use crate::uri::{URI};
+use crate::regex::{REGEX};
+use crate::sym::{SYM};
+use crate::key::{KEY};
+use crate::typed_arrays::*;
+use crate::set::{SET};
+use crate::lib::*;
+
+/// Builds a Value instead of writing bytes. The newtype and tuple struct
+/// names understood by ser::Serializer produce the matching Value variants,
+/// so `to_value(&x)` equals the Value read back from `x`'s bytes. Tuple and
+/// struct variants become `[variant [fields..]]` and `[variant {fields..}]`
+pub struct Serializer;
+
+fn unsupported<T>(code: ErrorCode) -> Result<T> {
+    Err(Error::syntax(code, 0))
+}
+
+fn into_list(value: Value) -> Result<Vec<Value>> {
+    match value {
+        Value::LIST(v) => Ok(v),
+        _ => unsupported(ErrorCode::UnsupportedTAType)
+    }
+}
+
+fn into_string(value: Value) -> Result<String> {
+    match value {
+        Value::STRING(s) => Ok(s),
+        _ => unsupported(ErrorCode::UnsupportedType)
+    }
+}
+
+/// collect the elements of a typed array, failing on anything else
+fn typed<T, F>(value: Value, f: F) -> Result<Vec<T>>
+where
+    F: Fn(Value) -> Option<T>,
+{
+    into_list(value)?
+        .into_iter()
+        .map(|v| f(v).ok_or_else(|| Error::syntax(ErrorCode::UnsupportedTAType, 0)))
+        .collect()
+}
+
+impl ser::Serializer for Serializer {
+    type Ok = Value;
+    type Error = Error;
+
+    type SerializeSeq = SerializeVec;
+    type SerializeTuple = SerializeVec;
+    type SerializeTupleStruct = SerializeTupleStruct;
+    type SerializeTupleVariant = SerializeTupleVariant;
+    type SerializeMap = SerializeMap;
+    type SerializeStruct = SerializeMap;
+    type SerializeStructVariant = SerializeStructVariant;
+
+    fn serialize_bool(self, v: bool) -> Result<Value> { Ok(Value::BOOL(v)) }
+
+    fn serialize_i8(self, v: i8) -> Result<Value> { Ok(Value::INT(i64::from(v))) }
+
+    fn serialize_i16(self, v: i16) -> Result<Value> { Ok(Value::INT(i64::from(v))) }
+
+    fn serialize_i32(self, v: i32) -> Result<Value> { Ok(Value::INT(i64::from(v))) }
+
+    fn serialize_i64(self, v: i64) -> Result<Value> { Ok(Value::INT(v)) }
+
+    fn serialize_u8(self, v: u8) -> Result<Value> { Ok(Value::INT(i64::from(v))) }
+
+    fn serialize_u16(self, v: u16) -> Result<Value> { Ok(Value::INT(i64::from(v))) }
+
+    fn serialize_u32(self, v: u32) -> Result<Value> { Ok(Value::INT(i64::from(v))) }
+
+    fn serialize_u64(self, v: u64) -> Result<Value> {
+        match i64::try_from(v) {
+            Ok(i) => Ok(Value::INT(i)),
+            Err(_) => unsupported(ErrorCode::IntTooLargeFori64)
+        }
+    }
+
+    fn serialize_f32(self, v: f32) -> Result<Value> { Ok(Value::FLOAT(OrderedFloat::from(v))) }
+
+    fn serialize_f64(self, v: f64) -> Result<Value> { Ok(Value::DOUBLE(OrderedFloat::from(v))) }
+
+    fn serialize_char(self, v: char) -> Result<Value> { Ok(Value::STRING(v.to_string())) }
+
+    fn serialize_str(self, v: &str) -> Result<Value> { Ok(Value::STRING(v.to_string())) }
+
+    fn serialize_bytes(self, v: &[u8]) -> Result<Value> { Ok(Value::BYTES(ByteBuf::from(v))) }
+
+    fn serialize_none(self) -> Result<Value> { Ok(Value::NULL) }
+
+    fn serialize_some<T>(self, value: &T) -> Result<Value>
+    where
+        T: ?Sized + Serialize,
+    {
+        value.serialize(self)
+    }
+
+    fn serialize_unit(self) -> Result<Value> { Ok(Value::NULL) }
+
+    fn serialize_unit_struct(self, _name: &'static str) -> Result<Value> { Ok(Value::NULL) }
+
+    fn serialize_unit_variant(self, _name: &'static str, _variant_index: u32, variant: &'static str) -> Result<Value> {
+        Ok(Value::STRING(variant.to_string()))
+    }
+
+    fn serialize_newtype_struct<T>(self, name: &'static str, value: &T) -> Result<Value>
+    where
+        T: ?Sized + Serialize,
+    {
+        let inner = value.serialize(Serializer)?;
+        match name {
+            "SET" => {
+                let set: BTreeSet<Value> = into_list(inner)?.into_iter().collect();
+                Ok(Value::SET(SET::from(set)))
+            }
+            "INST" => {
+                match inner {
+                    Value::INT(ms) => Ok(Value::INST(INST::from_millis(ms))),
+                    _ => unsupported(ErrorCode::UnsupportedType)
+                }
+            }
+            "UUID" => {
+                match inner {
+                    Value::BYTES(bytes) => {
+                        UUID::from_bytes(&bytes)
+                            .map(Value::UUID)
+                            .or_else(|_| unsupported(ErrorCode::UnsupportedType))
+                    }
+                    _ => unsupported(ErrorCode::UnsupportedType)
+                }
+            }
+            "URI" => {
+                URI::from_str(&into_string(inner)?)
+                    .map(Value::URI)
+                    .or_else(|_| unsupported(ErrorCode::UnsupportedType))
+            }
+            "REGEX" => {
+                REGEX::from_str(&into_string(inner)?)
+                    .map(Value::REGEX)
+                    .or_else(|_| unsupported(ErrorCode::UnsupportedType))
+            }
+            "INT_ARRAY" => {
+                let v = typed(inner, |v| match v {
+                    Value::INT(i) => i32::try_from(i).ok(),
+                    _ => None
+                })?;
+                Ok(Value::IntArray(IntArray::from_vec(v)))
+            }
+            "LONG_ARRAY" => {
+                let v = typed(inner, |v| match v {
+                    Value::INT(i) => Some(i),
+                    _ => None
+                })?;
+                Ok(Value::LongArray(LongArray::from_vec(v)))
+            }
+            "FLOAT_ARRAY" => {
+                let v = typed(inner, |v| match v {
+                    Value::FLOAT(f) => Some(f.into_inner()),
+                    _ => None
+                })?;
+                Ok(Value::FloatArray(FloatArray::from_vec(v)))
+            }
+            "DOUBLE_ARRAY" => {
+                let v = typed(inner, |v| match v {
+                    Value::DOUBLE(d) => Some(d.into_inner()),
+                    _ => None
+                })?;
+                Ok(Value::DoubleArray(DoubleArray::from_vec(v)))
+            }
+            "BOOLEAN_ARRAY" => {
+                let v = typed(inner, |v| match v {
+                    Value::BOOL(b) => Some(b),
+                    _ => None
+                })?;
+                Ok(Value::BooleanArray(BooleanArray::from_vec(v)))
+            }
+            "OBJECT_ARRAY" => unsupported(ErrorCode::UnsupportedType),
+            _ => Ok(inner)
+        }
+    }
+
+    fn serialize_newtype_variant<T>(
+        self,
+        name: &'static str,
+        _variant_index: u32,
+        variant: &'static str,
+        value: &T,
+    ) -> Result<Value>
+    where
+        T: ?Sized + Serialize,
+    {
+        match (name, variant) {
+            ("Result", "Ok") => value.serialize(self),
+            // there is no Value for fressian's ERROR code
+            ("Result", _) => unsupported(ErrorCode::UnsupportedType),
+            _ => {
+                let value = value.serialize(Serializer)?;
+                Ok(Value::LIST(vec![Value::STRING(variant.to_string()), value]))
+            }
+        }
+    }
+
+    fn serialize_seq(self, len: Option<usize>) -> Result<SerializeVec> {
+        Ok(SerializeVec{ vec: Vec::with_capacity(len.unwrap_or(0)) })
+    }
+
+    fn serialize_tuple(self, len: usize) -> Result<SerializeVec> {
+        self.serialize_seq(Some(len))
+    }
+
+    fn serialize_tuple_struct(self, name: &'static str, len: usize) -> Result<SerializeTupleStruct> {
+        Ok(SerializeTupleStruct{ name, vec: Vec::with_capacity(len) })
+    }
+
+    fn serialize_tuple_variant(
+        self,
+        _name: &'static str,
+        _variant_index: u32,
+        variant: &'static str,
+        len: usize,
+    ) -> Result<SerializeTupleVariant> {
+        Ok(SerializeTupleVariant{ variant, vec: Vec::with_capacity(len) })
+    }
+
+    fn serialize_map(self, _len: Option<usize>) -> Result<SerializeMap> {
+        Ok(SerializeMap{ map: BTreeMap::new(), next_key: None })
+    }
+
+    fn serialize_struct(self, _name: &'static str, len: usize) -> Result<SerializeMap> {
+        self.serialize_map(Some(len))
+    }
+
+    fn serialize_struct_variant(
+        self,
+        _name: &'static str,
+        _variant_index: u32,
+        variant: &'static str,
+        _len: usize,
+    ) -> Result<SerializeStructVariant> {
+        Ok(SerializeStructVariant{ variant, map: BTreeMap::new() })
+    }
+}
+
+pub struct SerializeVec {
+    vec: Vec<Value>,
+}
+
+pub struct SerializeTupleStruct {
+    name: &'static str,
+    vec: Vec<Value>,
+}
+
+pub struct SerializeTupleVariant {
+    variant: &'static str,
+    vec: Vec<Value>,
+}
+
+pub struct SerializeMap {
+    map: BTreeMap<Value, Value>,
+    next_key: Option<Value>,
+}
+
+pub struct SerializeStructVariant {
+    variant: &'static str,
+    map: BTreeMap<Value, Value>,
+}
+
+impl ser::SerializeSeq for SerializeVec {
+    type Ok = Value;
+    type Error = Error;
+
+    fn serialize_element<T>(&mut self, value: &T) -> Result<()>
+    where
+        T: ?Sized + Serialize,
+    {
+        self.vec.push(value.serialize(Serializer)?);
+        Ok(())
+    }
+
+    fn end(self) -> Result<Value> { Ok(Value::LIST(self.vec)) }
+}
+
+impl ser::SerializeTuple for SerializeVec {
+    type Ok = Value;
+    type Error = Error;
+
+    fn serialize_element<T>(&mut self, value: &T) -> Result<()>
+    where
+        T: ?Sized + Serialize,
+    {
+        ser::SerializeSeq::serialize_element(self, value)
+    }
+
+    fn end(self) -> Result<Value> { ser::SerializeSeq::end(self) }
+}
+
+/// the [namespace name] pair of a KEY or SYM
+fn named(mut vec: Vec<Value>) -> Result<(Option<String>, String)> {
+    if vec.len() != 2 {
+        return unsupported(ErrorCode::UnsupportedType)
+    }
+    let name = into_string(vec.pop().unwrap_or(Value::NULL))?;
+    match vec.pop() {
+        Some(Value::NULL) => Ok((None, name)),
+        Some(namespace) => Ok((Some(into_string(namespace)?), name)),
+        None => unsupported(ErrorCode::UnsupportedType)
+    }
+}
+
+impl ser::SerializeTupleStruct for SerializeTupleStruct {
+    type Ok = Value;
+    type Error = Error;
+
+    fn serialize_field<T>(&mut self, value: &T) -> Result<()>
+    where
+        T: ?Sized + Serialize,
+    {
+        self.vec.push(value.serialize(Serializer)?);
+        Ok(())
+    }
+
+    fn end(self) -> Result<Value> {
+        match self.name {
+            "SYM" => {
+                let (namespace, name) = named(self.vec)?;
+                Ok(Value::SYM(SYM::new(namespace, name)))
+            }
+            "KEY" => {
+                let (namespace, name) = named(self.vec)?;
+                Ok(Value::KEY(KEY::new(namespace, name)))
+            }
+            _ => Ok(Value::LIST(self.vec))
+        }
+    }
+}
+
+impl ser::SerializeTupleVariant for SerializeTupleVariant {
+    type Ok = Value;
+    type Error = Error;
+
+    fn serialize_field<T>(&mut self, value: &T) -> Result<()>
+    where
+        T: ?Sized + Serialize,
+    {
+        self.vec.push(value.serialize(Serializer)?);
+        Ok(())
+    }
+
+    fn end(self) -> Result<Value> {
+        Ok(Value::LIST(vec![Value::STRING(self.variant.to_string()), Value::LIST(self.vec)]))
+    }
+}
+
+impl ser::SerializeMap for SerializeMap {
+    type Ok = Value;
+    type Error = Error;
+
+    fn serialize_key<T>(&mut self, key: &T) -> Result<()>
+    where
+        T: ?Sized + Serialize,
+    {
+        self.next_key = Some(key.serialize(Serializer)?);
+        Ok(())
+    }
+
+    fn serialize_value<T>(&mut self, value: &T) -> Result<()>
+    where
+        T: ?Sized + Serialize,
+    {
+        let key = self.next_key.take()
+            .ok_or_else(|| Error::msg("serialize_value called before serialize_key".to_string()))?;
+        self.map.insert(key, value.serialize(Serializer)?);
+        Ok(())
+    }
+
+    fn end(self) -> Result<Value> { Ok(Value::MAP(self.map)) }
+}
+
+impl ser::SerializeStruct for SerializeMap {
+    type Ok = Value;
+    type Error = Error;
+
+    fn serialize_field<T>(&mut self, key: &'static str, value: &T) -> Result<()>
+    where
+        T: ?Sized + Serialize,
+    {
+        self.map.insert(Value::STRING(key.to_string()), value.serialize(Serializer)?);
+        Ok(())
+    }
+
+    fn end(self) -> Result<Value> { ser::SerializeMap::end(self) }
+}
+
+impl ser::SerializeStructVariant for SerializeStructVariant {
+    type Ok = Value;
+    type Error = Error;
+
+    fn serialize_field<T>(&mut self, key: &'static str, value: &T) -> Result<()>
+    where
+        T: ?Sized + Serialize,
+    {
+        self.map.insert(Value::STRING(key.to_string()), value.serialize(Serializer)?);
+        Ok(())
+    }
+
+    fn end(self) -> Result<Value> {
+        Ok(Value::LIST(vec![Value::STRING(self.variant.to_string()), Value::MAP(self.map)]))
+    }
+}
@@ -0,0 +1,306 @@
+#![allow(dead_code)]
+#![allow(unused_imports)]
+#![allow(non_snake_case)]
+
+#[macro_use]
+extern crate serde_derive;
+extern crate serde;
+extern crate serde_fressian;
+
+use serde_fressian::key::{KEY};
+use serde_fressian::value::{self, PathSegment, Value};
+use serde_fressian::cache::{self, train, CachePolicy, CacheTable};
+use serde_fressian::de::{self, Deserializer};
+use serde_fressian::ser::{self, Serializer};
+use serde_fressian::session::{SessionReader, SessionWriter};
+
+use serde::de::Deserialize;
+use serde::ser::Serialize;
+use std::collections::BTreeMap;
+
+fn user(i: i64) -> Vec<KEY> {
+    vec![KEY::namespaced("user".to_string(), "name".to_string()),
+         KEY::namespaced("user".to_string(), "email".to_string()),
+         KEY::simple(format!("id{}", i))]
+}
+
+#[test]
+fn train_ranks_repeated_strings(){
+    let samples: Vec<Value> = (0..10).map(|i| Value::from(user(i))).collect();
+    let table: CacheTable = train(&samples, 32);
+
+    let entries = table.entries();
+    assert!(entries.contains(&Value::STRING("user".to_string())));
+    assert!(entries.contains(&Value::STRING("email".to_string())));
+    assert!(entries.contains(&Value::STRING("name".to_string())));
+    // "user" occurs twice per sample so it is worth the most
+    assert_eq!(entries[0], Value::STRING("user".to_string()));
+    // one-off ids are never worth caching
+    assert!(!entries.contains(&Value::STRING("id3".to_string())));
+
+    let small = train(&samples, 1);
+    assert_eq!(small.len(), 1);
+    assert!(train(&samples, 0).is_empty());
+}
+
+#[test]
+fn trained_cache_rt(){
+    let samples: Vec<Value> = (0..10).map(|i| Value::from(user(i))).collect();
+    let table: CacheTable = train(&samples, 32);
+
+    let msg = user(42);
+    let mut ser = Serializer::with_cache(table.to_cache());
+    msg.serialize(&mut ser).unwrap();
+    let bytes = ser.to_vec();
+    assert!(bytes.len() < ser::to_vec(&msg).unwrap().len());
+
+    let mut de = Deserializer::with_cache(&bytes, table.to_values());
+    let test_msg: Vec<KEY> = Vec::deserialize(&mut de).unwrap();
+    assert_eq!(test_msg, msg);
+
+    // sessions can be seeded with the same table
+    let mut writer = SessionWriter::with_cache(table.to_cache());
+    let mut reader = SessionReader::with_cache(table.to_values());
+    let bytes = writer.write_message(&msg).unwrap();
+    let test_msg: Vec<KEY> = reader.read_message(&bytes).unwrap();
+    assert_eq!(test_msg, msg);
+}
+
+#[test]
+fn train_skips_collections(){
+    // the same map over and over would outscore every string if it were a candidate
+    let sample = value::from_edn("{:user/name :alice, :user/roles #{:admin :dev}, :user/tags [1 2 3 4 5 6 7 8]}").unwrap();
+    let samples: Vec<Value> = (0..10).map(|_| sample.clone()).collect();
+    let table: CacheTable = train(&samples, 4);
+
+    let entries = table.entries();
+    assert_eq!(entries.len(), 4);
+    assert_eq!(entries[0], Value::STRING("user".to_string()));
+    assert!(entries.iter().all(|entry| entry.is_string()));
+
+    let mut ser = Serializer::with_cache(table.to_cache());
+    sample.serialize(&mut ser).unwrap();
+    let bytes = ser.to_vec();
+    assert!(bytes.len() < ser::to_vec(&sample).unwrap().len());
+    let mut de = Deserializer::with_cache(&bytes, table.to_values());
+    assert_eq!(Value::deserialize(&mut de).unwrap(), sample);
+}
+
+#[test]
+fn cache_table_rt(){
+    let samples: Vec<Value> = (0..10).map(|i| Value::from(user(i))).collect();
+    let table: CacheTable = train(&samples, 32);
+    let bytes = ser::to_vec(&table).unwrap();
+    let test_table: CacheTable = de::from_bytes(&bytes).unwrap();
+    assert_eq!(test_table, table);
+}
+
+#[test]
+fn expand_priority_cache(){
+    let k = KEY::namespaced("user".to_string(), "name".to_string());
+    let v = vec![k.clone(), k.clone()];
+    let bytes = ser::to_vec(&v).unwrap();
+    // (write [:user/name :user/name]), the second keyword is all refs
+    assert_eq!(bytes, vec![230,202,205,222,117,115,101,114,205,222,110,97,109,101,202,128,129]);
+
+    let expanded = cache::expand(&bytes).unwrap();
+    assert_eq!(expanded, vec![230,202,222,117,115,101,114,222,110,97,109,101,202,222,117,115,101,114,222,110,97,109,101]);
+
+    let test_v: Vec<KEY> = de::from_bytes(&expanded).unwrap();
+    assert_eq!(test_v, v);
+    let test_v: Value = de::from_bytes(&expanded).unwrap();
+    assert_eq!(test_v, Value::from(v));
+
+    // cache free bytes are left alone
+    assert_eq!(cache::expand(&expanded).unwrap(), expanded);
+}
+
+#[test]
+fn expand_unpacked_refs_and_resets(){
+    let keys: Vec<KEY> = (0..40).map(|i| KEY::simple(format!("k{}", i))).collect();
+    let mut writer = SessionWriter::new();
+    let mut bytes = writer.write_message(&keys).unwrap();
+    bytes.extend(writer.write_message(&keys[39]).unwrap());
+    writer.reset_caches();
+    bytes.extend(writer.write_message(&keys[0]).unwrap());
+    bytes.extend(writer.write_message(&keys[0]).unwrap());
+
+    let mut expected = ser::to_vec(&keys).unwrap();
+    expected.extend(ser::to_vec(&keys[39]).unwrap());
+    expected.extend(ser::to_vec(&keys[0]).unwrap());
+    expected.extend(ser::to_vec(&keys[0]).unwrap());
+    assert_eq!(cache::expand(&bytes).unwrap(), cache::expand(&expected).unwrap());
+    assert!(!cache::expand(&bytes).unwrap().contains(&205));
+}
+
+#[test]
+fn expand_struct_refs(){
+    // [#pt{1 2} #pt{3 4}], the second struct refers back to the first struct type
+    let bytes: Vec<u8> = vec![230, 239,220,112,116,2, 1,2, 160, 3,4];
+    let expanded = cache::expand(&bytes).unwrap();
+    assert_eq!(expanded, vec![230, 239,220,112,116,2, 1,2, 239,220,112,116,2, 3,4]);
+
+    // cached tags are expanded inside the struct type header
+    let bytes: Vec<u8> = vec![230, 239,205,220,112,116,2, 1,2, 239,128,2, 3,4];
+    assert_eq!(cache::expand(&bytes).unwrap(), expanded);
+}
+
+#[test]
+fn expand_footer(){
+    let k = KEY::simple("foo".to_string());
+    let v = vec![k.clone(), k.clone()];
+    let bytes = ser::to_vec_footer(&v).unwrap();
+    let expanded = cache::expand(&bytes).unwrap();
+
+    let body = cache::expand(&ser::to_vec(&v).unwrap()).unwrap();
+    let length = body.len() as u8;
+    let mut expected = body.clone();
+    expected.extend(vec![207,207,207,207, 0,0,0,length, 0,0,0,0]);
+    assert_eq!(expanded, expected);
+}
+
+#[test]
+fn expand_errors(){
+    // nothing was put in the cache
+    assert!(cache::expand(&[128]).is_err());
+    // no struct types were defined
+    assert!(cache::expand(&[160]).is_err());
+    // a reset forgets what came before
+    assert!(cache::expand(&[230,205,220,97,98,254,128]).is_err());
+    // truncated
+    assert!(cache::expand(&[230,1]).is_err());
+
+    // reading a reference to nothing says where it was
+    let err = de::from_bytes::<Value>(&[229,128]).unwrap_err();
+    assert_eq!((err.err.code.to_string().as_str(), err.err.position), ("UnknownCacheRef", 2));
+    let err = de::from_bytes::<Value>(&[230,205,229,220,97,98,130]).unwrap_err();
+    assert_eq!((err.err.code.to_string().as_str(), err.err.position), ("UnknownCacheRef", 7));
+}
+
+#[derive(Deserialize, Debug)]
+struct Record {
+    id: i64,
+    status: KEY,
+    tags: Vec<String>
+}
+
+fn string(s: &str) -> Value {
+    Value::STRING(s.to_string())
+}
+
+fn records() -> Value {
+    let tags = Value::from(vec![string("alpha"), string("beta"), string("gamma"), string("delta")]);
+    let records: Vec<Value> = (0..10).map(|i| {
+        let mut m: BTreeMap<Value, Value> = BTreeMap::new();
+        m.insert(string("id"), Value::from(i as i64));
+        m.insert(string("status"), Value::KEY(KEY::namespaced("status".to_string(), "active".to_string())));
+        m.insert(string("tags"), tags.clone());
+        Value::from(m)
+    }).collect();
+    Value::from(records)
+}
+
+#[test]
+fn optimize_uncached_bytes(){
+    let value = records();
+    // what a writer that does no caching would produce
+    let plain = cache::expand(&ser::to_vec(&value).unwrap()).unwrap();
+
+    let optimized = cache::optimize(&plain).unwrap();
+    assert_eq!(optimized.original_size, plain.len());
+    assert!(0 < optimized.saved());
+    assert!(optimized.optimized_size() < ser::to_vec(&value).unwrap().len());
+
+    // the default policy only caches strings, so the output reads as is
+    let test_value: Value = de::from_bytes(&optimized.bytes).unwrap();
+    assert_eq!(test_value, value);
+
+    // optimizing again finds nothing new
+    let again = cache::optimize(&optimized.bytes).unwrap();
+    assert_eq!(again.bytes, optimized.bytes);
+}
+
+#[test]
+fn optimize_policy(){
+    let value = records();
+    let plain = cache::expand(&ser::to_vec(&value).unwrap()).unwrap();
+
+    let policy = CachePolicy { min_value_size: Some(16), ..CachePolicy::default() };
+    let optimized = cache::optimize_with(&plain, &policy).unwrap();
+    assert!(0 < optimized.saved());
+    assert!(optimized.optimized_size() < cache::optimize(&plain).unwrap().optimized_size());
+    let test_value: Value = de::from_bytes(&optimized.bytes).unwrap();
+    assert_eq!(test_value, value);
+    let test_value: Value = de::from_bytes(&cache::expand(&optimized.bytes).unwrap()).unwrap();
+    assert_eq!(test_value, value);
+
+    // caching only strings keeps the output readable without expanding
+    let optimized = cache::optimize_with(&plain, &CachePolicy::default()).unwrap();
+    let test_records: Vec<Record> = de::from_bytes(&optimized.bytes).unwrap();
+    assert_eq!(test_records.len(), 10);
+    assert_eq!(test_records[3].id, 3);
+    assert_eq!(test_records[3].status, KEY::namespaced("status".to_string(), "active".to_string()));
+    assert_eq!(test_records[9].tags, vec!["alpha", "beta", "gamma", "delta"]);
+
+    // caching nothing gives back the plain encoding
+    let policy = CachePolicy { keywords: false, map_keys: false, min_value_size: None };
+    let optimized = cache::optimize_with(&plain, &policy).unwrap();
+    assert_eq!(optimized.bytes, plain);
+    assert_eq!(optimized.saved(), 0);
+}
+
+#[test]
+fn optimize_malformed_strings(){
+    for malformed in &[&[0xDB, 0xC3][..], &[0xDC, 0xE2, 0x82], &[0xE6, 0xCD, 0xDB, 0xC3, 0x80]] {
+        let err = cache::optimize(malformed).unwrap_err();
+        assert_eq!(err.err.code.to_string(), "InvalidUTF8");
+        assert!(de::from_bytes::<Value>(malformed).is_err());
+    }
+}
+
+#[test]
+fn optimize_cached_values(){
+    // a put before a collection caches the whole collection, not the first
+    // string inside it
+    let a = Value::LIST(vec![Value::STRING("a".to_string())]);
+    let value = Value::LIST(vec![a.clone(), a]);
+    let policy = CachePolicy { min_value_size: Some(1), ..CachePolicy::default() };
+    let optimized = cache::optimize_with(&ser::to_vec(&value).unwrap(), &policy).unwrap();
+    assert_eq!(optimized.bytes, vec![0xE6, 0xCD, 0xE5, 0xDB, 0x61, 0x80]);
+    let test_value: Value = de::from_bytes(&optimized.bytes).unwrap();
+    assert_eq!(test_value, value);
+
+    // nothing inside a cached value is put, it is only written once
+    let inner = Value::LIST(vec![Value::INT(1), Value::INT(2), Value::INT(3)]);
+    let a = Value::LIST(vec![inner]);
+    let value = Value::LIST(vec![a.clone(), a]);
+    let optimized = cache::optimize_with(&ser::to_vec(&value).unwrap(), &policy).unwrap();
+    assert_eq!(optimized.bytes, vec![0xE6, 0xCD, 0xE5, 0xE7, 1, 2, 3, 0x80]);
+
+    // nested collections and scalars, with puts and references at every level
+    let kw = Value::KEY(KEY::namespaced("ns".to_string(), "kw".to_string()));
+    let point = Value::LIST(vec![Value::INT(1000), Value::INT(2000), kw.clone()]);
+    let mut m = BTreeMap::new();
+    m.insert(Value::STRING("points".to_string()), Value::LIST(vec![point.clone(), point.clone()]));
+    m.insert(kw.clone(), Value::LIST(vec![point.clone(), Value::from(2.5)]));
+    let value = Value::LIST(vec![Value::MAP(m.clone()), Value::MAP(m), point, Value::from(2.5)]);
+    let bytes = ser::to_vec(&value).unwrap();
+    for min_value_size in [1, 4, 16] {
+        let policy = CachePolicy { min_value_size: Some(min_value_size), ..CachePolicy::default() };
+        let optimized = cache::optimize_with(&bytes, &policy).unwrap();
+        let test_value: Value = de::from_bytes(&optimized.bytes).unwrap();
+        assert_eq!(test_value, value);
+        assert_eq!(serde_fressian::lazy::get_in(&optimized.bytes, &[PathSegment::Index(1), PathSegment::Key("points")]).unwrap(),
+                   value.get_in(&[PathSegment::Index(1), PathSegment::Key("points")]).cloned());
+    }
+}
+
+#[test]
+fn optimize_keeps_footer(){
+    let value = records();
+    let bytes = ser::to_vec_footer(&value).unwrap();
+    let optimized = cache::optimize(&bytes).unwrap();
+    let n = optimized.bytes.len();
+    assert_eq!(&optimized.bytes[n - 12 .. n - 8], &[207,207,207,207]);
+    assert_eq!(optimized.bytes[n - 5] as usize, (n - 12) & 0xFF);
+}
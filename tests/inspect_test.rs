@@ -0,0 +1,173 @@
+#![allow(dead_code)]
+#![allow(unused_imports)]
+
+#[macro_use]
+extern crate serde_fressian;
+
+use serde_fressian::inspect::{self, TypeStats};
+use serde_fressian::ser;
+use serde_fressian::value::Value;
+
+#[test]
+fn stats_test(){
+    let value = fress!([:user/name :user/name "abc" 1 nil]);
+    let bytes = ser::to_vec(&value).unwrap();
+    let stats = inspect::stats(&bytes).unwrap();
+
+    assert_eq!(stats.values, 1);
+    assert_eq!(stats.footers, 0);
+    assert_eq!(stats.bytes, bytes.len());
+    // every byte belongs to one type
+    assert_eq!(stats.types.values().map(|t| t.bytes).sum::<usize>(), bytes.len());
+    assert_eq!(stats.types["KEY"], TypeStats { count: 2, bytes: 2 });
+    assert_eq!(stats.types["LIST"].count, 1);
+    assert_eq!(stats.types["INT"].count, 1);
+    assert_eq!(stats.types["NULL"].count, 1);
+    // "user" and "name" are cached the first time and referenced the second
+    assert_eq!(stats.cache_puts, 2);
+    assert_eq!(stats.cache_hits.get(&0), Some(&1));
+    assert_eq!(stats.cache_hits.get(&1), Some(&1));
+
+    let bytes = ser::to_vec_footer(&value).unwrap();
+    let stats = inspect::stats(&bytes).unwrap();
+    assert_eq!(stats.values, 1);
+    assert_eq!(stats.footers, 1);
+    assert_eq!(stats.types["FOOTER"], TypeStats { count: 1, bytes: 12 });
+
+    assert!(inspect::stats(&bytes[..3]).is_err());
+    assert_eq!(inspect::code_name(0xE5), "LIST");
+    assert_eq!(inspect::code_name(0x05), "INT");
+    assert_eq!(inspect::code_name(0x80), "CACHE_REF");
+}
+
+#[test]
+fn disassemble_test(){
+    let value = fress!([:user/name :user/name]);
+    let bytes = ser::to_vec(&value).unwrap();
+    let annotated = inspect::disassemble(&bytes);
+
+    // every byte is labeled once, in order
+    let mut position = 0;
+    for a in &annotated {
+        assert_eq!(a.position, position);
+        position += a.bytes.len();
+    }
+    assert_eq!(position, bytes.len());
+
+    let lines: Vec<String> = annotated.iter().map(|a| a.to_string()).collect();
+    assert_eq!(lines, vec![
+        "0xE6 LIST_PACKED len=2",
+        "0xCA KEY",
+        "0xCD PUT_PRIORITY_CACHE #0",
+        "0xDE STRING_PACKED len=4 \"user\"",
+        "0xCD PUT_PRIORITY_CACHE #1",
+        "0xDE STRING_PACKED len=4 \"name\"",
+        "0xCA KEY",
+        "0x80 cache ref #0 → \"user\"",
+        "0x81 cache ref #1 → \"name\"",
+    ]);
+    assert_eq!(annotated.iter().map(|a| a.depth).collect::<Vec<_>>(), vec![0, 1, 2, 2, 2, 2, 1, 2, 2]);
+
+    let text = inspect::render(&annotated);
+    assert!(text.starts_with("00000000  e6                       LIST_PACKED len=2\n"));
+    assert!(text.contains("00000003  de 75 73 65 72               STRING_PACKED len=4 \"user\"\n"));
+
+    // an unreadable tail is labeled with the error
+    let annotated = inspect::disassemble(&bytes[..5]);
+    let last = annotated.last().unwrap();
+    assert_eq!((last.position, last.bytes), (3, &bytes[3..5]));
+    assert!(last.label.starts_with("error: "));
+
+    let bytes = ser::to_vec_footer(&fress!([1 -2.5 "x"])).unwrap();
+    let labels: Vec<String> = inspect::disassemble(&bytes).into_iter().map(|a| a.label).collect();
+    assert_eq!(&labels[..4], &["LIST_PACKED len=3", "INT_PACKED_1 1", "DOUBLE -2.5", "STRING_PACKED len=1 \"x\""]);
+    assert!(labels[4].starts_with("FOOTER length=13 checksum="));
+
+    // cache puts and precaches of top level values
+    let labels: Vec<String> = inspect::disassemble(&[0xCD, 0xDD, b'f', b'o', b'o', 0x80]).into_iter().map(|a| a.label).collect();
+    assert_eq!(labels.last().unwrap(), "cache ref #0 → \"foo\"");
+    let labels: Vec<String> = inspect::disassemble(&[0xCE, 0xDD, b'f', b'o', b'o', 0xE5, 0x80]).into_iter().map(|a| a.label).collect();
+    assert_eq!(labels, vec!["PRECACHE #0", "STRING_PACKED len=3 \"foo\"", "LIST_PACKED len=1", "cache ref #0 → \"foo\""]);
+}
+
+#[test]
+fn validate_test(){
+    let value = fress!([{:user/name "x", :tags #{:a :b}} {:user/name "y"}]);
+    let bytes = ser::to_vec(&value).unwrap();
+    let summary = inspect::validate(&bytes).unwrap();
+    assert_eq!(summary.values, 1);
+    assert_eq!(summary.footers, 0);
+    assert_eq!(summary.bytes, bytes.len());
+    // stats counts cache puts as a type of their own
+    let stats = inspect::stats(&bytes).unwrap();
+    assert_eq!(summary.objects, stats.types.values().map(|t| t.count).sum::<usize>() - stats.cache_puts);
+
+    // every truncation is caught
+    for end in 1..bytes.len() {
+        assert!(inspect::validate(&bytes[..end]).is_err(), "{}", end);
+    }
+
+    // MAP [1 2 3]
+    let err = inspect::validate(&[0xC0, 0xE7, 0x01, 0x02, 0x03]).unwrap_err();
+    assert_eq!((err.err.code.to_string().as_str(), err.err.position), ("OddMapLength", 0));
+    // MAP 1
+    let err = inspect::validate(&[0xC0, 0x01]).unwrap_err();
+    assert_eq!(err.err.position, 0);
+    // [(cached [1]) MAP #0]
+    let err = inspect::validate(&[0xE6, 0xCD, 0xE5, 0x01, 0xC0, 0x80]).unwrap_err();
+    assert_eq!((err.err.code.to_string().as_str(), err.err.position), ("OddMapLength", 4));
+    // BEGIN_CLOSED_LIST 1 without END_COLLECTION
+    assert!(inspect::validate(&[0xED, 0x01]).is_err());
+    assert_eq!(inspect::validate(&[0xED, 0x01, 0xFD]).unwrap().objects, 2);
+    // BYTES -1, and BYTES past i32::MAX, are counts the reader rejects too
+    let err = inspect::validate(&[0xD9, 0xFF]).unwrap_err();
+    assert_eq!((err.err.code.to_string().as_str(), err.err.position), ("Expectedi64", 1));
+    let err = inspect::validate(&[0xD9, 0xF8, 0, 0, 0, 0, 0x80, 0, 0, 0]).unwrap_err();
+    assert_eq!((err.err.code.to_string().as_str(), err.err.position), ("Expectedi64", 1));
+    // strings cut off in the middle of a character, two and three byte ones
+    for malformed in &[&[0xDB, 0xC3][..], &[0xDC, 0xE2, 0x82], &[0xE5, 0xE3, 0x01, 0xC3], &[0xE6, 0x01, 0xDB, 0xE2]] {
+        let err = inspect::validate(malformed).unwrap_err();
+        assert_eq!(err.err.code.to_string(), "InvalidUTF8");
+        let labels: Vec<String> = inspect::disassemble(malformed).into_iter().map(|a| a.label).collect();
+        assert!(labels.last().unwrap().starts_with("error: InvalidUTF8"));
+    }
+    let err = inspect::validate(&[0xE6, 0x01, 0xDB, 0xC3]).unwrap_err();
+    assert_eq!(err.err.position, 2);
+    assert!(inspect::validate(&ser::to_vec(&"é€").unwrap()).is_ok());
+    // cache reference to nothing
+    let err = inspect::validate(&[0xE5, 0x80]).unwrap_err();
+    assert_eq!((err.err.code.to_string().as_str(), err.err.position), ("UnknownCacheRef", 1));
+
+    let mut bytes = ser::to_vec_footer(&value).unwrap();
+    bytes.extend(ser::to_vec_footer(&fress!([1 2])).unwrap());
+    let summary = inspect::validate(&bytes).unwrap();
+    assert_eq!((summary.values, summary.footers), (2, 2));
+
+    // footer length is wrong
+    let mut bad = bytes.clone();
+    let footer = bad.len() - 12;
+    bad[footer + 7] += 1;
+    let err = inspect::validate(&bad).unwrap_err();
+    assert_eq!((err.err.code.to_string().as_str(), err.err.position), ("InvalidFooter", footer));
+
+    // adler32 of [0xE6 0x01 0x02] is 0x02B900EA
+    let segment = [0xE6, 0x01, 0x02];
+    let mut checked = segment.to_vec();
+    checked.extend(&[0xCF, 0xCF, 0xCF, 0xCF, 0, 0, 0, 3, 0x02, 0xB9, 0x00, 0xEA]);
+    assert_eq!(inspect::validate(&checked).unwrap().footers, 1);
+    let last = checked.len() - 1;
+    checked[last] += 1;
+    let err = inspect::validate(&checked).unwrap_err();
+    assert_eq!((err.err.code.to_string().as_str(), err.err.position), ("InvalidChecksum", 3));
+
+    // nesting is limited rather than overflowing the stack
+    let mut deep = vec![0xE5; 200_000];
+    deep.push(0xF7);
+    let err = inspect::validate(&deep).unwrap_err();
+    assert_eq!(err.err.code.to_string(), "DepthLimitExceeded");
+    assert!(inspect::stats(&deep).is_err());
+    assert!(inspect::disassemble(&deep).last().unwrap().label.starts_with("error: DepthLimitExceeded"));
+    let mut nested = vec![0xE5; 500];
+    nested.push(0xF7);
+    assert_eq!(inspect::validate(&nested).unwrap().objects, 501);
+}
@@ -0,0 +1,77 @@
+#![allow(dead_code)]
+#![allow(unused_imports)]
+
+#[macro_use]
+extern crate serde_derive;
+extern crate serde;
+#[macro_use]
+extern crate serde_fressian;
+
+use serde_fressian::key::{KEY};
+use serde_fressian::lazy;
+use serde_fressian::ser;
+use serde_fressian::value::{PathSegment, Value};
+
+#[derive(Deserialize, Debug, PartialEq)]
+struct User {
+    name: String,
+    id: i64,
+}
+
+#[test]
+fn get_in_test(){
+    // big enough to be written in chunks, which can be skipped but not read yet
+    let big = Value::BYTES(vec![1u8; 70_000].into());
+    let value = fress!({:blob (big),
+                        :users [{"name" "a", "id" 1} {"name" "b", "id" 2, "tags" #{:x :y}}],
+                        "s" "str",
+                        :user/name :user/name});
+    let bytes = ser::to_vec(&value).unwrap();
+
+    let get = |path: &[PathSegment]| lazy::get_in(&bytes, path).unwrap();
+    let tags = Value::from("tags");
+    let x = fress!(:x);
+    let one = Value::INT(1);
+    let paths: Vec<Vec<PathSegment>> = vec![
+        vec![":users".into()],
+        vec!["users".into(), 1.into(), "name".into()],
+        vec!["users".into(), (&one).into(), (&tags).into()],
+        vec!["users".into(), 1.into(), "tags".into(), (&x).into()],
+        vec!["s".into()],
+        vec!["user/name".into()],
+        // missing
+        vec!["users".into(), 2.into()],
+        vec!["users".into(), 0.into(), "tags".into()],
+        vec!["s".into(), 0.into()],
+        vec!["users".into(), "name".into()],
+        vec!["nope".into()],
+    ];
+    for path in &paths {
+        assert_eq!(get(path).as_ref(), value.get_in(path), "{:?}", path);
+    }
+
+    let user: Option<User> = lazy::get_in_as(&bytes, &["users".into(), 0.into()]).unwrap();
+    assert_eq!(user, Some(User { name: "a".to_string(), id: 1 }));
+    let s: Option<&str> = lazy::get_in_as(&bytes, &["s".into()]).unwrap();
+    assert_eq!(s, Some("str"));
+    // the keyword's strings were cached by the key beside it
+    let k: Option<KEY> = lazy::get_in_as(&bytes, &[":user/name".into()]).unwrap();
+    assert_eq!(k, Some(KEY::namespaced("user".to_string(), "name".to_string())));
+
+    // a closed list holding [1 "a" [2]]
+    let bytes = [0xED, 0x01, 0xDB, 0x61, 0xE5, 0x02, 0xFD];
+    assert_eq!(lazy::get_in(&bytes, &[2.into(), 0.into()]).unwrap(), Some(Value::INT(2)));
+    assert_eq!(lazy::get_in(&bytes, &[3.into()]).unwrap(), None);
+    assert!(lazy::get_in(&bytes[..5], &[2.into(), 0.into()]).is_err());
+
+    // [[[...]]] 1, skipping the nested lists stops at the depth limit
+    let nested = |depth: usize| {
+        let mut bytes = vec![0xE6];
+        bytes.extend(vec![0xE5; depth]);
+        bytes.extend(&[0x00, 0x01]);
+        bytes
+    };
+    assert_eq!(lazy::get_in(&nested(500), &[1.into()]).unwrap(), Some(Value::INT(1)));
+    let err = lazy::get_in(&nested(300_000), &[1.into()]).unwrap_err();
+    assert_eq!(err.err.code.to_string(), "DepthLimitExceeded");
+}
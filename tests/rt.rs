@@ -7,14 +7,12 @@
 #[macro_use]
 extern crate serde_derive;
 extern crate serde;
-extern crate serde_bytes;
+#[macro_use]
 extern crate serde_fressian;
-extern crate ordered_float;
 
 use std::collections::{BTreeSet, BTreeMap};
 
-use ordered_float::OrderedFloat;
-use serde_bytes::{ByteBuf, Bytes};
+use serde::de::Deserialize;
 
 // use serde_fressian::inst::{INST};
 // use serde_fressian::uuid::{UUID};
@@ -25,7 +23,7 @@ use serde_fressian::key::{KEY};
 // use serde_fressian::typed_arrays::*;
 use serde_fressian::set::{SET};
 
-use serde_fressian::value::{self, Value};
+use serde_fressian::value::{self, ByteBuf, OrderedFloat, Value};
 use serde_fressian::de::{self};
 use serde_fressian::ser::{self};
 
@@ -175,4 +173,136 @@ fn cache_hits(){
     assert_eq!(&control_bytes, &test_bytes);
 }
 
-// need serde-with + type extraction
\ No newline at end of file
+#[derive(Deserialize, Debug, PartialEq)]
+struct Borrowed<'a> {
+    name: &'a str,
+    data: &'a [u8],
+    #[serde(borrow)]
+    tag: std::borrow::Cow<'a, str>,
+    #[serde(borrow)]
+    note: std::borrow::Cow<'a, str>,
+}
+
+#[derive(Serialize)]
+struct Owned {
+    name: String,
+    data: ByteBuf,
+    tag: String,
+    note: String,
+}
+
+fn within(bytes: &[u8], s: &[u8]) -> bool {
+    let range = bytes.as_ptr_range();
+    range.start <= s.as_ptr() && s.as_ptr() < range.end
+}
+
+#[test]
+fn borrowed_rt(){
+    let owned = Owned {
+        name: "borrowed".to_string(),
+        data: ByteBuf::from(vec![0u8, 1, 2, 255]),
+        tag: "ascii".to_string(),
+        note: "déjà vu".to_string(),
+    };
+    let bytes = ser::to_vec(&owned).unwrap();
+
+    // ascii STRINGs and BYTES borrow from the input
+    let b: Borrowed = de::from_bytes(&bytes).unwrap();
+    assert_eq!(b.name, "borrowed");
+    assert_eq!(b.data, &[0u8, 1, 2, 255][..]);
+    assert!(within(&bytes, b.name.as_bytes()));
+    assert!(within(&bytes, b.data));
+    match b.tag {
+        std::borrow::Cow::Borrowed(tag) => {
+            assert_eq!(tag, "ascii");
+            assert!(within(&bytes, tag.as_bytes()));
+        }
+        std::borrow::Cow::Owned(_) => panic!("expected a borrowed tag")
+    }
+    // modified utf8 has to be decoded
+    assert_eq!(b.note, "déjà vu");
+    match b.note {
+        std::borrow::Cow::Owned(_) => (),
+        std::borrow::Cow::Borrowed(_) => panic!("expected an owned note")
+    }
+
+    // a non ascii STRING cannot be a &str
+    let note = ser::to_vec(&"déjà vu").unwrap();
+    assert!(de::from_bytes::<&str>(&note).is_err());
+    assert_eq!(de::from_bytes::<String>(&note).unwrap(), "déjà vu");
+
+    // UTF8 is borrowed even when it is not ascii
+    let mut utf8: Vec<u8> = vec![0xBF, "déjà vu".len() as u8];
+    utf8.extend_from_slice("déjà vu".as_bytes());
+    let s: &str = de::from_bytes(&utf8).unwrap();
+    assert_eq!(s, "déjà vu");
+    assert!(within(&utf8, s.as_bytes()));
+
+    // and validated
+    let invalid: Vec<u8> = vec![0xBF, 2, 0xC3, 0x28];
+    let err = de::from_bytes::<String>(&invalid).unwrap_err();
+    assert_eq!(err.err.code.to_string(), "InvalidUTF8");
+
+    let empty = ser::to_vec(&"").unwrap();
+    assert_eq!(de::from_bytes::<&str>(&empty).unwrap(), "");
+}
+
+#[derive(Deserialize, Debug, PartialEq)]
+struct Picked {
+    name: String,
+    kw: KEY,
+}
+
+#[test]
+fn ignored_any_skips(){
+    // "a" sorts first, so its keys put the strings that "kw" refers to
+    let big = Value::BYTES(ByteBuf::from(vec![7u8; 100_000]));
+    let value = fress!({"a" [:user/name (big) "long string goes here" #{1.5 nil} {"x" [true -2]}],
+                        "kw" :user/name,
+                        "name" "x",
+                        "z" [#{:c} (Value::INT(1 << 40))]});
+    let bytes = ser::to_vec(&value).unwrap();
+    let picked = Picked { name: "x".to_string(), kw: KEY::namespaced("user".to_string(), "name".to_string()) };
+    assert_eq!(de::from_vec::<Picked>(&bytes).unwrap(), picked);
+    #[cfg(feature = "std")]
+    assert_eq!(de::from_reader::<_, Picked>(&bytes[..]).unwrap(), picked);
+
+    // skipping a whole value ends where decoding it would
+    let mut de = de::Deserializer::from_bytes(&bytes);
+    serde::de::IgnoredAny::deserialize(&mut de).unwrap();
+    assert_eq!(de.byte_offset(), bytes.len());
+
+    // a closed list, and a truncated one
+    let mut de = de::Deserializer::from_bytes(&[0xED, 0x01, 0xDB, 0x61, 0xFD]);
+    serde::de::IgnoredAny::deserialize(&mut de).unwrap();
+    assert_eq!(de.byte_offset(), 5);
+    assert!(de::from_bytes::<serde::de::IgnoredAny>(&bytes[..bytes.len() - 1]).is_err());
+
+    // negative lengths, BYTES -1 and STRING -1
+    for malformed in &[[0xD9, 0xFF], [0xE3, 0xFF]] {
+        let err = de::from_bytes::<serde::de::IgnoredAny>(malformed).unwrap_err();
+        assert_eq!(err.err.code.to_string(), "Expectedi64");
+    }
+    // and one longer than the input
+    assert!(de::from_bytes::<serde::de::IgnoredAny>(&[0xD9, 0xF8, 0, 0, 0, 0, 0x7F, 0xFF, 0xFF, 0xFF]).unwrap_err().is_eof());
+    // and one past i32::MAX
+    let err = de::from_bytes::<serde::de::IgnoredAny>(&[0xD9, 0xF8, 0, 0, 0, 0, 0x80, 0, 0, 0]).unwrap_err();
+    assert_eq!(err.err.code.to_string(), "Expectedi64");
+
+    // {"a" [[[...]]] "name" "x"}, where skipping "a" stops at the depth limit
+    #[derive(Deserialize, Debug)]
+    struct Named {
+        name: String
+    }
+    let nested = |depth: usize| {
+        let mut bytes = vec![0xC0, 0xE8, 0xDB, b'a'];
+        bytes.extend(vec![0xE5; depth]);
+        bytes.extend(&[0x00, 0xDE, b'n', b'a', b'm', b'e', 0xDB, b'x']);
+        bytes
+    };
+    assert_eq!(de::from_bytes::<Named>(&nested(500)).unwrap().name, "x");
+    let err = de::from_bytes::<Named>(&nested(300_000)).unwrap_err();
+    assert_eq!(err.err.code.to_string(), "DepthLimitExceeded");
+}
+
+// need serde-with + type extraction
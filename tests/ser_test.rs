@@ -7,7 +7,6 @@
 #[macro_use]
 extern crate serde_derive;
 extern crate serde;
-extern crate serde_bytes;
 extern crate serde_fressian;
 
 use std::collections::{HashMap, HashSet};
@@ -96,44 +95,48 @@ fn de_test(){
 
     ///////////////////////////////////////////////////////////////
 
-    //map
-    //(api/write {"foo" "bar"})
-    let value: Vec<u8> = vec![192,230,221,102,111,111,221,98,97,114];
-    let mut control: HashMap<String,String> = HashMap::new();
-    control.insert("foo".to_string(), "bar".to_string());
-    let t: HashMap<String,String> = serde_fressian::de::from_vec(&value).unwrap();
-    assert_eq!(control, t);
-
-    ////////////////////////////////////////////////////////////////////
-
-    //map; closed list
-    // (write-as-closed {"thom" "jonny" "phil" "colin"})
-    let value: Vec<u8> = vec![192,237,222,116,104,111,109,223,106,111,110,110,121,222,112,104,105,108,223,99,111,108,105,110,253];
-    let mut control: HashMap<String,String> = HashMap::new();
-    control.insert("thom".to_string(), "jonny".to_string());
-    control.insert("phil".to_string(), "colin".to_string());
-    let t: HashMap<String,String> = serde_fressian::de::from_vec(&value).unwrap();
-    assert_eq!(control, t);
-
-    ////////////////////////////////////////////////////////////////////
-
-    //set (api/write #{"thom" "jonny" "phil" "colin" "ed"})
-    let value: Vec<u8> = vec![193,233,223,99,111,108,105,110,220,101,100,222,116,104,111,109,223,106,111,110,110,121,222,112,104,105,108];
-    let mut control: HashSet<String> = HashSet::new();
-    control.insert("thom".to_string());
-    control.insert("jonny".to_string());
-    control.insert("phil".to_string());
-    control.insert("colin".to_string());
-    control.insert("ed".to_string());
-    let t: HashSet<String> = serde_fressian::de::from_vec(&value).unwrap();
-    assert_eq!(control, t);
-
-    ////////////////////////////////////////////////////////////////////
-
-    // (write-as-closed #{"thom" "jonny" "phil" "colin" "ed"})
-    let value: Vec<u8> = vec![193,237,223,99,111,108,105,110,220,101,100,222,116,104,111,109,223,106,111,110,110,121,222,112,104,105,108,253];
-    let t: HashSet<String> = serde_fressian::de::from_vec(&value).unwrap();
-    assert_eq!(control, t);
+    // std only: serde has no HashMap or HashSet impls without it
+    #[cfg(feature = "std")]
+    {
+        //map
+        //(api/write {"foo" "bar"})
+        let value: Vec<u8> = vec![192,230,221,102,111,111,221,98,97,114];
+        let mut control: HashMap<String,String> = HashMap::new();
+        control.insert("foo".to_string(), "bar".to_string());
+        let t: HashMap<String,String> = serde_fressian::de::from_vec(&value).unwrap();
+        assert_eq!(control, t);
+
+        ////////////////////////////////////////////////////////////////////
+
+        //map; closed list
+        // (write-as-closed {"thom" "jonny" "phil" "colin"})
+        let value: Vec<u8> = vec![192,237,222,116,104,111,109,223,106,111,110,110,121,222,112,104,105,108,223,99,111,108,105,110,253];
+        let mut control: HashMap<String,String> = HashMap::new();
+        control.insert("thom".to_string(), "jonny".to_string());
+        control.insert("phil".to_string(), "colin".to_string());
+        let t: HashMap<String,String> = serde_fressian::de::from_vec(&value).unwrap();
+        assert_eq!(control, t);
+
+        ////////////////////////////////////////////////////////////////////
+
+        //set (api/write #{"thom" "jonny" "phil" "colin" "ed"})
+        let value: Vec<u8> = vec![193,233,223,99,111,108,105,110,220,101,100,222,116,104,111,109,223,106,111,110,110,121,222,112,104,105,108];
+        let mut control: HashSet<String> = HashSet::new();
+        control.insert("thom".to_string());
+        control.insert("jonny".to_string());
+        control.insert("phil".to_string());
+        control.insert("colin".to_string());
+        control.insert("ed".to_string());
+        let t: HashSet<String> = serde_fressian::de::from_vec(&value).unwrap();
+        assert_eq!(control, t);
+
+        ////////////////////////////////////////////////////////////////////
+
+        // (write-as-closed #{"thom" "jonny" "phil" "colin" "ed"})
+        let value: Vec<u8> = vec![193,237,223,99,111,108,105,110,220,101,100,222,116,104,111,109,223,106,111,110,110,121,222,112,104,105,108,253];
+        let t: HashSet<String> = serde_fressian::de::from_vec(&value).unwrap();
+        assert_eq!(control, t);
+    }
 }
 
 #[test]
@@ -179,7 +182,7 @@ fn bytes_serialization_test(){
     // - can't override built in serialize impl?
     // https://github.com/rust-lang/rust/issues/31844
     // soln -> https://docs.serde.rs/serde_bytes/
-    let bb = serde_bytes::ByteBuf::from(v);
+    let bb = serde_fressian::value::ByteBuf::from(v);
     fw.reset();
     bb.serialize(&mut fw).unwrap();
     assert_eq!(&fw.to_vec(), &control);
@@ -269,6 +272,7 @@ fn assert_map_eq(a: &Vec<u8>, b: &Vec<u8>, count: i32) {
 
 }
 
+#[cfg(feature = "std")]
 #[test]
 fn map_test() {
     let mut fw = Serializer::from_vec(Vec::new());
@@ -511,6 +515,7 @@ impl Serialize for ClosedListMap {
     }
 }
 
+#[cfg(feature = "std")]
 #[test]
 fn closed_list_map_test(){
 
@@ -530,4 +535,368 @@ fn closed_list_map_test(){
 
     let test_val: HashMap<String,u8> = de::from_vec(&test_bytes).unwrap();
     assert_eq!(control_val, test_val)
-}
\ No newline at end of file
+}
+
+#[derive(Serialize)]
+enum Variants {
+    N(i64),
+    T(i64, i64),
+    S { a: i64, b: i64 },
+}
+
+#[test]
+fn variant_test(){
+    // every variant with data is a two element list, [variant data]
+    assert_eq!(ser::to_vec(&Variants::N(1)).unwrap(), vec![0xE6, 0xDB, b'N', 1]);
+    // [variant [fields]]
+    assert_eq!(ser::to_vec(&Variants::T(1, 2)).unwrap(), vec![0xE6, 0xDB, b'T', 0xE6, 1, 2]);
+    // [variant {fields}]
+    assert_eq!(ser::to_vec(&Variants::S { a: 1, b: 2 }).unwrap(),
+               vec![0xE6, 0xDB, b'S', 0xC0, 0xE8, 0xDB, b'a', 1, 0xDB, b'b', 2]);
+    // one value each, so they can be list elements
+    assert_eq!(ser::to_vec(&vec![Variants::T(1, 2)]).unwrap(), vec![0xE5, 0xE6, 0xDB, b'T', 0xE6, 1, 2]);
+}
+
+#[cfg(feature = "std")]
+struct FailingWriter {
+    limit: usize
+}
+
+#[cfg(feature = "std")]
+impl std::io::Write for FailingWriter {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        if self.limit < buf.len() {
+            Err(std::io::Error::new(std::io::ErrorKind::Other, "full"))
+        } else {
+            self.limit -= buf.len();
+            Ok(buf.len())
+        }
+    }
+    fn flush(&mut self) -> std::io::Result<()> { Ok(()) }
+}
+
+#[cfg(feature = "std")]
+#[test]
+fn to_writer_test(){
+    let data: Vec<String> = vec!["some".to_string(), "strings".to_string()];
+
+    let mut out: Vec<u8> = Vec::new();
+    ser::to_writer(&mut out, &data).unwrap();
+    assert_eq!(out, ser::to_vec(&data).unwrap());
+
+    let mut cursor = std::io::Cursor::new(Vec::new());
+    ser::to_writer(&mut cursor, &data).unwrap();
+    assert_eq!(cursor.into_inner(), out);
+
+    // the cache works the same as with vecs
+    let mut fw = Serializer::from_writer(Vec::new());
+    let k = serde_fressian::key::KEY::simple("foo".to_string());
+    k.serialize(&mut fw).unwrap();
+    k.serialize(&mut fw).unwrap();
+    fw.write_footer().unwrap();
+    let bytes = fw.into_inner();
+    let mut control = ser::to_vec(&vec![k.clone(), k.clone()]).unwrap()[1..].to_vec();
+    control.extend(vec![207,207,207,207, 0,0,0,10, 0,0,0,0]);
+    assert_eq!(bytes, control);
+
+    let err = ser::to_writer(FailingWriter{limit: 3}, &data).unwrap_err();
+    assert_eq!(err.classify(), serde_fressian::error::Category::Io);
+    // the list and string headers made it out, the string body did not
+    assert_eq!(err.err.position, 2);
+}
+
+#[cfg(feature = "std")]
+/// hands out one byte per read call
+struct Trickle<'a> {
+    bytes: &'a [u8]
+}
+
+#[cfg(feature = "std")]
+impl<'a> std::io::Read for Trickle<'a> {
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        if self.bytes.is_empty() || buf.is_empty() {
+            Ok(0)
+        } else {
+            buf[0] = self.bytes[0];
+            self.bytes = &self.bytes[1..];
+            Ok(1)
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+#[test]
+fn from_reader_test(){
+    use std::io::Read;
+    use serde_fressian::value::Value;
+
+    let long_string: String = std::iter::repeat("fressian").take(3000).collect();
+    let data: Vec<String> = vec!["some".to_string(), long_string, "strings".to_string()];
+    let bytes = ser::to_vec(&data).unwrap();
+
+    let test_data: Vec<String> = de::from_reader(bytes.as_slice()).unwrap();
+    assert_eq!(test_data, data);
+
+    let test_data: Vec<String> = de::from_reader(Trickle{bytes: &bytes}).unwrap();
+    assert_eq!(test_data, data);
+
+    let test_value: Value = de::from_reader(Trickle{bytes: &bytes}).unwrap();
+    assert_eq!(test_value, de::from_bytes::<Value>(&bytes).unwrap());
+
+    // the input has to end after the value, apart from a footer
+    let res: Result<Vec<String>, _> = de::from_reader(bytes.as_slice().chain(std::io::repeat(0)));
+    assert_eq!(res.unwrap_err().err.code.to_string(), "TrailingBytes");
+    let test_data: Vec<String> = de::from_reader(ser::to_vec_footer(&data).unwrap().as_slice()).unwrap();
+    assert_eq!(test_data, data);
+
+    // only what is needed is read, Deserializer::from_reader can stop anywhere
+    let mut rdr = Deserializer::from_reader(bytes.as_slice().chain(std::io::repeat(0)));
+    assert_eq!(Vec::<String>::deserialize(&mut rdr).unwrap(), data);
+
+    // truncated input is an eof error
+    let res: Result<Vec<String>, _> = de::from_reader(&bytes[0..100]);
+    assert!(res.unwrap_err().is_eof());
+
+    // lengths are not trusted: a negative one is an error, and a huge one only
+    // reads what is there before reaching eof
+    let err = de::from_reader::<_, Value>(&[0xD9, 0xFF][..]).unwrap_err();
+    assert_eq!(err.err.code.to_string(), "Expectedi64");
+    let res = de::from_reader::<_, Value>(&[0xD9, 0xF8, 0, 0, 0, 0, 0x7F, 0xFF, 0xFF, 0xFF][..]);
+    assert!(res.unwrap_err().is_eof());
+
+    // a reader keeps its cache between values
+    let k = serde_fressian::key::KEY::simple("foo".to_string());
+    let bytes = ser::to_vec(&vec![k.clone(), k.clone()]).unwrap();
+    let mut rdr = Deserializer::from_reader(&bytes[1..]);
+    assert_eq!(serde_fressian::key::KEY::deserialize(&mut rdr).unwrap(), k);
+    assert_eq!(serde_fressian::key::KEY::deserialize(&mut rdr).unwrap(), k);
+}
+
+#[test]
+fn stream_deserializer_test(){
+    use serde_fressian::key::KEY;
+    use serde_fressian::value::Value;
+
+    let k = KEY::simple("foo".to_string());
+    let mut fw = Serializer::new();
+    k.serialize(&mut fw).unwrap();
+    k.serialize(&mut fw).unwrap();
+    42i64.serialize(&mut fw).unwrap();
+    let bytes = fw.get_ref().clone();
+
+    // the second keyword is read from the cache the first one filled
+    let mut stream = Deserializer::from_bytes(&bytes).into_iter::<Value>();
+    assert_eq!(stream.next().unwrap().unwrap(), Value::KEY(k.clone()));
+    assert_eq!(stream.byte_offset(), 7);
+    assert_eq!(stream.next().unwrap().unwrap(), Value::KEY(k.clone()));
+    assert_eq!(stream.byte_offset(), 10);
+    assert_eq!(stream.next().unwrap().unwrap(), Value::INT(42));
+    assert_eq!(stream.byte_offset(), 11);
+    assert!(stream.next().is_none());
+    assert!(stream.next().is_none());
+
+    // readers work too
+    #[cfg(feature = "std")]
+    {
+        let keys: Vec<Value> = Deserializer::from_reader(&bytes[..10]).into_iter().map(|v| v.unwrap()).collect();
+        assert_eq!(keys, vec![Value::KEY(k.clone()), Value::KEY(k.clone())]);
+    }
+
+    // iteration stops at a footer
+    fw.write_footer().unwrap();
+    let mut bytes = fw.get_ref().clone();
+    bytes.extend(vec![1, 2, 3]);
+    let mut stream = Deserializer::from_bytes(&bytes).into_iter::<Value>();
+    assert_eq!(stream.by_ref().count(), 3);
+    assert_eq!(stream.byte_offset(), 23);
+
+    // a footer with the wrong length is an error
+    let n = bytes.len();
+    bytes[n - 8] = 99;
+    let values: Vec<_> = Deserializer::from_bytes(&bytes).into_iter::<Value>().collect();
+    assert_eq!(values.len(), 4);
+    assert!(values[3].is_err());
+
+    // errors end the iteration
+    let bytes: Vec<u8> = vec![1, 2, 236];
+    let mut stream = Deserializer::from_bytes(&bytes).into_iter::<i64>();
+    assert_eq!(stream.next().unwrap().unwrap(), 1);
+    assert_eq!(stream.next().unwrap().unwrap(), 2);
+    assert!(stream.next().unwrap().is_err());
+    assert_eq!(stream.byte_offset(), 2);
+    assert!(stream.next().is_none());
+}
+
+#[test]
+fn trailing_bytes_test(){
+    let bytes = ser::to_vec(&vec![1i64, 2, 3]).unwrap();
+    let v: Vec<i64> = de::from_bytes(&bytes).unwrap();
+    assert_eq!(v, vec![1, 2, 3]);
+
+    let mut garbage = bytes.clone();
+    garbage.push(7);
+    let err = de::from_bytes::<Vec<i64>>(&garbage).unwrap_err();
+    assert_eq!(err.err.code.to_string(), "TrailingBytes");
+    assert_eq!(err.err.position, bytes.len());
+
+    // a footer is not trailing garbage, but bytes after it are
+    let footer = ser::to_vec_footer(&vec![1i64, 2, 3]).unwrap();
+    let v: Vec<i64> = de::from_bytes(&footer).unwrap();
+    assert_eq!(v, vec![1, 2, 3]);
+    let mut garbage = footer.clone();
+    garbage.push(7);
+    assert!(de::from_bytes::<Vec<i64>>(&garbage).is_err());
+
+    let mut rdr = Deserializer::from_bytes(&garbage);
+    let _: Vec<i64> = Vec::deserialize(&mut rdr).unwrap();
+    assert!(rdr.end().is_err());
+
+    // partial reads report how much they used
+    let mut framed = bytes.clone();
+    framed.extend(ser::to_vec(&"next").unwrap());
+    let (v, used): (Vec<i64>, usize) = de::from_bytes_partial(&framed).unwrap();
+    assert_eq!(v, vec![1, 2, 3]);
+    assert_eq!(used, bytes.len());
+    let (s, _): (String, usize) = de::from_bytes_partial(&framed[used..]).unwrap();
+    assert_eq!(s, "next");
+}
+
+/// writes part of a list and then fails
+struct HalfWritten;
+
+impl Serialize for HalfWritten {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: serde::Serializer,
+    {
+        use serde::ser::{Error, SerializeSeq};
+        let mut seq = serializer.serialize_seq(Some(2))?;
+        seq.serialize_element("written")?;
+        Err(S::Error::custom("half written"))
+    }
+}
+
+#[test]
+fn to_vec_into_test(){
+    let data: Vec<String> = vec!["some".to_string(), "strings".to_string()];
+    let control = ser::to_vec(&data).unwrap();
+
+    let mut buf: Vec<u8> = Vec::with_capacity(64);
+    ser::to_vec_into(&mut buf, &data).unwrap();
+    assert_eq!(buf, control);
+
+    // appends, and reuses the capacity it was given
+    let capacity = buf.capacity();
+    ser::to_vec_into(&mut buf, &data).unwrap();
+    assert_eq!(&buf[..control.len()], &control[..]);
+    assert_eq!(&buf[control.len()..], &control[..]);
+    assert_eq!(buf.capacity(), capacity);
+
+    // a failed write leaves the buffer as it was
+    let before = buf.clone();
+    assert!(ser::to_vec_into(&mut buf, &HalfWritten).is_err());
+    assert_eq!(buf, before);
+
+    // resetting a borrowed buffer only drops what the serializer wrote
+    let mut buf: Vec<u8> = vec![1, 2, 3];
+    {
+        let mut fw = Serializer::with_buffer(&mut buf);
+        data.serialize(&mut fw).unwrap();
+        assert_eq!(fw.written(), &control[..]);
+        fw.reset();
+        assert!(fw.written().is_empty());
+        "foo".serialize(&mut fw).unwrap();
+    }
+    let mut expected = vec![1, 2, 3];
+    expected.extend(ser::to_vec(&"foo").unwrap());
+    assert_eq!(buf, expected);
+}
+
+#[cfg(feature = "std")]
+#[test]
+fn buffer_pool_test(){
+    use serde_fressian::pool::BufferPool;
+
+    let data: Vec<String> = vec!["some".to_string(), "strings".to_string()];
+    let control = ser::to_vec(&data).unwrap();
+    let pool = BufferPool::new(2, 128);
+    assert!(pool.is_empty());
+
+    {
+        let buf = pool.serialize(&data).unwrap();
+        assert_eq!(&buf[..], &control[..]);
+        let other = pool.get();
+        assert!(other.is_empty());
+    }
+    assert_eq!(pool.len(), 2);
+
+    // returned buffers come back empty with their capacity
+    {
+        let buf = pool.get();
+        assert!(buf.is_empty());
+        assert!(128 <= buf.capacity());
+    }
+
+    // no more than max_pooled buffers are kept
+    {
+        let _a = pool.get();
+        let _b = pool.get();
+        let _c = pool.get();
+    }
+    assert_eq!(pool.len(), 2);
+
+    let owned = pool.serialize(&data).unwrap().into_inner();
+    assert_eq!(owned, control);
+    assert_eq!(pool.len(), 1);
+
+    // usable across threads
+    let pool = std::sync::Arc::new(BufferPool::default());
+    let handles: Vec<_> = (0..4).map(|i| {
+        let pool = pool.clone();
+        std::thread::spawn(move || {
+            let buf = pool.serialize(&i).unwrap();
+            let n: i64 = de::from_bytes(&buf).unwrap();
+            n
+        })
+    }).collect();
+    let results: Vec<i64> = handles.into_iter().map(|h| h.join().unwrap()).collect();
+    assert_eq!(results, vec![0, 1, 2, 3]);
+}
+
+#[test]
+fn to_slice_test(){
+    let data: Vec<String> = vec!["some".to_string(), "strings".to_string()];
+    let control = ser::to_vec(&data).unwrap();
+
+    let mut buf = [0u8; 64];
+    let n = ser::to_slice(&data, &mut buf).unwrap();
+    assert_eq!(n, control.len());
+    assert_eq!(&buf[..n], &control[..]);
+    let read: Vec<String> = de::from_bytes(&buf[..n]).unwrap();
+    assert_eq!(read, data);
+
+    // exactly enough room
+    let mut exact = vec![0u8; control.len()];
+    assert_eq!(ser::to_slice(&data, &mut exact).unwrap(), control.len());
+    assert_eq!(exact, control);
+
+    // the first string fits, the second does not
+    let mut small = [0u8; 8];
+    let err = ser::to_slice(&data, &mut small).unwrap_err();
+    assert_eq!(err.err.code.to_string(), "BufferFull");
+    assert_eq!(err.classify(), serde_fressian::error::Category::Ser);
+    assert_eq!(err.err.position, 8);
+
+    let mut empty: [u8; 0] = [];
+    let err = ser::to_slice(&1i64, &mut empty).unwrap_err();
+    assert_eq!(err.err.position, 0);
+
+    // the cache works the same as with vecs
+    let k = serde_fressian::key::KEY::simple("foo".to_string());
+    let mut buf = [0u8; 64];
+    let mut fw = Serializer::from_slice(&mut buf);
+    k.serialize(&mut fw).unwrap();
+    k.serialize(&mut fw).unwrap();
+    let control = ser::to_vec(&vec![k.clone(), k.clone()]).unwrap()[1..].to_vec();
+    assert_eq!(fw.written(), &control[..]);
+}
@@ -0,0 +1,103 @@
+#![allow(dead_code)]
+#![allow(unused_imports)]
+#![allow(non_snake_case)]
+
+#[macro_use]
+extern crate serde_derive;
+extern crate serde;
+extern crate serde_fressian;
+
+use serde_fressian::key::{KEY};
+use serde_fressian::value::{Value};
+use serde_fressian::session::{FressianSession, SessionReader, SessionWriter};
+use serde_fressian::ser;
+
+#[test]
+fn session_reuses_cache_across_messages(){
+    let mut writer = SessionWriter::new();
+    let mut reader = SessionReader::new();
+
+    let k = KEY::namespaced("user".to_string(), "name".to_string());
+
+    // (write :user/name)
+    let first: Vec<u8> = writer.write_message(&k).unwrap();
+    assert_eq!(first, vec![202,205,222,117,115,101,114,205,222,110,97,109,101]);
+    assert_eq!(first, ser::to_vec(&k).unwrap());
+
+    // both strings now come from the cache
+    let second: Vec<u8> = writer.write_message(&k).unwrap();
+    assert_eq!(second, vec![202,128,129]);
+
+    let a: KEY = reader.read_message(&first).unwrap();
+    let b: KEY = reader.read_message(&second).unwrap();
+    assert_eq!(a, k);
+    assert_eq!(b, k);
+
+    // a fresh reader has no idea what #0 and #1 are
+    let mut lost = SessionReader::new();
+    let res: Result<KEY, _> = lost.read_message(&second);
+    assert!(res.is_err());
+}
+
+#[test]
+fn session_pair_rt(){
+    let mut client = FressianSession::new();
+    let mut server = FressianSession::new();
+
+    let request: Vec<KEY> = vec![KEY::simple("ping".to_string()), KEY::simple("ping".to_string())];
+    let response: Vec<KEY> = vec![KEY::simple("pong".to_string())];
+
+    for _ in 0..3 {
+        let bytes = client.write_message(&request).unwrap();
+        let req: Vec<KEY> = server.read_message(&bytes).unwrap();
+        assert_eq!(req, request);
+
+        let bytes = server.write_message(&response).unwrap();
+        let res: Vec<KEY> = client.read_message(&bytes).unwrap();
+        assert_eq!(res, response);
+    }
+
+    // Value reads share the same cache
+    let bytes = client.write_message(&request).unwrap();
+    let req: Value = server.read_message(&bytes).unwrap();
+    assert_eq!(req, Value::from(request));
+}
+
+#[test]
+fn session_reset_caches(){
+    let mut writer = SessionWriter::new();
+    let mut reader = SessionReader::new();
+    let k = KEY::simple("foo".to_string());
+
+    let bytes = writer.write_message(&k).unwrap();
+    let _: KEY = reader.read_message(&bytes).unwrap();
+
+    writer.reset_caches();
+    let bytes = writer.write_message(&k).unwrap();
+    // RESET_CACHES, then the keyword is written in full again
+    assert_eq!(bytes[0], 254);
+    assert_eq!(&bytes[1..], &ser::to_vec(&k).unwrap()[..]);
+    let test_k: KEY = reader.read_message(&bytes).unwrap();
+    assert_eq!(test_k, k);
+
+    let bytes = writer.write_message(&k).unwrap();
+    assert_eq!(bytes, vec![202,247,128]);
+    let test_k: KEY = reader.read_message(&bytes).unwrap();
+    assert_eq!(test_k, k);
+}
+
+#[test]
+fn session_unpacked_cache_refs(){
+    // past 32 entries refs are written as GET_PRIORITY_CACHE + index
+    let mut session = FressianSession::new();
+    let keys: Vec<KEY> = (0..40).map(|i| KEY::simple(format!("k{}", i))).collect();
+
+    let bytes = session.write_message(&keys).unwrap();
+    let test_keys: Vec<KEY> = session.read_message(&bytes).unwrap();
+    assert_eq!(test_keys, keys);
+
+    let bytes = session.write_message(&keys[39]).unwrap();
+    assert_eq!(bytes, vec![202,247,204,39]);
+    let test_k: KEY = session.read_message(&bytes).unwrap();
+    assert_eq!(test_k, keys[39]);
+}
@@ -8,7 +8,6 @@ extern crate maplit;
 #[macro_use]
 extern crate serde_derive;
 extern crate serde;
-extern crate serde_bytes;
 extern crate serde_fressian;
 // extern crate uuid as _uuid;
 // extern crate url;
@@ -198,7 +197,9 @@ fn typed_arrays_test(){
 
 #[test]
 fn sets(){
-    use serde_fressian::set::{SET, HASHSET};
+    use serde_fressian::set::SET;
+    #[cfg(feature = "std")]
+    use serde_fressian::set::HASHSET;
 
     let btreeset: BTreeSet<i64> = btreeset!{0,1,2,3};
     let output = ser::to_vec(&btreeset); //--> serialized as LIST; [0 1 2 3]
@@ -212,23 +213,28 @@ fn sets(){
     let control_bytes: Vec<u8> = vec![193,232,0,1,2,3];
     assert_eq!(output.unwrap(), control_bytes);
 
-    // SET derives hash from its btreeset, so it can be stored in a hashset if we want
-    // but we could not do the other way around.
-    let hashset: HashSet<SET<i64>> = hashset!{wrapped_btreeset};
-    let output = ser::to_vec(&hashset); //--> serialized as LIST; [#{0 1 2 3}]
+    // HASHSET is std only
+    #[cfg(feature = "std")]
+    {
+        // SET derives hash from its btreeset, so it can be stored in a hashset if we want
+        // but we could not do the other way around.
+        let hashset: HashSet<SET<i64>> = hashset!{wrapped_btreeset};
+        let output = ser::to_vec(&hashset); //--> serialized as LIST; [#{0 1 2 3}]
 
-    //this may have nondet ordering
-    let control_bytes: Vec<u8> = vec![229,193,232,0,1,2,3];
-    assert_eq!(output.unwrap(), control_bytes);
+        //this may have nondet ordering
+        let control_bytes: Vec<u8> = vec![229,193,232,0,1,2,3];
+        assert_eq!(output.unwrap(), control_bytes);
 
-    let wrapped_hashset: HASHSET<SET<i64>> = HASHSET::from(hashset);
-    let output = ser::to_vec(&wrapped_hashset); //--> serialized as SET; #{#{0 1 2 3}}
+        let wrapped_hashset: HASHSET<SET<i64>> = HASHSET::from(hashset);
+        let output = ser::to_vec(&wrapped_hashset); //--> serialized as SET; #{#{0 1 2 3}}
 
-    //this may have nondet ordering
-    let control_bytes: Vec<u8> = vec![193,229,193,232,0,1,2,3];
-    assert_eq!(output.unwrap(), control_bytes);
+        //this may have nondet ordering
+        let control_bytes: Vec<u8> = vec![193,229,193,232,0,1,2,3];
+        assert_eq!(output.unwrap(), control_bytes);
+    }
 }
 
+#[cfg(feature = "std")]
 #[test]
 fn attributes_with_test(){
     use serde_fressian::set::{SET, HASHSET};
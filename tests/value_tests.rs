@@ -5,14 +5,12 @@
 #[macro_use]
 extern crate serde_derive;
 extern crate serde;
-extern crate serde_bytes;
+#[macro_use]
 extern crate serde_fressian;
-extern crate ordered_float;
 
 use std::collections::{BTreeSet, BTreeMap};
 
-use ordered_float::OrderedFloat;
-use serde_bytes::ByteBuf;
+use serde_fressian::value::{ByteBuf, OrderedFloat};
 
 use serde_fressian::inst::{INST};
 use serde_fressian::uuid::{UUID};
@@ -197,3 +195,359 @@ fn map_value_rt(){
     let derived_value: Value = de::from_vec(&test_bytes).unwrap();
     assert_eq!(control_value, derived_value);
 }
+
+#[test]
+fn value_ref_rt(){
+    use std::borrow::Cow;
+    use serde_fressian::value::ValueRef;
+
+    // (write {:foo 42, "baz" [1 2 3]})
+    let control_bytes: Vec<u8> = vec![192,232,202,247,205,221,102,111,111,42,221,98,97,122,231,1,2,3];
+    let test_ref: ValueRef = de::from_bytes(&control_bytes).unwrap();
+    let control_value: Value = de::from_bytes(&control_bytes).unwrap();
+    assert_eq!(test_ref.clone().into_owned(), control_value);
+    assert_eq!(ser::to_vec(&test_ref).unwrap(), ser::to_vec(&control_value).unwrap());
+
+    let within = |s: &[u8]| {
+        let range = control_bytes.as_ptr_range();
+        range.start <= s.as_ptr() && s.as_ptr() < range.end
+    };
+    match test_ref {
+        ValueRef::MAP(ref m) => {
+            let (k, v) = m.iter().find(|(k, _)| match k { ValueRef::STRING(_) => true, _ => false }).unwrap();
+            match k {
+                ValueRef::STRING(Cow::Borrowed(s)) => {
+                    assert_eq!(*s, "baz");
+                    assert!(within(s.as_bytes()));
+                }
+                _ => panic!("expected a borrowed string")
+            }
+            assert_eq!(*v, ValueRef::LIST(vec![ValueRef::INT(1), ValueRef::INT(2), ValueRef::INT(3)]));
+        }
+        _ => panic!("expected a map")
+    }
+    assert!(!test_ref.is_owned());
+
+    let value = Value::LIST(vec![
+        Value::from(vec![0u8, 1, 2].as_slice()),
+        Value::STRING("déjà vu".to_string()),
+        Value::DOUBLE(OrderedFloat::from(1.0)),
+        Value::NULL,
+    ]);
+    let bytes = ser::to_vec(&value).unwrap();
+    let test_ref: ValueRef = de::from_bytes(&bytes).unwrap();
+    match test_ref {
+        ValueRef::LIST(ref items) => {
+            match items[0] {
+                ValueRef::BYTES(Cow::Borrowed(b)) => assert_eq!(b, &[0u8, 1, 2][..]),
+                _ => panic!("expected borrowed bytes")
+            }
+            // modified utf8 is decoded
+            assert_eq!(items[1], ValueRef::STRING(Cow::Owned("déjà vu".to_string())));
+        }
+        _ => panic!("expected a list")
+    }
+    assert_eq!(Value::from(test_ref), value);
+
+    // readers cannot lend out their buffer for long, so everything is owned
+    #[cfg(feature = "std")]
+    {
+        let mut rdr = de::Deserializer::from_reader(&bytes[..]);
+        let test_ref: ValueRef = serde::Deserialize::deserialize(&mut rdr).unwrap();
+        assert!(test_ref.is_owned());
+        assert_eq!(test_ref.into_owned(), value);
+    }
+}
+
+#[derive(Deserialize, Serialize, PartialEq, Debug)]
+enum Shape {
+    Empty,
+    Circle(f64),
+    Rect(f64, f64),
+    Polygon { sides: i64, length: f64 },
+}
+
+#[derive(Deserialize, Serialize, PartialEq, Debug)]
+struct Typed {
+    name: String,
+    tags: Vec<KEY>,
+    sym: SYM,
+    nums: serde_fressian::set::SET<i64>,
+    when: INST,
+    ints: IntArray,
+    maybe: Option<i64>,
+    shapes: Vec<Shape>,
+}
+
+#[test]
+fn from_value_test(){
+    let typed = Typed {
+        name: "nested".to_string(),
+        tags: vec![KEY::new(None, "foo".to_string()), KEY::new(Some("bar".to_string()), "baz".to_string())],
+        sym: SYM::new(Some("ns".to_string()), "s".to_string()),
+        nums: serde_fressian::set::SET::from(vec![1i64, 2, 3].into_iter().collect::<BTreeSet<i64>>()),
+        when: INST::from_millis(1535328836181),
+        ints: IntArray::from_vec(vec![1, -2, 3]),
+        maybe: None,
+        shapes: vec![Shape::Empty, Shape::Circle(1.5), Shape::Rect(2.0, 3.0), Shape::Polygon { sides: 6, length: 0.5 }],
+    };
+    let bytes = ser::to_vec(&typed).unwrap();
+    let value: Value = de::from_bytes(&bytes).unwrap();
+    let from_value: Typed = value::from_value(value.clone()).unwrap();
+    assert_eq!(from_value, typed);
+
+    // a Value reads into a Value unchanged
+    let same: Value = value::from_value(value.clone()).unwrap();
+    assert_eq!(same, value);
+
+    let n: i64 = value::from_value(Value::INT(42)).unwrap();
+    assert_eq!(n, 42);
+    assert!(value::from_value::<i64>(Value::STRING("no".to_string())).is_err());
+}
+
+#[test]
+fn to_value_test(){
+    let typed = Typed {
+        name: "nested".to_string(),
+        tags: vec![KEY::new(None, "foo".to_string())],
+        sym: SYM::new(Some("ns".to_string()), "s".to_string()),
+        nums: serde_fressian::set::SET::from(vec![3i64, 1].into_iter().collect::<BTreeSet<i64>>()),
+        when: INST::from_millis(1535328836181),
+        ints: IntArray::from_vec(vec![1, -2, 3]),
+        maybe: Some(7),
+        shapes: vec![Shape::Empty, Shape::Circle(1.5), Shape::Rect(2.0, 3.0), Shape::Polygon { sides: 6, length: 0.5 }],
+    };
+    // to_value agrees with what the bytes decode to
+    let value = value::to_value(&typed).unwrap();
+    let bytes = ser::to_vec(&typed).unwrap();
+    let control: Value = de::from_bytes(&bytes).unwrap();
+    assert_eq!(value, control);
+    // variants with fields are [variant [fields]] and [variant {fields}]
+    let rect = Shape::Rect(2.0, 3.0);
+    assert_eq!(value::to_value(&rect).unwrap(), de::from_bytes::<Value>(&ser::to_vec(&rect).unwrap()).unwrap());
+    assert_eq!(value::to_value(&rect).unwrap().to_string(), "[\"Rect\" [2.0 3.0]]");
+    let polygon = Shape::Polygon { sides: 6, length: 0.5 };
+    assert_eq!(value::to_value(&polygon).unwrap(), de::from_bytes::<Value>(&ser::to_vec(&polygon).unwrap()).unwrap());
+    // same content, though MAP entries are written in Value order
+    let reread: Value = de::from_bytes(&ser::to_vec(&value).unwrap()).unwrap();
+    assert_eq!(reread, control);
+
+    let back: Typed = value::from_value(value).unwrap();
+    assert_eq!(back, typed);
+
+    let arrays = (LongArray::from_vec(vec![1, 2]),
+                  FloatArray::from_vec(vec![1.5]),
+                  DoubleArray::from_vec(vec![2.5]),
+                  BooleanArray::from_vec(vec![true, false]));
+    let value = value::to_value(&arrays).unwrap();
+    let control: Value = de::from_bytes(&ser::to_vec(&arrays).unwrap()).unwrap();
+    assert_eq!(value, control);
+
+    // a Value becomes itself
+    assert_eq!(value::to_value(&control).unwrap(), control);
+
+    assert!(value::to_value(&u64::max_value()).is_err());
+}
+
+#[test]
+fn value_index_test(){
+    use serde_fressian::value::PathSegment;
+
+    let mut inner = BTreeMap::new();
+    inner.insert(Value::KEY(KEY::namespaced("user".to_string(), "name".to_string())), Value::from("ann".to_string()));
+    inner.insert(Value::KEY(KEY::simple("tags".to_string())), Value::LIST(vec![Value::INT(1), Value::INT(2)]));
+    inner.insert(Value::STRING("s".to_string()), Value::BOOL(true));
+    inner.insert(Value::INT(7), Value::STRING("seven".to_string()));
+    let set: BTreeSet<Value> = vec![Value::INT(1), Value::INT(2)].into_iter().collect();
+    inner.insert(Value::STRING("set".to_string()), Value::from(set));
+    let mut outer = BTreeMap::new();
+    outer.insert(Value::STRING("user".to_string()), Value::MAP(inner));
+    let v = Value::MAP(outer);
+
+    assert_eq!(v["user"]["user/name"], Value::STRING("ann".to_string()));
+    assert_eq!(v["user"][":user/name"], Value::STRING("ann".to_string()));
+    assert_eq!(v["user"]["tags"][1], Value::INT(2));
+    assert_eq!(v["user"]["s"], Value::BOOL(true));
+    assert_eq!(v["user"][&Value::INT(7)], Value::STRING("seven".to_string()));
+    assert_eq!(v["user"]["tags"][5], Value::NULL);
+    assert_eq!(v["nope"]["nope"], Value::NULL);
+
+    assert_eq!(v.get("user").and_then(|u| u.get(KEY::simple("tags".to_string()))),
+               Some(&Value::LIST(vec![Value::INT(1), Value::INT(2)])));
+    assert_eq!(v.get(0), None);
+
+    let one = Value::INT(1);
+    let path = [PathSegment::from("user"), PathSegment::from("set"), PathSegment::from(&one)];
+    assert_eq!(v.get_in(&path), Some(&Value::INT(1)));
+    let path = [PathSegment::Key("user"), PathSegment::Key("tags"), PathSegment::Index(0)];
+    assert_eq!(v.get_in(&path), Some(&Value::INT(1)));
+    let path = [PathSegment::Key("user"), PathSegment::Key("missing"), PathSegment::Index(0)];
+    assert_eq!(v.get_in(&path), None);
+    assert_eq!(v.get_in(&[]), Some(&v));
+}
+
+#[test]
+fn value_accessors_test(){
+    use std::convert::TryFrom;
+    use serde_fressian::error::ErrorCode;
+
+    let key = Value::KEY(KEY::namespaced("user".to_string(), "name".to_string()));
+    assert!(key.is_keyword());
+    assert!(!key.is_symbol());
+    assert_eq!(key.type_name(), "KEY");
+    assert_eq!(key.as_keyword().and_then(|k| k.namespace()), Some("user"));
+    assert_eq!(key.as_str(), None);
+
+    assert_eq!(Value::STRING("s".to_string()).as_str(), Some("s"));
+    assert_eq!(Value::INT(3).as_i64(), Some(3));
+    assert_eq!(Value::FLOAT(OrderedFloat::from(1.5)).as_f64(), Some(1.5));
+    assert!(Value::INT(3).is_number());
+    assert!(Value::NULL.is_null());
+    assert!(Value::IntArray(IntArray::from_vec(vec![1])).is_typed_array());
+    assert_eq!(Value::INST(INST::from_millis(5)).as_inst(), Some(&INST::from_millis(5)));
+
+    let mut list = Value::LIST(vec![Value::INT(1)]);
+    list.as_list_mut().unwrap().push(Value::INT(2));
+    assert_eq!(list.as_list().map(|l| l.len()), Some(2));
+    assert!(list.as_map().is_none());
+
+    assert_eq!(i64::try_from(Value::INT(-4)).unwrap(), -4);
+    assert_eq!(u8::try_from(Value::INT(255)).unwrap(), 255);
+    assert_eq!(f64::try_from(Value::DOUBLE(OrderedFloat::from(2.5))).unwrap(), 2.5);
+    assert_eq!(String::try_from(Value::STRING("x".to_string())).unwrap(), "x");
+    assert_eq!(KEY::try_from(key.clone()).unwrap(), KEY::namespaced("user".to_string(), "name".to_string()));
+
+    let err = String::try_from(key).unwrap_err();
+    match err.err.code {
+        ErrorCode::UnexpectedValueType(expected, found) => {
+            assert_eq!((expected, found), ("STRING", "KEY"));
+        }
+        _ => panic!("wrong error code")
+    }
+    assert_eq!(err.err.code.to_string(), "expected STRING, found KEY");
+    assert_eq!(u8::try_from(Value::INT(256)).unwrap_err().err.code.to_string(), "expected u8, found INT");
+}
+
+#[test]
+fn fress_macro_test(){
+    let n = 42;
+    let name = "ann";
+    let v = fress!({:user/name (name), :tags #{:a :b}, :n (n + 1), "xs" [1 -2.5 nil true "s"], :empty {}});
+
+    let mut control = BTreeMap::new();
+    control.insert(Value::KEY(KEY::namespaced("user".to_string(), "name".to_string())), Value::from("ann"));
+    let tags: BTreeSet<Value> = vec![Value::KEY(KEY::simple("a".to_string())), Value::KEY(KEY::simple("b".to_string()))].into_iter().collect();
+    control.insert(Value::KEY(KEY::simple("tags".to_string())), Value::from(tags));
+    control.insert(Value::KEY(KEY::simple("n".to_string())), Value::INT(43));
+    control.insert(Value::from("xs"), Value::LIST(vec![Value::INT(1),
+                                                       Value::DOUBLE(OrderedFloat::from(-2.5)),
+                                                       Value::NULL,
+                                                       Value::BOOL(true),
+                                                       Value::from("s")]));
+    control.insert(Value::KEY(KEY::simple("empty".to_string())), Value::MAP(BTreeMap::new()));
+    assert_eq!(v, Value::MAP(control));
+
+    assert_eq!(fress!(nil), Value::NULL);
+    assert_eq!(fress!(-1), Value::INT(-1));
+    assert_eq!(fress!(:a/b), Value::KEY(KEY::namespaced("a".to_string(), "b".to_string())));
+    assert_eq!(fress!([]), Value::LIST(vec![]));
+    assert_eq!(fress!(#{1, 1, 2}).as_set().map(|s| s.len()), Some(2));
+    assert_eq!(fress!(INST::from_millis(5)), Value::INST(INST::from_millis(5)));
+}
+
+#[test]
+fn edn_display_test(){
+    let v = fress!({:user/name "a \"q\"\n", :n 1, :d 2.0, :tags #{:a :b}, "xs" [nil true -1.5]});
+    assert_eq!(v.to_string(), r#"{"xs" [nil true -1.5], :d 2.0, :n 1, :tags #{:a :b}, :user/name "a \"q\"\n"}"#);
+
+    assert_eq!(Value::SYM(SYM::namespaced("clojure.core".to_string(), "map".to_string())).to_string(), "clojure.core/map");
+    assert_eq!(Value::INST(INST::from_millis(1535328836181)).to_string(), r#"#inst "2018-08-27T00:13:56.181-00:00""#);
+    assert_eq!(Value::INST(INST::from_millis(-1)).to_string(), r#"#inst "1969-12-31T23:59:59.999-00:00""#);
+    let u = UUID::from_bytes(&[0x55, 0x0e, 0x84, 0x00, 0xe2, 0x9b, 0x41, 0xd4, 0xa7, 0x16, 0x44, 0x66, 0x55, 0x44, 0x00, 0x00]).unwrap();
+    assert_eq!(Value::UUID(u).to_string(), r#"#uuid "550e8400-e29b-41d4-a716-446655440000""#);
+    assert_eq!(Value::DOUBLE(OrderedFloat::from(std::f64::NAN)).to_string(), "##NaN");
+    assert_eq!(Value::FLOAT(OrderedFloat::from(1.0f32)).to_string(), "1.0");
+    assert_eq!(Value::IntArray(IntArray::from_vec(vec![1, 2])).to_string(), "#ints [1 2]");
+    assert_eq!(Value::from(vec![1u8, 2].as_slice()).to_string(), "#bytes [1 2]");
+    assert_eq!(Value::REGEX(REGEX::from_str("a+").unwrap()).to_string(), r#"#regex "a+""#);
+
+    let long: Vec<Value> = (0..30).map(|i| Value::INT(i * 1000)).collect();
+    let v = fress!({:short [1 2], :long (long)});
+    let pretty = format!("{:#}", v);
+    let lines: Vec<&str> = pretty.lines().collect();
+    assert_eq!(lines[0], "{:long [0");
+    assert_eq!(lines[1], "        1000");
+    assert_eq!(lines[lines.len() - 1], " :short [1 2]}");
+    // compact values print the same either way
+    assert_eq!(format!("{:#}", fress!([1 2])), "[1 2]");
+}
+
+#[test]
+fn from_edn_test(){
+    use serde_fressian::error::ErrorCode;
+
+    let v = value::from_edn(r#"
+        ; a comment
+        {:user/name "x\ty", :tags #{:a :b}, :n 1, "xs" [1 -2.5 nil true (foo/bar baz)],
+         :c \c :nl \newline :big 12N :dec 1.5M #_ :ignored #_ 42
+         :nan ##NaN}"#).unwrap();
+    assert_eq!(v, fress!({:user/name "x\ty", :tags #{:a :b}, :n 1,
+                          "xs" [1 -2.5 nil true [(Value::SYM(SYM::namespaced("foo".to_string(), "bar".to_string())))
+                                                 (Value::SYM(SYM::simple("baz".to_string())))]],
+                          :c "c" :nl "\n" :big 12 :dec 1.5
+                          :nan (std::f64::NAN)}));
+
+    assert_eq!(value::from_edn(r#"#inst "2018-08-27T00:13:56.181-00:00""#).unwrap(), Value::INST(INST::from_millis(1535328836181)));
+    assert_eq!(value::from_edn(r#"#inst "2018-08-27T02:13:56.181+02:00""#).unwrap(), Value::INST(INST::from_millis(1535328836181)));
+    assert_eq!(value::from_edn(r#"#inst "1970-01-02""#).unwrap(), Value::INST(INST::from_millis(86_400_000)));
+    assert_eq!(value::from_edn(r#"#inst "1969-12-31T23:59:59.999Z""#).unwrap(), Value::INST(INST::from_millis(-1)));
+    let u = UUID::from_bytes(&[0x55, 0x0e, 0x84, 0x00, 0xe2, 0x9b, 0x41, 0xd4, 0xa7, 0x16, 0x44, 0x66, 0x55, 0x44, 0x00, 0x00]).unwrap();
+    assert_eq!(value::from_edn(r#"#uuid "550e8400-e29b-41d4-a716-446655440000""#).unwrap(), Value::UUID(u));
+
+    // Display output reads back, except FLOATs which print like DOUBLEs
+    let printed = fress!({:ints (IntArray::from_vec(vec![1, 2])),
+                          :bytes (vec![0u8, 255].as_slice()),
+                          :re (REGEX::from_str("a+").unwrap()),
+                          :when (INST::from_millis(-12345)),
+                          :s "quote\" \u{1}",
+                          :x [#{}]});
+    assert_eq!(value::from_edn(&printed.to_string()).unwrap(), printed);
+    assert_eq!(value::from_edn(&format!("{:#}", printed)).unwrap(), printed);
+
+    // encodes like anything else
+    let bytes = ser::to_vec(&value::from_edn("[:a 1]").unwrap()).unwrap();
+    let back: Value = de::from_bytes(&bytes).unwrap();
+    assert_eq!(back, fress!([:a 1]));
+
+    let err = value::from_edn("{:a 1 :b}").unwrap_err();
+    assert_eq!(err.err.code.to_string(), "InvalidEdn: map literal needs an even number of forms");
+    let err = value::from_edn("[1 2").unwrap_err();
+    assert_eq!(err.err.position, 4);
+    let err = value::from_edn("[1 #foo 2]").unwrap_err();
+    assert_eq!(err.err.position, 4);
+    match value::from_edn("1 2").unwrap_err().err.code {
+        ErrorCode::TrailingBytes => {}
+        _ => panic!("expected TrailingBytes")
+    }
+    assert!(value::from_edn("99999999999999999999N").is_err());
+    // M decimals are read only when a double holds them without rounding
+    assert_eq!(value::from_edn("[0.1M -2.50M 1e3M 0M 1.5e-7M]").unwrap(),
+               Value::LIST(vec![Value::from(0.1), Value::from(-2.5), Value::from(1000.0), Value::from(0.0), Value::from(1.5e-7)]));
+    for rounded in &["0.1000000000000000001M", "12345678901234567890.5M", "1e400M", "1e-400M"] {
+        let err = value::from_edn(rounded).unwrap_err();
+        assert_eq!(err.err.code.to_string(), "InvalidEdn: invalid number", "{}", rounded);
+    }
+    // nesting is limited rather than exhausting the stack
+    let err = value::from_edn(&"[".repeat(200000)).unwrap_err();
+    assert_eq!((err.err.code.to_string().as_str(), err.err.position), ("InvalidEdn: nesting too deep", 512));
+    let err = value::from_edn(&"#{".repeat(200000)).unwrap_err();
+    assert_eq!(err.err.code.to_string(), "InvalidEdn: nesting too deep");
+    let deep = format!("{}{}", "[".repeat(500), "]".repeat(500));
+    assert!(value::from_edn(&deep).is_ok());
+    assert!(value::from_edn(r#"#inst "2018-13-01""#).is_err());
+    assert!(value::from_edn(r#"#inst "2018-02-31""#).is_err());
+    assert!(value::from_edn(r#"#inst "2018-04-31""#).is_err());
+    assert!(value::from_edn(r#"#inst "1900-02-29""#).is_err());
+    assert!(value::from_edn(r#"#inst "2000-02-29""#).is_ok());
+    assert!(value::from_edn(r#"#inst "2016-02-29""#).is_ok());
+}